@@ -12,11 +12,28 @@ class RustModel:
     def __getattr__(self, item):
         return self.__pydantic_model_data__.get_attr(item)
 
-    def model_dump(self):
-        return self.__pydantic_model_data__.model_dump()
+    def __setattr__(self, key, value):
+        self.__pydantic_model_data__.set_attr(key, value)
 
-    def model_dump_json(self):
-        return self.__pydantic_model_data__.model_dump_json()
+    def __eq__(self, other):
+        if type(other) is not type(self):
+            return NotImplemented
+        return self.__pydantic_model_data__ == other.__pydantic_model_data__
+
+    def __hash__(self):
+        return hash(self.__pydantic_model_data__)
+
+    def __repr__(self):
+        return repr(self.__pydantic_model_data__)
+
+    def model_dump(self, **kwargs):
+        return self.__pydantic_model_data__.model_dump(**kwargs)
+
+    def model_dump_json(self, **kwargs):
+        return self.__pydantic_model_data__.model_dump_json(**kwargs)
+
+    def model_copy(self, **kwargs):
+        return self.__pydantic_model_data__.model_copy(**kwargs)
 
 
 class MyModel(RustModel):
@@ -1,22 +1,42 @@
-use jiter::JiterError;
-use pyo3::exceptions::{PyTypeError, PyValueError};
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+use ahash::AHashSet;
+use jiter::{Jiter, JiterError, JiterErrorType, JsonErrorType, LinePosition};
+use pyo3::exceptions::{PyAssertionError, PyIndexError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
+use pyo3::types::{IntoPyDict, PyDict, PyList};
 use pyo3::DowncastError;
+use serde::ser::SerializeMap;
+use serde::Serialize;
+
+use crate::field::{get_as_opt, RawData};
+
+/// pydantic's documentation for each error type, the `url` of errors from `ValidationError.errors()`
+const ERROR_URL: &str = "https://errors.pydantic.dev/2.8/v/";
 
 #[pyclass(extends=PyValueError)]
 #[derive(Debug)]
 pub struct ValidationError {
     errors: Vec<LineError>,
+    // the model name or type being validated, used in `__str__`
+    title: String,
 }
 
 impl ValidationError {
-    pub fn new(errors: Vec<LineError>) -> Self {
-        Self { errors }
+    pub fn new(errors: Vec<LineError>, title: String) -> Self {
+        Self { errors, title }
     }
 
-    pub fn new_err(py: Python, errors: Vec<LineError>) -> PyResult<PyErr> {
-        let slf = Self::new(errors);
+    pub fn new_err(
+        py: Python,
+        mut errors: Vec<LineError>,
+        title: &str,
+        options: ErrorOptions,
+    ) -> PyResult<PyErr> {
+        options.apply(&mut errors);
+        let slf = Self::new(errors, title.to_string());
         let py_val_error = Py::new(py, slf)?;
         Ok(PyErr::from_value_bound(
             py_val_error.into_bound(py).into_any(),
@@ -24,18 +44,113 @@ impl ValidationError {
     }
 }
 
+/// How the errors from one validation are presented, from the schema's "sort_errors" and "dedup_errors"
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorOptions {
+    // errors are ordered by location then type, so the output doesn't depend on the order of keys in the input.
+    // The sort is stable, errors of the same type at the same location keep their order
+    pub sort: bool,
+    // errors with the same type and location as an earlier error are dropped, e.g. from each choice of a union
+    pub dedup: bool,
+}
+
+impl ErrorOptions {
+    pub fn new(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
+        Ok(Self {
+            sort: get_as_opt(schema, "sort_errors")?.unwrap_or(true),
+            dedup: get_as_opt(schema, "dedup_errors")?.unwrap_or(false),
+        })
+    }
+
+    fn apply(self, errors: &mut Vec<LineError>) {
+        if self.dedup {
+            let mut seen = AHashSet::new();
+            errors.retain(|e| seen.insert((format!("{:?}", e.error_type), e.rev_loc.clone())));
+        }
+        if self.sort {
+            errors.sort_by(LineError::cmp_loc_type);
+        }
+    }
+}
+
+/// Error dicts like `ValidationError.errors()`, for APIs which return errors rather than raising them
+pub fn errors_list<'py>(
+    py: Python<'py>,
+    mut errors: Vec<LineError>,
+    options: ErrorOptions,
+) -> PyResult<Bound<'py, PyList>> {
+    options.apply(&mut errors);
+    let dicts = errors.iter().map(|e| e.as_dict(py, true, true));
+    Ok(PyList::new_bound(py, dicts.collect::<PyResult<Vec<_>>>()?))
+}
+
 #[pymethods]
 impl ValidationError {
     fn error_count(&self) -> usize {
         self.errors.len()
     }
 
-    fn errors<'py>(&self, py: Python<'py>) -> Bound<'py, PyList> {
-        PyList::new_bound(py, self.errors.iter().map(|e| e.to_object(py)))
+    /// Like pydantic, `url` and `ctx` are omitted for errors without them
+    #[pyo3(signature = (*, include_url = true, include_context = true))]
+    fn errors<'py>(
+        &self,
+        py: Python<'py>,
+        include_url: bool,
+        include_context: bool,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let dicts = self
+            .errors
+            .iter()
+            .map(|e| e.as_dict(py, include_url, include_context));
+        Ok(PyList::new_bound(py, dicts.collect::<PyResult<Vec<_>>>()?))
+    }
+
+    fn __len__(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// A single error dict without building the whole `errors()` list, negative indexes count from the end
+    fn __getitem__(&self, py: Python, index: isize) -> PyResult<PyObject> {
+        let len = self.errors.len() as isize;
+        let index = if index < 0 { index + len } else { index };
+        match usize::try_from(index).ok().and_then(|i| self.errors.get(i)) {
+            Some(error) => error.as_dict(py, true, true),
+            None => Err(PyIndexError::new_err("list index out of range")),
+        }
     }
 
-    fn __str__(&self) -> String {
-        format!("{:#?}", self.errors)
+    #[pyo3(signature = (*, indent = None))]
+    fn json(&self, py: Python, indent: Option<usize>) -> PyResult<String> {
+        let errors: Vec<LineErrorSerializer> = self
+            .errors
+            .iter()
+            .map(|error| LineErrorSerializer { py, error })
+            .collect();
+        let result = match indent {
+            Some(indent) => {
+                let indent = vec![b' '; indent];
+                let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+                let mut writer = Vec::new();
+                let mut ser = serde_json::Serializer::with_formatter(&mut writer, formatter);
+                errors.serialize(&mut ser).map(|_| writer)
+            }
+            None => serde_json::to_vec(&errors),
+        };
+        let json = result.map_err(|e| PyValueError::new_err(e.to_string()))?;
+        // serde_json only writes valid UTF-8
+        Ok(String::from_utf8(json).unwrap())
+    }
+
+    /// Matches pydantic's format, a header line then each error's location and message
+    fn __str__(&self, py: Python) -> String {
+        let count = self.errors.len();
+        let plural = if count == 1 { "" } else { "s" };
+        let mut s = format!("{count} validation error{plural} for {}", self.title);
+        for error in &self.errors {
+            s.push('\n');
+            s.push_str(&error.display(py));
+        }
+        s
     }
 }
 
@@ -44,6 +159,8 @@ pub struct LineError {
     error_type: ErrorType,
     // reversed so that adding an "outer" location item is pushing, it's reversed before showing to the user
     rev_loc: Vec<LocItem>,
+    // python input is held by reference as `RawData::Py` rather than converted
+    input: Option<RawData>,
 }
 
 impl LineError {
@@ -51,6 +168,7 @@ impl LineError {
         Self {
             error_type,
             rev_loc: vec![loc.into()],
+            input: None,
         }
     }
 
@@ -58,24 +176,102 @@ impl LineError {
         Self {
             error_type,
             rev_loc: vec![],
+            input: None,
+        }
+    }
+
+    fn cmp_loc_type(&self, other: &Self) -> Ordering {
+        let loc = self.rev_loc.iter().rev();
+        loc.cmp(other.rev_loc.iter().rev())
+            .then_with(|| self.error_type.to_str().cmp(other.error_type.to_str()))
+    }
+
+    pub fn with_input(mut self, input: RawData) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// The location on its own line if there is one, then the indented message and details
+    fn display(&self, py: Python) -> String {
+        let loc: Vec<String> = self.rev_loc.iter().rev().map(ToString::to_string).collect();
+        let mut s = match loc.is_empty() {
+            true => String::new(),
+            false => format!("{}\n", loc.join(".")),
+        };
+        s.push_str(&format!(
+            "  {} [type={}",
+            self.error_type.message(),
+            self.error_type.to_str()
+        ));
+        if let Some(input) = self.input.as_ref().and_then(|input| input.to_py(py).ok()) {
+            let input = input.bind(py);
+            let input_value = input.repr().map(|r| r.to_string()).unwrap_or_default();
+            let input_type = input
+                .get_type()
+                .name()
+                .map(|n| n.to_string())
+                .unwrap_or_default();
+            s.push_str(&format!(
+                ", input_value={input_value}, input_type={input_type}"
+            ));
+        }
+        s.push(']');
+        s
+    }
+}
+
+struct LineErrorSerializer<'py> {
+    py: Python<'py>,
+    error: &'py LineError,
+}
+
+impl Serialize for LineErrorSerializer<'_> {
+    fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let error = self.error;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", error.error_type.to_str())?;
+        let loc: Vec<&LocItem> = error.rev_loc.iter().rev().collect();
+        map.serialize_entry("loc", &loc)?;
+        map.serialize_entry("msg", &error.error_type.message())?;
+        if let Some(input) = &error.input {
+            // inputs which can't be serialized, e.g. arbitrary python objects, are represented by their repr,
+            // the input is written compactly even with an indent
+            match serde_json::value::to_raw_value(input) {
+                Ok(raw) => map.serialize_entry("input", &raw)?,
+                Err(_) => map.serialize_entry("input", &input_repr(self.py, input))?,
+            }
         }
+        map.end()
     }
 }
 
-impl ToPyObject for LineError {
-    fn to_object(&self, py: Python) -> PyObject {
+fn input_repr(py: Python, input: &RawData) -> String {
+    match input.to_py(py).and_then(|input| input.bind(py).repr()) {
+        Ok(repr) => repr.to_string(),
+        Err(_) => "<unknown>".to_string(),
+    }
+}
+
+impl LineError {
+    fn as_dict(&self, py: Python, include_url: bool, include_context: bool) -> PyResult<PyObject> {
         let loc = self.rev_loc.iter().rev().map(|li| match li {
-            LocItem::S(s) => s.to_object(py),
+            LocItem::S(s) => s.as_ref().to_object(py),
             LocItem::I(i) => i.to_object(py),
         });
         let error_dict = PyDict::new_bound(py);
-        error_dict
-            .set_item("error_type", self.error_type.to_str())
-            .unwrap();
-        error_dict
-            .set_item("location", PyList::new_bound(py, loc))
-            .unwrap();
-        error_dict.into()
+        error_dict.set_item("error_type", self.error_type.to_str())?;
+        error_dict.set_item("location", PyList::new_bound(py, loc))?;
+        error_dict.set_item("msg", self.error_type.message())?;
+        if let Some(input) = &self.input {
+            error_dict.set_item("input", input.to_py(py)?)?;
+        }
+        if let Some(context) = self.error_type.context(py)?.filter(|_| include_context) {
+            error_dict.set_item("ctx", context)?;
+        }
+        if let Some(url) = self.error_type.url().filter(|_| include_url) {
+            error_dict.set_item("url", url)?;
+        }
+        Ok(error_dict.into())
     }
 }
 
@@ -83,34 +279,437 @@ impl ToPyObject for LineError {
 pub enum ErrorType {
     MissingField,
     StringType,
-    StringUnicode,
+    StringUnicode {
+        error: String,
+    },
+    StringTooShort {
+        min: usize,
+    },
+    StringTooLong {
+        max: usize,
+    },
     IntType,
     IntTooBig,
+    IntFromFloat,
+    IntParsing,
+    GreaterThan {
+        limit: i64,
+    },
+    GreaterThanEqual {
+        limit: i64,
+    },
+    LessThan {
+        limit: i64,
+    },
+    LessThanEqual {
+        limit: i64,
+    },
+    MultipleOf {
+        multiple_of: i64,
+    },
+    FloatType,
+    FloatGreaterThan {
+        limit: f64,
+    },
+    FloatGreaterThanEqual {
+        limit: f64,
+    },
+    FloatLessThan {
+        limit: f64,
+    },
+    FloatLessThanEqual {
+        limit: f64,
+    },
+    BoolType,
+    BytesType,
+    ListType,
+    TooShort {
+        min: usize,
+    },
+    TooLong {
+        max: usize,
+    },
+    SetType,
+    TupleType,
+    TupleLength {
+        expected: usize,
+    },
     DictType,
-    JsonError(String),
+    ExtraForbidden,
+    DuplicateKey,
+    FrozenInstance,
+    UuidType,
+    UuidParsing {
+        error: String,
+    },
+    DatetimeType,
+    DatetimeParsing {
+        error: String,
+    },
+    DecimalType,
+    DecimalParsing,
+    DecimalMaxDigits {
+        max_digits: usize,
+    },
+    DecimalMaxPlaces {
+        decimal_places: usize,
+    },
+    LiteralError {
+        expected: String,
+    },
+    EnumError {
+        expected: String,
+    },
+    UnionTagInvalid {
+        discriminator: String,
+        tag: String,
+        expected_tags: String,
+    },
+    UnionTagNotFound {
+        discriminator: String,
+    },
+    ValueError {
+        error: String,
+    },
+    AssertionError {
+        error: String,
+    },
+    RecursionLimit,
+    JsonTooDeep {
+        max_depth: u16,
+    },
+    JsonType,
+    /// `position` is set by `ValError::with_json_position` once the whole document is known
+    JsonInvalid {
+        error: String,
+        index: usize,
+        position: Option<LinePosition>,
+    },
 }
 
 impl ErrorType {
-    fn to_str(&self) -> String {
-        format!("{self:?}")
+    /// The error `type` identifier used by pydantic
+    fn to_str(&self) -> &'static str {
+        match self {
+            Self::MissingField => "missing",
+            Self::StringType => "string_type",
+            Self::StringUnicode { .. } => "string_unicode",
+            Self::StringTooShort { .. } => "string_too_short",
+            Self::StringTooLong { .. } => "string_too_long",
+            Self::IntType => "int_type",
+            Self::IntTooBig => "int_parsing_size",
+            Self::IntFromFloat => "int_from_float",
+            Self::IntParsing => "int_parsing",
+            Self::GreaterThan { .. } => "greater_than",
+            Self::GreaterThanEqual { .. } => "greater_than_equal",
+            Self::LessThan { .. } => "less_than",
+            Self::LessThanEqual { .. } => "less_than_equal",
+            Self::MultipleOf { .. } => "multiple_of",
+            Self::FloatType => "float_type",
+            Self::FloatGreaterThan { .. } => "greater_than",
+            Self::FloatGreaterThanEqual { .. } => "greater_than_equal",
+            Self::FloatLessThan { .. } => "less_than",
+            Self::FloatLessThanEqual { .. } => "less_than_equal",
+            Self::BoolType => "bool_type",
+            Self::BytesType => "bytes_type",
+            Self::ListType => "list_type",
+            Self::TooShort { .. } => "too_short",
+            Self::TooLong { .. } => "too_long",
+            Self::SetType => "set_type",
+            Self::TupleType => "tuple_type",
+            Self::TupleLength { .. } => "tuple_length",
+            Self::DictType => "dict_type",
+            Self::ExtraForbidden => "extra_forbidden",
+            Self::DuplicateKey => "duplicate_key",
+            Self::FrozenInstance => "frozen_instance",
+            Self::UuidType => "uuid_type",
+            Self::UuidParsing { .. } => "uuid_parsing",
+            Self::DatetimeType => "datetime_type",
+            Self::DatetimeParsing { .. } => "datetime_parsing",
+            Self::DecimalType => "decimal_type",
+            Self::DecimalParsing => "decimal_parsing",
+            Self::DecimalMaxDigits { .. } => "decimal_max_digits",
+            Self::DecimalMaxPlaces { .. } => "decimal_max_places",
+            Self::LiteralError { .. } => "literal_error",
+            Self::UnionTagInvalid { .. } => "union_tag_invalid",
+            Self::UnionTagNotFound { .. } => "union_tag_not_found",
+            Self::EnumError { .. } => "enum",
+            Self::ValueError { .. } => "value_error",
+            Self::AssertionError { .. } => "assertion_error",
+            Self::RecursionLimit => "recursion_limit",
+            Self::JsonTooDeep { .. } => "json_too_deep",
+            Self::JsonType => "json_type",
+            Self::JsonInvalid { .. } => "json_invalid",
+        }
+    }
+
+    /// Values used in the message, keyed like pydantic's `ctx`
+    fn context<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let context = match self {
+            Self::StringTooShort { min } => [("min_length", min)].into_py_dict_bound(py),
+            Self::StringTooLong { max } => [("max_length", max)].into_py_dict_bound(py),
+            Self::TooShort { min } => {
+                let items = [
+                    ("field_type", "List".to_object(py)),
+                    ("min_length", min.to_object(py)),
+                ];
+                items.into_py_dict_bound(py)
+            }
+            Self::TooLong { max } => {
+                let items = [
+                    ("field_type", "List".to_object(py)),
+                    ("max_length", max.to_object(py)),
+                ];
+                items.into_py_dict_bound(py)
+            }
+            Self::GreaterThan { limit } => [("gt", limit)].into_py_dict_bound(py),
+            Self::GreaterThanEqual { limit } => [("ge", limit)].into_py_dict_bound(py),
+            Self::LessThan { limit } => [("lt", limit)].into_py_dict_bound(py),
+            Self::LessThanEqual { limit } => [("le", limit)].into_py_dict_bound(py),
+            Self::MultipleOf { multiple_of } => {
+                [("multiple_of", multiple_of)].into_py_dict_bound(py)
+            }
+            Self::FloatGreaterThan { limit } => [("gt", limit)].into_py_dict_bound(py),
+            Self::FloatGreaterThanEqual { limit } => [("ge", limit)].into_py_dict_bound(py),
+            Self::FloatLessThan { limit } => [("lt", limit)].into_py_dict_bound(py),
+            Self::FloatLessThanEqual { limit } => [("le", limit)].into_py_dict_bound(py),
+            Self::TupleLength { expected } => [("expected", expected)].into_py_dict_bound(py),
+            Self::DecimalMaxDigits { max_digits } => {
+                [("max_digits", max_digits)].into_py_dict_bound(py)
+            }
+            Self::DecimalMaxPlaces { decimal_places } => {
+                [("decimal_places", decimal_places)].into_py_dict_bound(py)
+            }
+            Self::LiteralError { expected } | Self::EnumError { expected } => {
+                [("expected", expected)].into_py_dict_bound(py)
+            }
+            Self::UnionTagInvalid {
+                discriminator,
+                tag,
+                expected_tags,
+            } => [
+                ("discriminator", discriminator),
+                ("tag", tag),
+                ("expected_tags", expected_tags),
+            ]
+            .into_py_dict_bound(py),
+            Self::UnionTagNotFound { discriminator } => {
+                [("discriminator", discriminator)].into_py_dict_bound(py)
+            }
+            Self::StringUnicode { error }
+            | Self::UuidParsing { error }
+            | Self::DatetimeParsing { error }
+            | Self::ValueError { error }
+            | Self::AssertionError { error } => [("error", error)].into_py_dict_bound(py),
+            Self::JsonInvalid {
+                error,
+                index,
+                position,
+            } => {
+                let context = [
+                    (
+                        "error",
+                        json_invalid_detail(error, *index, position).to_object(py),
+                    ),
+                    ("index", index.to_object(py)),
+                ]
+                .into_py_dict_bound(py);
+                if let Some(position) = position {
+                    context.set_item("line", position.line)?;
+                    context.set_item("column", position.column)?;
+                }
+                context
+            }
+            Self::JsonTooDeep { max_depth } => [("max_depth", max_depth)].into_py_dict_bound(py),
+            _ => return Ok(None),
+        };
+        Ok(Some(context))
+    }
+
+    /// Error types which aren't pydantic's have no documentation to link to
+    fn url(&self) -> Option<String> {
+        match self {
+            Self::DuplicateKey | Self::JsonTooDeep { .. } => None,
+            _ => Some(format!("{ERROR_URL}{}", self.to_str())),
+        }
+    }
+
+    /// A human readable description of the error, matching pydantic's messages
+    pub fn message(&self) -> String {
+        match self {
+            Self::MissingField => "Field required".to_string(),
+            Self::StringType => "Input should be a valid string".to_string(),
+            Self::StringUnicode { error } => {
+                format!("Input should be a valid string, unable to parse raw data as a unicode string, {error}")
+            }
+            Self::StringTooShort { min } => {
+                format!(
+                    "String should have at least {min} {}",
+                    plural(*min, "character", "characters")
+                )
+            }
+            Self::StringTooLong { max } => {
+                format!(
+                    "String should have at most {max} {}",
+                    plural(*max, "character", "characters")
+                )
+            }
+            Self::IntType => "Input should be a valid integer".to_string(),
+            Self::IntTooBig => {
+                "Unable to parse input string as an integer, exceeded maximum size".to_string()
+            }
+            Self::IntFromFloat => {
+                "Input should be a valid integer, got a number with a fractional part".to_string()
+            }
+            Self::IntParsing => {
+                "Input should be a valid integer, unable to parse string as an integer".to_string()
+            }
+            Self::GreaterThan { limit } => format!("Input should be greater than {limit}"),
+            Self::GreaterThanEqual { limit } => {
+                format!("Input should be greater than or equal to {limit}")
+            }
+            Self::LessThan { limit } => format!("Input should be less than {limit}"),
+            Self::LessThanEqual { limit } => {
+                format!("Input should be less than or equal to {limit}")
+            }
+            Self::MultipleOf { multiple_of } => {
+                format!("Input should be a multiple of {multiple_of}")
+            }
+            Self::FloatType => "Input should be a valid number".to_string(),
+            Self::FloatGreaterThan { limit } => format!("Input should be greater than {limit}"),
+            Self::FloatGreaterThanEqual { limit } => {
+                format!("Input should be greater than or equal to {limit}")
+            }
+            Self::FloatLessThan { limit } => format!("Input should be less than {limit}"),
+            Self::FloatLessThanEqual { limit } => {
+                format!("Input should be less than or equal to {limit}")
+            }
+            Self::BoolType => "Input should be a valid boolean".to_string(),
+            Self::BytesType => "Input should be a valid bytes".to_string(),
+            Self::ListType => "Input should be a valid list".to_string(),
+            Self::TooShort { min } => {
+                format!(
+                    "List should have at least {min} {} after validation",
+                    plural(*min, "item", "items")
+                )
+            }
+            Self::TooLong { max } => {
+                format!(
+                    "List should have at most {max} {} after validation",
+                    plural(*max, "item", "items")
+                )
+            }
+            Self::SetType => "Input should be a valid set".to_string(),
+            Self::TupleType => "Input should be a valid tuple".to_string(),
+            Self::TupleLength { expected } => format!(
+                "Tuple should have {expected} {}",
+                plural(*expected, "item", "items")
+            ),
+            Self::DictType => "Input should be a valid dictionary".to_string(),
+            Self::ExtraForbidden => "Extra inputs are not permitted".to_string(),
+            Self::DuplicateKey => "Duplicate key in JSON object".to_string(),
+            Self::FrozenInstance => "Instance is frozen".to_string(),
+            Self::UuidType => "UUID input should be a string or UUID object".to_string(),
+            Self::UuidParsing { error } => format!("Input should be a valid UUID, {error}"),
+            Self::DatetimeType => "Input should be a valid datetime".to_string(),
+            Self::DatetimeParsing { error } => format!("Input should be a valid datetime, {error}"),
+            Self::DecimalType => {
+                "Decimal input should be an integer, float, string or Decimal object".to_string()
+            }
+            Self::DecimalParsing => "Input should be a valid decimal".to_string(),
+            Self::DecimalMaxDigits { max_digits } => format!(
+                "Decimal input should have no more than {max_digits} {} in total",
+                plural(*max_digits, "digit", "digits")
+            ),
+            Self::DecimalMaxPlaces { decimal_places } => format!(
+                "Decimal input should have no more than {decimal_places} {}",
+                plural(*decimal_places, "decimal place", "decimal places")
+            ),
+            Self::LiteralError { expected } | Self::EnumError { expected } => {
+                format!("Input should be {expected}")
+            }
+            Self::UnionTagInvalid {
+                discriminator,
+                tag,
+                expected_tags,
+            } => format!(
+                "Input tag '{tag}' found using {discriminator} does not match any of the expected tags: {expected_tags}"
+            ),
+            Self::UnionTagNotFound { discriminator } => {
+                format!("Unable to extract tag using discriminator {discriminator}")
+            }
+            Self::ValueError { error } => format!("Value error, {error}"),
+            Self::AssertionError { error } => format!("Assertion failed, {error}"),
+            Self::RecursionLimit => "Input is nested too deeply".to_string(),
+            Self::JsonTooDeep { max_depth } => {
+                format!("JSON input should be nested at most {max_depth} levels deep")
+            }
+            Self::JsonType => "JSON input should be string, bytes or bytearray".to_string(),
+            Self::JsonInvalid {
+                error,
+                index,
+                position,
+            } => format!("Invalid JSON: {}", json_invalid_detail(error, *index, position)),
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+/// The jiter error with its line and column if known, otherwise its byte index
+fn json_invalid_detail(error: &str, index: usize, position: &Option<LinePosition>) -> String {
+    match position {
+        Some(position) => format!("{error} at {position}"),
+        None => format!("{error} at index {index}"),
+    }
+}
+
+fn plural(count: usize, one: &'static str, many: &'static str) -> &'static str {
+    match count {
+        1 => one,
+        _ => many,
+    }
+}
+
+// keys sort before indexes, only relevant to ordering errors
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum LocItem {
-    S(String),
+    // field names are shared with `FieldInfo`, so locating an error at a field doesn't allocate
+    S(Arc<str>),
     I(i64),
 }
 
+impl Serialize for LocItem {
+    fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            LocItem::S(s) => serializer.serialize_str(s),
+            LocItem::I(i) => serializer.serialize_i64(*i),
+        }
+    }
+}
+
+impl fmt::Display for LocItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LocItem::S(s) => write!(f, "{s}"),
+            LocItem::I(i) => write!(f, "{i}"),
+        }
+    }
+}
+
 impl From<String> for LocItem {
     fn from(s: String) -> Self {
-        LocItem::S(s)
+        LocItem::S(s.into())
     }
 }
 
 impl From<&'_ str> for LocItem {
     fn from(s: &'_ str) -> Self {
-        LocItem::S(s.to_owned())
+        LocItem::S(s.into())
+    }
+}
+
+impl From<&'_ Arc<str>> for LocItem {
+    fn from(s: &'_ Arc<str>) -> Self {
+        LocItem::S(s.clone())
     }
 }
 
@@ -139,10 +738,71 @@ impl ValError {
         }
     }
 
-    pub fn to_py_err(self, py: Python) -> PyErr {
+    /// Attach the input to errors without a location yet, nested errors keep their own input
+    fn with_input(self, get_input: impl FnOnce() -> Option<RawData>) -> Self {
+        match self {
+            ValError::LineErrors(mut errors) => {
+                let mut get_input = Some(get_input);
+                let mut input = None;
+                let without_input = errors
+                    .iter_mut()
+                    .filter(|e| e.rev_loc.is_empty() && e.input.is_none());
+                for error in without_input {
+                    if let Some(get_input) = get_input.take() {
+                        input = get_input();
+                    }
+                    error.input.clone_from(&input);
+                }
+                ValError::LineErrors(errors)
+            }
+            internal_error => internal_error,
+        }
+    }
+
+    /// Set the line and column of `json_invalid` errors from the document they're an index into
+    pub fn with_json_position(mut self, json_data: &[u8]) -> Self {
+        if let ValError::LineErrors(errors) = &mut self {
+            for error in errors.iter_mut() {
+                if let ErrorType::JsonInvalid {
+                    index, position, ..
+                } = &mut error.error_type
+                {
+                    position.get_or_insert_with(|| LinePosition::find(json_data, *index));
+                }
+            }
+        }
+        self
+    }
+
+    pub fn with_py_input(self, input: &Bound<'_, PyAny>) -> Self {
+        self.with_input(|| Some(RawData::Py(Arc::new(input.clone().unbind()))))
+    }
+
+    /// `start` points to the beginning of the input, which is only parsed if there are errors
+    pub fn with_json_input(self, mut start: Jiter) -> Self {
+        self.with_input(|| start.next_value().ok().map(RawData::from))
+    }
+
+    /// `ValueError` and `AssertionError` from user functions become line errors, others are raised as is
+    pub fn from_function_error(py: Python, py_err: PyErr) -> Self {
+        let error_type = if py_err.is_instance_of::<PyValueError>(py) {
+            ErrorType::ValueError {
+                error: py_err.value_bound(py).to_string(),
+            }
+        } else if py_err.is_instance_of::<PyAssertionError>(py) {
+            ErrorType::AssertionError {
+                error: py_err.value_bound(py).to_string(),
+            }
+        } else {
+            return Self::InternalError(py_err);
+        };
+        error_type.into()
+    }
+
+    pub fn into_py_err(self, py: Python, title: &str, options: ErrorOptions) -> PyErr {
         match self {
             ValError::LineErrors(errors) => {
-                ValidationError::new_err(py, errors).unwrap_or_else(|e| e)
+                ValidationError::new_err(py, errors, title, options).unwrap_or_else(|e| e)
             }
             ValError::InternalError(e) => e,
         }
@@ -181,9 +841,407 @@ impl From<ErrorType> for ValError {
     }
 }
 
+/// Invalid UTF-8 and lone surrogates in JSON strings are `string_unicode` errors, not `json_invalid`
 impl From<JiterError> for ValError {
     fn from(jiter_error: JiterError) -> Self {
-        let line_error = LineError::new(ErrorType::JsonError(jiter_error.to_string()));
-        Self::LineErrors(vec![line_error])
+        let error_type = match jiter_error.error_type {
+            JiterErrorType::JsonError(
+                JsonErrorType::InvalidUnicodeCodePoint
+                | JsonErrorType::LoneLeadingSurrogateInHexEscape,
+            ) => ErrorType::StringUnicode {
+                error: jiter_error.to_string(),
+            },
+            error_type => ErrorType::JsonInvalid {
+                error: error_type.to_string(),
+                index: jiter_error.index,
+                position: None,
+            },
+        };
+        Self::LineErrors(vec![LineError::new(error_type)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::run_python;
+
+    #[test]
+    fn pydantic_error_types() {
+        let error = || String::from("error");
+        let cases = [
+            (ErrorType::MissingField, "missing"),
+            (ErrorType::StringType, "string_type"),
+            (
+                ErrorType::StringUnicode { error: error() },
+                "string_unicode",
+            ),
+            (ErrorType::StringTooShort { min: 1 }, "string_too_short"),
+            (ErrorType::StringTooLong { max: 1 }, "string_too_long"),
+            (ErrorType::IntType, "int_type"),
+            (ErrorType::IntTooBig, "int_parsing_size"),
+            (ErrorType::IntFromFloat, "int_from_float"),
+            (ErrorType::IntParsing, "int_parsing"),
+            (ErrorType::GreaterThan { limit: 1 }, "greater_than"),
+            (
+                ErrorType::GreaterThanEqual { limit: 1 },
+                "greater_than_equal",
+            ),
+            (ErrorType::LessThan { limit: 1 }, "less_than"),
+            (ErrorType::LessThanEqual { limit: 1 }, "less_than_equal"),
+            (ErrorType::MultipleOf { multiple_of: 2 }, "multiple_of"),
+            (ErrorType::FloatType, "float_type"),
+            (ErrorType::FloatGreaterThan { limit: 1.0 }, "greater_than"),
+            (
+                ErrorType::FloatGreaterThanEqual { limit: 1.0 },
+                "greater_than_equal",
+            ),
+            (ErrorType::FloatLessThan { limit: 1.0 }, "less_than"),
+            (
+                ErrorType::FloatLessThanEqual { limit: 1.0 },
+                "less_than_equal",
+            ),
+            (ErrorType::BoolType, "bool_type"),
+            (ErrorType::BytesType, "bytes_type"),
+            (ErrorType::ListType, "list_type"),
+            (ErrorType::TooShort { min: 1 }, "too_short"),
+            (ErrorType::TooLong { max: 1 }, "too_long"),
+            (ErrorType::SetType, "set_type"),
+            (ErrorType::TupleType, "tuple_type"),
+            (ErrorType::TupleLength { expected: 1 }, "tuple_length"),
+            (ErrorType::DictType, "dict_type"),
+            (ErrorType::ExtraForbidden, "extra_forbidden"),
+            (ErrorType::DuplicateKey, "duplicate_key"),
+            (ErrorType::FrozenInstance, "frozen_instance"),
+            (ErrorType::UuidType, "uuid_type"),
+            (ErrorType::UuidParsing { error: error() }, "uuid_parsing"),
+            (ErrorType::DatetimeType, "datetime_type"),
+            (
+                ErrorType::DatetimeParsing { error: error() },
+                "datetime_parsing",
+            ),
+            (ErrorType::DecimalType, "decimal_type"),
+            (ErrorType::DecimalParsing, "decimal_parsing"),
+            (
+                ErrorType::DecimalMaxDigits { max_digits: 1 },
+                "decimal_max_digits",
+            ),
+            (
+                ErrorType::DecimalMaxPlaces { decimal_places: 1 },
+                "decimal_max_places",
+            ),
+            (
+                ErrorType::LiteralError { expected: error() },
+                "literal_error",
+            ),
+            (ErrorType::EnumError { expected: error() }, "enum"),
+            (
+                ErrorType::UnionTagInvalid {
+                    discriminator: error(),
+                    tag: error(),
+                    expected_tags: error(),
+                },
+                "union_tag_invalid",
+            ),
+            (
+                ErrorType::UnionTagNotFound {
+                    discriminator: error(),
+                },
+                "union_tag_not_found",
+            ),
+            (ErrorType::ValueError { error: error() }, "value_error"),
+            (
+                ErrorType::AssertionError { error: error() },
+                "assertion_error",
+            ),
+            (ErrorType::RecursionLimit, "recursion_limit"),
+            (ErrorType::JsonTooDeep { max_depth: 1 }, "json_too_deep"),
+            (ErrorType::JsonType, "json_type"),
+            (
+                ErrorType::JsonInvalid {
+                    error: error(),
+                    index: 0,
+                    position: None,
+                },
+                "json_invalid",
+            ),
+        ];
+        for (error_type, expected) in cases {
+            assert_eq!(error_type.to_str(), expected);
+        }
+    }
+
+    #[test]
+    fn error_dicts() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int', 'gt': 3}),
+                field('s', {'type': 'string', 'max_length': 2}),
+                field('l', {'type': 'list', 'items_schema': {'type': 'int'}}, required=False, default=None),
+            )
+            try:
+                v.validate_python({'a': 1, 's': 'xxx', 'l': [1, 'y', None]})
+            except ValidationError as e:
+                assert e.errors() == [
+                    {
+                        'error_type': 'greater_than', 'location': ['a'], 'msg': 'Input should be greater than 3',
+                        'input': 1, 'ctx': {'gt': 3}, 'url': 'https://errors.pydantic.dev/2.8/v/greater_than',
+                    },
+                    {
+                        'error_type': 'int_type', 'location': ['l', 1],
+                        'msg': 'Input should be a valid integer',
+                        'input': 'y', 'url': 'https://errors.pydantic.dev/2.8/v/int_type',
+                    },
+                    {
+                        'error_type': 'int_type', 'location': ['l', 2], 'msg': 'Input should be a valid integer',
+                        'input': None, 'url': 'https://errors.pydantic.dev/2.8/v/int_type',
+                    },
+                    {
+                        'error_type': 'string_too_long', 'location': ['s'],
+                        'msg': 'String should have at most 2 characters', 'input': 'xxx',
+                        'ctx': {'max_length': 2}, 'url': 'https://errors.pydantic.dev/2.8/v/string_too_long',
+                    },
+                ], e.errors()
+                assert [sorted(d) for d in e.errors(include_url=False, include_context=False)] == [
+                    ['error_type', 'input', 'location', 'msg'],
+                ] * 4
+                assert 'url' not in e.errors(include_url=False)[0]
+                assert e.errors(include_context=False)[0]['url'].endswith('greater_than')
+            else:
+                raise AssertionError('expected a ValidationError')
+
+            # missing fields have no input, JSON input is included as python values
+            try:
+                v.validate_json(b'{"s": "xxx", "l": [{"q": 1}]}')
+            except ValidationError as e:
+                assert [(d['error_type'], d.get('input', 'MISSING')) for d in e.errors()] == [
+                    ('missing', 'MISSING'), ('int_type', {'q': 1}), ('string_too_long', 'xxx'),
+                ]
+            else:
+                raise AssertionError('expected a ValidationError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn json_and_str() {
+        run_python(
+            r#"
+            import json
+            class User(Model):
+                __slots__ = ()
+            v = SchemaValidator({'type': 'model', 'cls': User, 'fields': [
+                field('a', {'type': 'int'}),
+                field('l', {'type': 'list', 'items_schema': {'type': 'int'}}, required=False, default=None),
+            ]})
+            try:
+                v.validate_python({'a': 'x', 'l': [1, 'y', {'k': b'x'}]})
+            except ValidationError as e:
+                errors_json = json.loads(e.json())
+                assert json.loads(e.json(indent=2)) == errors_json
+                assert e.json(indent=2).startswith('[\n  {\n    "type": "int_type",')
+                assert errors_json == [
+                    {
+                        'type': 'int_type', 'loc': ['a'],
+                        'msg': 'Input should be a valid integer',
+                        'input': 'x',
+                    },
+                    {
+                        'type': 'int_type', 'loc': ['l', 1],
+                        'msg': 'Input should be a valid integer',
+                        'input': 'y',
+                    },
+                    {
+                        'type': 'int_type', 'loc': ['l', 2], 'msg': 'Input should be a valid integer',
+                        'input': {'k': 'eA=='},
+                    },
+                ], errors_json
+                assert str(e) == (
+                    "3 validation errors for User\n"
+                    "a\n"
+                    "  Input should be a valid integer [type=int_type, input_value='x', input_type=str]\n"
+                    "l.1\n"
+                    "  Input should be a valid integer [type=int_type, input_value='y', input_type=str]\n"
+                    "l.2\n"
+                    "  Input should be a valid integer [type=int_type, input_value={'k': b'x'}, input_type=dict]"
+                ), str(e)
+            else:
+                raise AssertionError('expected a ValidationError')
+
+            # inputs which can't be serialized are represented by their repr
+            class Opaque:
+                def __repr__(self):
+                    return 'Opaque()'
+            try:
+                v.validate_python({'a': Opaque()})
+            except ValidationError as e:
+                assert json.loads(e.json())[0]['input'] == 'Opaque()', e.json()
+            else:
+                raise AssertionError('expected a ValidationError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn sequence_protocol() {
+        run_python(
+            r#"
+            v = model_validator(*[field(name, {'type': 'int'}) for name in 'abc'])
+            try:
+                v.validate_python({'a': 'x', 'b': 'y'})
+            except ValidationError as e:
+                assert len(e) == e.error_count() == 3
+                assert e[0]['location'] == ['a'] and e[-1]['location'] == ['c']
+                assert e[-3] == e[0]
+                assert list(e) == e.errors()
+                for index in [3, -4]:
+                    try:
+                        e[index]
+                    except IndexError:
+                        pass
+                    else:
+                        raise AssertionError(index)
+            else:
+                raise AssertionError('expected a ValidationError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn title() {
+        run_python(
+            r#"
+            class User(Model):
+                __slots__ = ()
+            def first_line(v, method, data):
+                try:
+                    getattr(v, method)(data)
+                except ValidationError as e:
+                    return str(e).splitlines()[0]
+            fields = [field('a', {'type': 'int'})]
+            v = SchemaValidator({'type': 'model', 'cls': User, 'fields': fields})
+            assert first_line(v, 'validate_python', {'a': 'x'}) == '1 validation error for User'
+            assert first_line(v, 'validate_json_many', b'[1, "x"]') == '2 validation errors for list[User]'
+            v = SchemaValidator({'type': 'model', 'cls': User, 'title': 'Account', 'fields': fields})
+            assert first_line(v, 'validate_python', {'a': 'x'}) == '1 validation error for Account'
+            v = SchemaValidator({'type': 'int'})
+            assert first_line(v, 'validate_python', 'x') == '1 validation error for int'
+            v = SchemaValidator({'type': 'int', 'title': 'Age'})
+            assert first_line(v, 'validate_python', 'x') == '1 validation error for Age'
+            "#,
+        );
+    }
+
+    #[test]
+    fn url() {
+        let url = ErrorType::IntParsing.url();
+        assert_eq!(
+            url.as_deref(),
+            Some("https://errors.pydantic.dev/2.8/v/int_parsing")
+        );
+        // error types pydantic doesn't have aren't documented
+        assert_eq!(ErrorType::DuplicateKey.url(), None);
+        assert_eq!(ErrorType::JsonTooDeep { max_depth: 1 }.url(), None);
+    }
+
+    #[test]
+    fn sort_and_dedup() {
+        run_python(
+            r#"
+            import itertools, json
+            fields = [
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'string'}),
+                field('c', {'type': 'list', 'items_schema': {'type': 'int'}}),
+            ]
+            v = model_validator(*fields)
+            data = {'c': [1, 'x', 'y'], 'b': [], 'a': 'x'}
+            for items in itertools.permutations(data.items()):
+                data = dict(items)
+                expected = [('int_type', ('a',)), ('string_type', ('b',)),
+                            ('int_type', ('c', 1)), ('int_type', ('c', 2))]
+                assert errors(v.validate_python, data) == expected
+                assert errors(v.validate_json, json.dumps(data).encode()) == expected
+            v = model_validator(*fields, sort_errors=False)
+            assert errors(v.validate_json, b'{"c": [1, "x"], "b": []}') == [
+                ('int_type', ('c', 1)), ('string_type', ('b',)), ('missing', ('a',)),
+            ]
+
+            union = {'type': 'union', 'choices': [
+                {'type': 'int', 'gt': 5}, {'type': 'int', 'gt': 5}, {'type': 'int', 'gt': 9},
+            ]}
+            assert len(errors(SchemaValidator(union).validate_python, 3)) == 3
+            assert errors(SchemaValidator({**union, 'dedup_errors': True}).validate_python, 3) == [
+                ('greater_than', ('int',)), ('greater_than', ('int',)),
+            ]
+            "#,
+        );
+    }
+
+    #[test]
+    fn string_unicode_context() {
+        run_python(
+            r#"
+            v = model_validator(field('a', {'type': 'string'}))
+            def error(f, data):
+                try:
+                    f(data)
+                except ValidationError as e:
+                    [error] = e.errors()
+                    return error
+                raise AssertionError('expected a ValidationError')
+            for f, data in [
+                (v.validate_python, {'a': '\ud800'}),
+                (v.validate_python, {'a': b'\xff'}),
+                (v.validate_json, b'{"a": "\xff"}'),
+                (v.validate_json, b'{"a": "\\udc00"}'),
+            ]:
+                e = error(f, data)
+                assert e['error_type'] == 'string_unicode', e
+                assert e['msg'] == (
+                    'Input should be a valid string, unable to parse raw data as a unicode string, '
+                    + e['ctx']['error']
+                ), e
+            assert error(v.validate_python, {'a': b'\xff'})['ctx'] == {
+                'error': 'invalid utf-8 sequence of 1 bytes from index 0',
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn json_errors() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'list', 'items_schema': {'type': 'int'}}, required=False, default=None),
+            )
+            data = '{\n  "a": 1,\n  "b": [1, 2,, 3]\n}'
+            try:
+                v.validate_json(data)
+            except ValidationError as e:
+                [error] = e.errors()
+                assert error['error_type'] == 'json_invalid' and error['location'] == []
+                assert error['msg'] == 'Invalid JSON: expected value at line 3 column 14'
+                ctx = error['ctx']
+                assert (ctx['index'], ctx['line'], ctx['column']) == (25, 3, 14), ctx
+                assert data[ctx['index']] == ','
+            else:
+                raise AssertionError('expected a ValidationError')
+
+            # numbers too big for an i64 are errors for the field rather than the whole document
+            big = 10**30
+            assert errors(v.validate_json, b'{"a": %d}' % big) == [('int_parsing_size', ('a',))]
+            assert errors(v.validate_json, b'{"a": 1, "b": [1, %d, "x"]}' % big) == [
+                ('int_parsing_size', ('b', 1)), ('int_type', ('b', 2)),
+            ]
+            try:
+                v.validate_json(b'{"a": %d}' % big)
+            except ValidationError as e:
+                assert e.errors()[0]['input'] == big
+            "#,
+        );
     }
 }
@@ -1,21 +1,43 @@
 use std::fmt::Debug;
 use std::sync::Arc;
 
-use pyo3::exceptions::PyKeyError;
+use jiter::JsonValue;
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::intern;
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList, PyString};
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{
+    IntoPyDict, PyBool, PyBytes, PyDateAccess, PyDateTime, PyDelta, PyDeltaAccess, PyDict, PyFloat,
+    PyFrozenSet, PyInt, PyList, PySet, PyString, PyTimeAccess, PyTuple, PyType, PyTzInfo,
+};
 
+use serde::ser::SerializeMap;
 use serde::Serialize;
 use smallvec::SmallVec;
+use speedate::{Date, DateTime, Time};
+use uuid::Uuid;
 
-use crate::validators::CombinedValidator;
+use crate::base64;
+use crate::errors::{LocItem, ValResult};
+use crate::model_data::{model_data_eq, DumpOptions, ModelData, SerializePy};
+use crate::validators::{CombinedValidator, Definitions, ValidationState, Validator};
 
 #[derive(Debug)]
 pub struct FieldInfo {
     pub name: String,
     pub name_py: Py<PyString>,
+    pub aliases: FieldAliases,
+    // the validation alias or else the name, see `input_name`
+    input_name: Arc<str>,
+    // true iff the schema has neither "default" nor "default_factory", so `"default": None` makes a field optional
     pub required: bool,
     pub default: PyObject,
+    // called to create a fresh default for each instance, e.g. for mutable defaults like lists
+    pub default_factory: Option<PyObject>,
+    // from the schema's "validate_default", run defaults through `validator` when they're used
+    pub validate_default: bool,
+    // from the schema's "exclude", the field is validated and stored but never included in dumps
+    pub exclude: bool,
     pub validator: CombinedValidator,
 }
 
@@ -23,34 +45,168 @@ impl FieldInfo {
     pub fn new(
         py: Python,
         name: &str,
+        aliases: FieldAliases,
         required: bool,
         default: PyObject,
+        default_factory: Option<PyObject>,
         validator: CombinedValidator,
     ) -> Self {
         let name_py = PyString::new_bound(py, name).into_py(py);
+        let input_name = aliases.validation.as_deref().unwrap_or(name).into();
         Self {
             name: name.to_owned(),
             name_py,
+            aliases,
+            input_name,
             required,
             default,
+            default_factory,
+            validate_default: false,
+            exclude: false,
             validator,
         }
     }
+
+    pub fn with_validate_default(mut self, validate_default: bool) -> Self {
+        self.validate_default = validate_default;
+        self
+    }
+
+    pub fn with_exclude(mut self, exclude: bool) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    /// The value when the field is missing, `validate_default` errors are located at the field name
+    pub fn get_default(&self, py: Python) -> ValResult<FieldValue> {
+        let default = match &self.default_factory {
+            Some(default_factory) => default_factory.call0(py)?,
+            None => self.default.clone_ref(py),
+        };
+        if !self.validate_default {
+            return Ok(FieldValue::Py(default));
+        }
+        let default = default.bind(py);
+        let mut state = ValidationState::new(py, None, None);
+        match self.validator.validate_python(&mut state, default) {
+            Ok(field_value) => Ok(field_value),
+            Err(e) => {
+                let errors = e
+                    .with_py_input(default)
+                    .line_errors_with_loc(self.name.as_str())?;
+                Err(errors.into())
+            }
+        }
+    }
+
+    /// The key for this field in input
+    pub fn input_name(&self) -> &str {
+        &self.input_name
+    }
+
+    /// `input_name` as the location of errors for this field, without copying the name
+    pub fn input_loc(&self) -> LocItem {
+        (&self.input_name).into()
+    }
+
+    /// The key to use for this field in `model_dump` and `model_dump_json` output
+    pub fn dump_name(&self, by_alias: bool) -> &str {
+        match (&self.aliases.serialization, by_alias) {
+            (Some(alias), true) => alias,
+            _ => &self.name,
+        }
+    }
+}
+
+/// Keys for a field other than its name, used for input and for dumps with `by_alias`
+#[derive(Debug, Default)]
+pub struct FieldAliases {
+    pub validation: Option<String>,
+    pub serialization: Option<String>,
 }
 
-pub fn parse_fields(py: Python, fields: Bound<PyList>) -> PyResult<Vec<FieldInfo>> {
+/// Derives aliases for every field of a model from the field names, aliases set on a field win
+#[derive(Debug, Clone, Copy)]
+pub enum AliasGenerator {
+    // `user_id` -> `userId`
+    Camel,
+}
+
+impl AliasGenerator {
+    pub fn new(alias_generator: &str) -> PyResult<Self> {
+        match alias_generator {
+            "camel" => Ok(Self::Camel),
+            s => Err(PyValueError::new_err(format!(
+                "Invalid alias_generator: {s}"
+            ))),
+        }
+    }
+
+    fn alias(self, name: &str) -> String {
+        match self {
+            Self::Camel => {
+                let mut alias = String::with_capacity(name.len());
+                for word in name.split('_').filter(|word| !word.is_empty()) {
+                    let mut chars = word.chars();
+                    if let Some(first) = chars.next() {
+                        match alias.is_empty() {
+                            true => alias.extend(first.to_lowercase()),
+                            false => alias.extend(first.to_uppercase()),
+                        }
+                        alias.push_str(chars.as_str());
+                    }
+                }
+                alias
+            }
+        }
+    }
+}
+
+pub fn parse_fields(
+    py: Python,
+    fields: Bound<PyList>,
+    strict: bool,
+    alias_generator: Option<AliasGenerator>,
+    definitions: &Definitions,
+) -> PyResult<Vec<FieldInfo>> {
     fields
         .iter()
         .map(|field| {
             let field: &Bound<PyDict> = field.downcast()?;
             let name: String = get_as_req(field, "name")?;
-            let (required, default) = match field.get_item("default")? {
-                Some(default) => (false, default.to_object(py)),
-                None => (true, py.None()),
+            let generated = alias_generator.map(|generator| generator.alias(&name));
+            let aliases = FieldAliases {
+                validation: get_as_opt(field, "validation_alias")?.or_else(|| generated.clone()),
+                serialization: get_as_opt(field, "serialization_alias")?.or(generated),
+            };
+            let default_factory: Option<PyObject> = get_as_opt(field, "default_factory")?;
+            let (required, default) = match (field.get_item("default")?, &default_factory) {
+                (Some(_), Some(_)) => {
+                    let msg = format!(
+                        "field \"{}\": cannot set both default and default_factory",
+                        name
+                    );
+                    return Err(PyValueError::new_err(msg));
+                }
+                (Some(default), None) => (false, default.to_object(py)),
+                (None, Some(_)) => (false, py.None()),
+                (None, None) => (true, py.None()),
             };
             let schema = get_as_req(field, "schema")?;
-            let validator = CombinedValidator::new(&schema)?;
-            Ok(FieldInfo::new(py, &name, required, default, validator))
+            // a field's "strict" overrides the model's for its validator
+            let strict = get_as_opt(field, "strict")?.unwrap_or(strict);
+            let validator = CombinedValidator::new(&schema, strict, definitions)?;
+            Ok(FieldInfo::new(
+                py,
+                &name,
+                aliases,
+                required,
+                default,
+                default_factory,
+                validator,
+            )
+            .with_validate_default(get_as_opt(field, "validate_default")?.unwrap_or(false))
+            .with_exclude(get_as_opt(field, "exclude")?.unwrap_or(false)))
         })
         .collect()
 }
@@ -65,10 +221,20 @@ where
     }
 }
 
+pub fn get_as_opt<'py, T>(field: &Bound<'py, PyDict>, key: &str) -> PyResult<Option<T>>
+where
+    T: FromPyObject<'py>,
+{
+    match field.get_item(key)? {
+        Some(t) => t.extract().map(Some),
+        None => Ok(None),
+    }
+}
+
 #[derive(Debug)]
 pub enum FieldValue {
     Py(PyObject),
-    Model(PyObject),
+    Model(PyObject, Py<ModelData>),
     Raw(RawData),
     Both(PyObject, RawData),
 }
@@ -77,26 +243,77 @@ impl FieldValue {
     pub fn new_raw(into_raw: impl Into<RawData>) -> Self {
         FieldValue::Raw(into_raw.into())
     }
-}
 
-impl ToPyObject for FieldValue {
-    fn to_object(&self, py: Python) -> PyObject {
+    pub fn is_none(&self, py: Python) -> bool {
         match self {
-            Self::Py(py_obj) => py_obj.clone_ref(py),
-            Self::Model(py_obj) => py_obj.clone_ref(py),
-            Self::Raw(raw) => raw.to_object(py),
-            Self::Both(py_obj, _) => py_obj.clone_ref(py),
+            Self::Py(py_obj) => py_obj.is_none(py),
+            Self::Raw(raw) | Self::Both(_, raw) => matches!(raw, RawData::None),
+            Self::Model(..) => false,
+        }
+    }
+
+    /// Python objects are converted once and cached as `Both`, models have no raw representation
+    pub fn raw_value(&mut self, py: Python) -> Option<&RawData> {
+        if let Self::Py(py_obj) = self {
+            let raw = RawData::from_py(py_obj.bind(py));
+            *self = Self::Both(py_obj.clone_ref(py), raw);
+        }
+        match self {
+            Self::Raw(raw) | Self::Both(_, raw) => Some(raw),
+            Self::Py(_) | Self::Model(..) => None,
+        }
+    }
+
+    /// The python object for this value, raw values are converted once and cached by upgrading to `Both`
+    pub fn py_value(&mut self, py: Python) -> PyResult<PyObject> {
+        if let Self::Raw(raw) = self {
+            *self = Self::Both(raw.to_py(py)?, std::mem::replace(raw, RawData::None));
+        }
+        self.to_py(py)
+    }
+
+    /// Equality as in python, models are equal if they're the same class with equal field data
+    pub fn py_eq(&self, py: Python, other: &FieldValue) -> PyResult<bool> {
+        match (self, other) {
+            (Self::Model(self_obj, self_data), Self::Model(other_obj, other_data)) => {
+                let same_class = self_obj
+                    .bind(py)
+                    .get_type()
+                    .is(&other_obj.bind(py).get_type());
+                Ok(same_class && model_data_eq(self_data.bind(py), other_data.bind(py))?)
+            }
+            (
+                Self::Raw(self_raw) | Self::Both(_, self_raw),
+                Self::Raw(other_raw) | Self::Both(_, other_raw),
+            ) => self_raw.py_eq(py, other_raw),
+            _ => self.to_py(py)?.bind(py).eq(other.to_py(py)?),
         }
     }
-}
 
-impl IntoPy<PyObject> for FieldValue {
-    fn into_py(self, py: Python) -> PyObject {
+    /// Used when collecting validated items into a `RawData` container
+    pub fn into_raw(self) -> RawData {
         match self {
-            Self::Py(py_obj) => py_obj,
-            Self::Model(py_obj) => py_obj,
-            Self::Raw(raw) => raw.to_object(py),
-            Self::Both(py_obj, _) => py_obj,
+            Self::Raw(raw) | Self::Both(_, raw) => raw,
+            Self::Py(py_obj) | Self::Model(py_obj, _) => RawData::Py(Arc::new(py_obj)),
+        }
+    }
+
+    /// Raw values are converted on each call, which can fail, e.g. for a datetime python can't represent
+    pub fn to_py(&self, py: Python) -> PyResult<PyObject> {
+        match self {
+            Self::Py(py_obj) => Ok(py_obj.clone_ref(py)),
+            Self::Model(py_obj, _) => Ok(py_obj.clone_ref(py)),
+            Self::Raw(raw) => raw.to_py(py),
+            Self::Both(py_obj, _) => Ok(py_obj.clone_ref(py)),
+        }
+    }
+
+    pub fn into_py_object(self, py: Python) -> PyResult<PyObject> {
+        match self {
+            Self::Py(py_obj) => Ok(py_obj),
+            Self::Model(py_obj, _) => Ok(py_obj),
+            Self::Raw(raw) => raw.to_py(py),
+            Self::Both(py_obj, _) => Ok(py_obj),
         }
     }
 }
@@ -108,12 +325,22 @@ pub enum RawData {
     Int(i64),
     Float(f64),
     Str(String),
+    Bytes(Vec<u8>),
     List(RawList),
+    // unique items in the order they were first seen
+    Set(RawList),
+    Tuple(RawList),
     Dict(RawDict),
+    Uuid(Uuid),
+    Datetime(DateTime),
+    // the exact string form, so precision isn't lost to `f64`
+    Decimal(String),
+    // python objects which can't be represented natively, e.g. strings from python input or models in a list
+    Py(Arc<PyObject>),
 }
 
-type RawList = Arc<SmallVec<[RawData; 8]>>;
-type RawDict = Arc<SmallVec<[(String, RawData); 8]>>;
+pub type RawList = Arc<SmallVec<[RawData; 8]>>;
+pub type RawDict = Arc<SmallVec<[(RawData, RawData); 8]>>;
 
 impl Serialize for RawData {
     fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
@@ -123,29 +350,308 @@ impl Serialize for RawData {
             RawData::Int(i) => serializer.serialize_i64(*i),
             RawData::Float(f) => serializer.serialize_f64(*f),
             RawData::Str(s) => serializer.serialize_str(s),
-            RawData::List(l) => l.serialize(serializer),
-            RawData::Dict(d) => d.serialize(serializer),
+            RawData::Bytes(b) => serializer.serialize_str(&base64::encode(b)),
+            RawData::Uuid(u) => serializer.collect_str(&u.hyphenated()),
+            RawData::Datetime(dt) => serializer.collect_str(dt),
+            RawData::Decimal(d) => serializer.serialize_str(d),
+            RawData::List(l) | RawData::Set(l) | RawData::Tuple(l) => l.serialize(serializer),
+            RawData::Dict(d) => {
+                let mut map = serializer.serialize_map(Some(d.len()))?;
+                for (k, v) in d.iter() {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            RawData::Py(py_obj) => Python::with_gil(|py| {
+                SerializePy(py_obj.bind(py), DumpOptions::default()).serialize(serializer)
+            }),
         }
     }
 }
 
-impl ToPyObject for RawData {
-    fn to_object(&self, py: Python<'_>) -> PyObject {
-        match self {
-            Self::None => py.None().to_object(py),
+/// Structural equality without python, unlike `py_eq`, e.g. `Int(1)` isn't equal to `Float(1.0)`
+impl PartialEq for RawData {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::None, Self::None) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            // IEEE 754, so `NaN` isn't equal to itself
+            (Self::Float(a), Self::Float(b)) => a == b,
+            (Self::Str(a), Self::Str(b)) => a == b,
+            (Self::Bytes(a), Self::Bytes(b)) => a == b,
+            (Self::List(a), Self::List(b)) | (Self::Tuple(a), Self::Tuple(b)) => a == b,
+            // set items are unique, so equal lengths and containment means equal sets
+            (Self::Set(a), Self::Set(b)) => a.len() == b.len() && a.iter().all(|v| b.contains(v)),
+            // likewise dict keys
+            (Self::Dict(a), Self::Dict(b)) => {
+                a.len() == b.len() && a.iter().all(|entry| b.contains(entry))
+            }
+            (Self::Uuid(a), Self::Uuid(b)) => a == b,
+            (Self::Datetime(a), Self::Datetime(b)) => a == b,
+            (Self::Decimal(a), Self::Decimal(b)) => a == b,
+            // python objects by identity
+            (Self::Py(a), Self::Py(b)) => a.as_ptr() == b.as_ptr(),
+            _ => false,
+        }
+    }
+}
+
+impl RawData {
+    /// Equality as in python, values of the same simple type are compared without converting to python
+    pub fn py_eq(&self, py: Python, other: &RawData) -> PyResult<bool> {
+        match (self, other) {
+            (Self::None, Self::None) => Ok(true),
+            (Self::Bool(a), Self::Bool(b)) => Ok(a == b),
+            (Self::Int(a), Self::Int(b)) => Ok(a == b),
+            (Self::Float(a), Self::Float(b)) => Ok(a == b),
+            (Self::Str(a), Self::Str(b)) => Ok(a == b),
+            (Self::Bytes(a), Self::Bytes(b)) => Ok(a == b),
+            (Self::Uuid(a), Self::Uuid(b)) => Ok(a == b),
+            (Self::List(a), Self::List(b)) => {
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for (a_item, b_item) in a.iter().zip(b.iter()) {
+                    if !a_item.py_eq(py, b_item)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            // e.g. `1 == 1.0`, dicts with keys in a different order or datetimes in different timezones
+            _ => self.to_py(py)?.bind(py).eq(other.to_py(py)?),
+        }
+    }
+
+    /// Convert a python object, anything which can't be represented natively is kept as `RawData::Py`
+    pub fn from_py(py_value: &Bound<'_, PyAny>) -> Self {
+        let as_py = || RawData::Py(Arc::new(py_value.clone().unbind()));
+        if py_value.is_none() {
+            RawData::None
+        } else if let Ok(value) = py_value.downcast::<PyBool>() {
+            RawData::Bool(value.is_true())
+        } else if let Ok(value) = py_value.downcast::<PyString>() {
+            match value.to_str() {
+                Ok(s) => RawData::Str(s.to_owned()),
+                Err(_) => as_py(),
+            }
+        } else if let Ok(value) = py_value.downcast::<PyInt>() {
+            match value.extract::<i64>() {
+                Ok(i) => RawData::Int(i),
+                Err(_) => as_py(),
+            }
+        } else if let Ok(value) = py_value.downcast::<PyFloat>() {
+            RawData::Float(value.value())
+        } else if let Ok(value) = py_value.downcast::<PyBytes>() {
+            RawData::Bytes(value.as_bytes().to_vec())
+        } else if let Ok(value) = py_value.downcast::<PyDateTime>() {
+            match datetime_from_py(value) {
+                Ok(dt) => RawData::Datetime(dt),
+                Err(_) => as_py(),
+            }
+        } else if let Ok(value) = py_value.downcast::<PyList>() {
+            RawData::List(Arc::new(
+                value.iter().map(|v| RawData::from_py(&v)).collect(),
+            ))
+        } else if let Ok(value) = py_value.downcast::<PyTuple>() {
+            RawData::Tuple(Arc::new(
+                value.iter().map(|v| RawData::from_py(&v)).collect(),
+            ))
+        } else if let Ok(value) = py_value.downcast::<PySet>() {
+            RawData::Set(Arc::new(
+                value.iter().map(|v| RawData::from_py(&v)).collect(),
+            ))
+        } else if let Ok(value) = py_value.downcast::<PyFrozenSet>() {
+            RawData::Set(Arc::new(
+                value.iter().map(|v| RawData::from_py(&v)).collect(),
+            ))
+        } else if let Ok(value) = py_value.downcast::<PyDict>() {
+            let items = value
+                .iter()
+                .map(|(k, v)| (RawData::from_py(&k), RawData::from_py(&v)))
+                .collect();
+            RawData::Dict(Arc::new(items))
+        } else if let Ok(Some(uuid)) = uuid_from_py(py_value) {
+            RawData::Uuid(uuid)
+        } else if let Ok(Some(decimal)) = decimal_from_py(py_value) {
+            RawData::Decimal(decimal)
+        } else {
+            as_py()
+        }
+    }
+}
+
+static UUID_TYPE: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+
+fn get_uuid_type(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
+    let uuid_type = UUID_TYPE.get_or_try_init(py, || {
+        let uuid_type = py.import_bound("uuid")?.getattr("UUID")?;
+        Ok::<_, PyErr>(uuid_type.downcast_into::<PyType>()?.unbind())
+    })?;
+    Ok(uuid_type.bind(py))
+}
+
+/// `None` if the value isn't a `uuid.UUID` instance
+pub fn uuid_from_py(py_value: &Bound<'_, PyAny>) -> PyResult<Option<Uuid>> {
+    let py = py_value.py();
+    if py_value.is_instance(get_uuid_type(py)?)? {
+        let int: u128 = py_value.getattr(intern!(py, "int"))?.extract()?;
+        Ok(Some(Uuid::from_u128(int)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn uuid_to_py(py: Python, uuid: &Uuid) -> PyResult<PyObject> {
+    let kwargs = [("int", uuid.as_u128())].into_py_dict_bound(py);
+    Ok(get_uuid_type(py)?.call((), Some(&kwargs))?.unbind())
+}
+
+static DECIMAL_TYPE: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+
+pub fn get_decimal_type(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
+    let decimal_type = DECIMAL_TYPE.get_or_try_init(py, || {
+        let decimal_type = py.import_bound("decimal")?.getattr("Decimal")?;
+        Ok::<_, PyErr>(decimal_type.downcast_into::<PyType>()?.unbind())
+    })?;
+    Ok(decimal_type.bind(py))
+}
+
+static ENUM_TYPE: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+
+pub fn get_enum_type(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
+    let enum_type = ENUM_TYPE.get_or_try_init(py, || {
+        let enum_type = py.import_bound("enum")?.getattr("Enum")?;
+        Ok::<_, PyErr>(enum_type.downcast_into::<PyType>()?.unbind())
+    })?;
+    Ok(enum_type.bind(py))
+}
+
+/// `None` if the value isn't a `decimal.Decimal` instance, otherwise its exact string form
+pub fn decimal_from_py(py_value: &Bound<'_, PyAny>) -> PyResult<Option<String>> {
+    if py_value.is_instance(get_decimal_type(py_value.py())?)? {
+        Ok(Some(py_value.str()?.to_str()?.to_owned()))
+    } else {
+        Ok(None)
+    }
+}
+
+fn decimal_to_py(py: Python, decimal: &str) -> PyResult<PyObject> {
+    Ok(get_decimal_type(py)?.call1((decimal,))?.unbind())
+}
+
+/// `tz_offset` is taken from `utcoffset()`, so it's `None` for naive datetimes
+pub fn datetime_from_py(py_dt: &Bound<'_, PyDateTime>) -> PyResult<DateTime> {
+    let py = py_dt.py();
+    let utc_offset = py_dt.call_method0(intern!(py, "utcoffset"))?;
+    let tz_offset = match utc_offset.downcast::<PyDelta>() {
+        Ok(delta) => Some(delta.get_days() * 86_400 + delta.get_seconds()),
+        Err(_) => None,
+    };
+    Ok(DateTime {
+        date: Date {
+            year: py_dt.get_year() as u16,
+            month: py_dt.get_month(),
+            day: py_dt.get_day(),
+        },
+        time: Time {
+            hour: py_dt.get_hour(),
+            minute: py_dt.get_minute(),
+            second: py_dt.get_second(),
+            microsecond: py_dt.get_microsecond(),
+            tz_offset,
+        },
+    })
+}
+
+fn datetime_to_py(py: Python, dt: &DateTime) -> PyResult<PyObject> {
+    let tzinfo = match dt.time.tz_offset {
+        Some(offset) => {
+            let delta = PyDelta::new_bound(py, 0, offset, 0, true)?;
+            let timezone = py.import_bound("datetime")?.getattr("timezone")?;
+            let tzinfo = timezone.call1(PyTuple::new_bound(py, [delta]))?;
+            Some(tzinfo.downcast_into::<PyTzInfo>()?)
+        }
+        None => None,
+    };
+    let py_dt = PyDateTime::new_bound(
+        py,
+        i32::from(dt.date.year),
+        dt.date.month,
+        dt.date.day,
+        dt.time.hour,
+        dt.time.minute,
+        dt.time.second,
+        dt.time.microsecond,
+        tzinfo.as_ref(),
+    )?;
+    Ok(py_dt.into_any().unbind())
+}
+
+impl From<JsonValue<'_>> for RawData {
+    fn from(value: JsonValue) -> Self {
+        match value {
+            JsonValue::Null => RawData::None,
+            JsonValue::Bool(b) => RawData::Bool(b),
+            JsonValue::Int(i) => RawData::Int(i),
+            JsonValue::BigInt(b) => {
+                // jiter gives a `BigInt` for any integer of 19 or more digits, even if it fits in an `i64`
+                let digits = b.to_string();
+                match digits.parse() {
+                    Ok(i) => RawData::Int(i),
+                    Err(_) => Python::with_gil(|py| {
+                        match py.get_type_bound::<PyInt>().call1((&digits,)) {
+                            Ok(int) => RawData::Py(Arc::new(int.unbind())),
+                            Err(_) => RawData::Str(digits),
+                        }
+                    }),
+                }
+            }
+            JsonValue::Float(f) => RawData::Float(f),
+            JsonValue::Str(s) => RawData::Str(s.into_owned()),
+            JsonValue::Array(array) => {
+                RawData::List(Arc::new(array.iter().map(|v| v.clone().into()).collect()))
+            }
+            JsonValue::Object(object) => {
+                let items = object
+                    .iter()
+                    .map(|(k, v)| (RawData::Str(k.to_string()), v.clone().into()))
+                    .collect();
+                RawData::Dict(Arc::new(items))
+            }
+        }
+    }
+}
+
+impl RawData {
+    /// Can fail for values python can't represent or, in sets and dict keys, can't hash
+    pub fn to_py(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let to_py_items = |items: &RawList| -> PyResult<Vec<PyObject>> {
+            items.iter().map(|item| item.to_py(py)).collect()
+        };
+        Ok(match self {
+            Self::None => py.None(),
             Self::Bool(b) => b.to_object(py),
             Self::Int(i) => i.to_object(py),
             Self::Float(f) => f.to_object(py),
             Self::Str(s) => s.to_object(py),
-            Self::List(v) => PyList::new_bound(py, v.iter().map(|v| v.to_object(py))).to_object(py),
+            Self::Bytes(b) => PyBytes::new_bound(py, b).into_any().unbind(),
+            Self::Uuid(u) => uuid_to_py(py, u)?,
+            Self::Datetime(dt) => datetime_to_py(py, dt)?,
+            Self::Decimal(d) => decimal_to_py(py, d)?,
+            Self::List(v) => PyList::new_bound(py, to_py_items(v)?).into_any().unbind(),
+            Self::Tuple(v) => PyTuple::new_bound(py, to_py_items(v)?).into_any().unbind(),
+            Self::Set(v) => PySet::new_bound(py, &to_py_items(v)?)?.into_any().unbind(),
             Self::Dict(o) => {
                 let dict = PyDict::new_bound(py);
                 for (k, v) in o.iter() {
-                    dict.set_item(k, v.to_object(py)).unwrap();
+                    dict.set_item(k.to_py(py)?, v.to_py(py)?)?;
                 }
-                dict.to_object(py)
+                dict.into_any().unbind()
             }
-        }
+            Self::Py(py_obj) => py_obj.clone_ref(py),
+        })
     }
 }
 
@@ -178,3 +684,137 @@ impl From<&str> for RawData {
         RawData::Str(v.to_owned())
     }
 }
+
+impl From<Vec<u8>> for RawData {
+    fn from(v: Vec<u8>) -> Self {
+        RawData::Bytes(v)
+    }
+}
+
+impl From<DateTime> for RawData {
+    fn from(v: DateTime) -> Self {
+        RawData::Datetime(v)
+    }
+}
+
+impl From<Uuid> for RawData {
+    fn from(v: Uuid) -> Self {
+        RawData::Uuid(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camel_alias() {
+        let camel = AliasGenerator::new("camel").unwrap();
+        assert_eq!(camel.alias("user_id"), "userId");
+        assert_eq!(camel.alias("id"), "id");
+        assert_eq!(camel.alias("created_at_utc"), "createdAtUtc");
+        // empty words from leading, trailing or repeated underscores are dropped
+        assert_eq!(camel.alias("_private__name_"), "privateName");
+        assert_eq!(camel.alias("Already_Camel"), "alreadyCamel");
+        assert_eq!(camel.alias("é_école"), "éÉcole");
+        assert!(AliasGenerator::new("snake").is_err());
+    }
+
+    #[test]
+    fn raw_data_eq() {
+        assert_eq!(RawData::None, RawData::None);
+        assert_eq!(RawData::from(1i64), RawData::from(1i64));
+        assert_eq!(RawData::from("a"), RawData::from("a".to_string()));
+        // different variants are never equal
+        assert_ne!(RawData::from(1i64), RawData::from(1.0));
+        assert_ne!(RawData::from(true), RawData::from(1i64));
+        assert_ne!(RawData::from("a"), RawData::from(b"a".to_vec()));
+        // floats compare as IEEE 754
+        assert_ne!(RawData::from(f64::NAN), RawData::from(f64::NAN));
+        assert_eq!(RawData::from(0.0), RawData::from(-0.0));
+        // decimals by their string form
+        assert_eq!(
+            RawData::Decimal("1.0".into()),
+            RawData::Decimal("1.0".into())
+        );
+        assert_ne!(
+            RawData::Decimal("1.0".into()),
+            RawData::Decimal("1.00".into())
+        );
+    }
+
+    fn list(items: impl IntoIterator<Item = RawData>) -> RawList {
+        Arc::new(items.into_iter().collect())
+    }
+
+    #[test]
+    fn raw_data_eq_containers() {
+        let (a, b) = (RawData::from(1i64), RawData::from(2i64));
+        assert_eq!(
+            RawData::List(list([a.clone(), b.clone()])),
+            RawData::List(list([a.clone(), b.clone()]))
+        );
+        // lists and tuples are ordered, and not equal to each other
+        assert_ne!(
+            RawData::List(list([a.clone(), b.clone()])),
+            RawData::List(list([b.clone(), a.clone()]))
+        );
+        assert_ne!(
+            RawData::List(list([a.clone()])),
+            RawData::Tuple(list([a.clone()]))
+        );
+        // sets and dicts aren't
+        assert_eq!(
+            RawData::Set(list([a.clone(), b.clone()])),
+            RawData::Set(list([b.clone(), a.clone()]))
+        );
+        assert_ne!(
+            RawData::Set(list([a.clone()])),
+            RawData::Set(list([a.clone(), b.clone()]))
+        );
+        let dict =
+            |items: &[(RawData, RawData)]| RawData::Dict(Arc::new(items.iter().cloned().collect()));
+        assert_eq!(
+            dict(&[(a.clone(), b.clone()), (b.clone(), a.clone())]),
+            dict(&[(b.clone(), a.clone()), (a.clone(), b.clone())])
+        );
+        assert_ne!(
+            dict(&[(a.clone(), b.clone())]),
+            dict(&[(a.clone(), a.clone())])
+        );
+        // a NaN item makes the containers unequal too
+        let nan = RawData::List(list([RawData::from(f64::NAN)]));
+        assert_ne!(nan, nan.clone());
+    }
+
+    #[test]
+    fn raw_data_eq_datetime() {
+        let parse = |s: &str| RawData::from(DateTime::parse_str(s).unwrap());
+        assert_eq!(
+            parse("2020-01-01T00:00:00Z"),
+            parse("2020-01-01T00:00:00+00:00")
+        );
+        // the same instant in a different timezone isn't equal
+        assert_ne!(
+            parse("2020-01-01T00:00:00Z"),
+            parse("2020-01-01T01:00:00+01:00")
+        );
+    }
+
+    #[test]
+    fn raw_data_to_py() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let raw = RawData::List(list([RawData::from(1i64), RawData::from("a")]));
+            let py_list = raw.to_py(py).unwrap();
+            let expected = PyList::new_bound(py, [1.to_object(py), "a".to_object(py)]);
+            assert!(py_list.bind(py).eq(expected).unwrap());
+            // python's datetime starts at year 1, the error is raised rather than panicking
+            let year_0 = DateTime::parse_str("0000-01-01T00:00:00").unwrap();
+            assert!(RawData::from(year_0).to_py(py).is_err());
+            // unhashable set items are a `TypeError` like python
+            let set = RawData::Set(list([RawData::List(list([]))]));
+            assert!(set.to_py(py).is_err());
+        });
+    }
+}
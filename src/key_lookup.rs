@@ -0,0 +1,78 @@
+use ahash::AHashMap;
+
+/// Models with at most this many fields use `KeyLookup::Linear`
+const LINEAR_MAX_FIELDS: usize = 8;
+
+/// Maps field names to their index, small models scan a `Vec` as that's faster than hashing
+#[derive(Debug)]
+pub enum KeyLookup {
+    Linear(Vec<(String, usize)>),
+    Map(AHashMap<String, usize>),
+}
+
+impl KeyLookup {
+    pub fn new<'a>(names: impl ExactSizeIterator<Item = &'a str>) -> Self {
+        if names.len() <= LINEAR_MAX_FIELDS {
+            Self::new_linear(names)
+        } else {
+            Self::new_map(names)
+        }
+    }
+
+    pub fn new_linear<'a>(names: impl Iterator<Item = &'a str>) -> Self {
+        Self::Linear(
+            names
+                .enumerate()
+                .map(|(i, name)| (name.to_owned(), i))
+                .collect(),
+        )
+    }
+
+    pub fn new_map<'a>(names: impl Iterator<Item = &'a str>) -> Self {
+        Self::Map(
+            names
+                .enumerate()
+                .map(|(i, name)| (name.to_owned(), i))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<usize> {
+        match self {
+            Self::Linear(items) => items
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, index)| *index),
+            Self::Map(map) => map.get(key).copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 10] = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+
+    #[test]
+    fn new_picks_by_field_count() {
+        let small = KeyLookup::new(NAMES[..LINEAR_MAX_FIELDS].iter().copied());
+        assert!(matches!(small, KeyLookup::Linear(_)));
+        let large = KeyLookup::new(NAMES.iter().copied());
+        assert!(matches!(large, KeyLookup::Map(_)));
+    }
+
+    #[test]
+    fn get() {
+        for lookup in [
+            KeyLookup::new_linear(NAMES.iter().copied()),
+            KeyLookup::new_map(NAMES.iter().copied()),
+        ] {
+            for (index, name) in NAMES.iter().enumerate() {
+                assert_eq!(lookup.get(name), Some(index));
+            }
+            assert_eq!(lookup.get("A"), None);
+            assert_eq!(lookup.get(""), None);
+        }
+    }
+}
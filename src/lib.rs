@@ -1,49 +1,719 @@
+// pyo3 0.22's `#[pymethods]` expansion trips this lint on every `PyResult` return type
+#![allow(clippy::useless_conversion)]
+
+use std::borrow::Cow;
+
+use jiter::{Jiter, Peek};
+use pyo3::exceptions::PyTypeError;
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyDict, PyList};
 
-use crate::validators::{CombinedValidator, Validator};
+use crate::errors::{errors_list, ErrorOptions, LineError};
+use crate::field::get_as_opt;
+use crate::model_data::ModelData;
+use crate::validation_cache::ValidationCache;
+use crate::validators::{json_bytes, CombinedValidator, Definitions, DEFAULT_MAX_DEPTH};
 
+mod base64;
 mod errors;
 mod field;
+pub mod key_lookup;
 mod model_data;
 mod model_validator;
+#[cfg(test)]
+mod test_utils;
+mod validation_cache;
 mod validators;
 
+// for libraries adding validators for their own schema types, see `register_validator`
+pub use crate::errors::{ErrorType, ValError, ValResult};
+pub use crate::field::FieldValue;
+pub use crate::validators::{register_validator, ValidationState, Validator, ValidatorFactory};
+
 #[derive(Debug)]
 #[pyclass(module = "rustmodel")]
 pub struct SchemaValidator {
     validator: CombinedValidator,
+    // owns the validators which "definition-ref" schemas hold weak references to
+    _definitions: Definitions,
+    strict: bool,
+    // used in `ValidationError` headers, from the schema's "title" or else the model class name or type
+    title: String,
+    error_options: ErrorOptions,
+    // from the schema's "max_depth", the number of nested JSON arrays and objects containers will validate
+    max_depth: u16,
+    // from `cache_size`, models from `validate_json` with the default options are cached by their input
+    cache: Option<ValidationCache>,
 }
 
 #[pymethods]
 impl SchemaValidator {
+    /// With `cache_size`, `validate_json` returns copies of models cached by their input
     #[new]
-    fn new(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
-        CombinedValidator::new(schema).map(|validator| Self { validator })
+    #[pyo3(signature = (schema, *, cache_size = 0))]
+    pub fn new(schema: &Bound<'_, PyDict>, cache_size: usize) -> PyResult<Self> {
+        let strict = get_as_opt(schema, "strict")?.unwrap_or(false);
+        let definitions = Definitions::new(schema, strict)?;
+        let validator = CombinedValidator::new(schema, strict, &definitions)?;
+        let title = match get_as_opt(schema, "title")? {
+            Some(title) => title,
+            None => validator.title().to_string(),
+        };
+        Ok(Self {
+            validator,
+            _definitions: definitions,
+            strict,
+            title,
+            error_options: ErrorOptions::new(schema)?,
+            max_depth: get_as_opt(schema, "max_depth")?.unwrap_or(DEFAULT_MAX_DEPTH),
+            cache: (cache_size > 0).then(|| ValidationCache::new(cache_size)),
+        })
+    }
+
+    /// `strict` overrides the schema's for this call, `context` is passed to function validators
+    #[pyo3(signature = (data, *, strict = None, context = None))]
+    pub fn validate_python<'py>(
+        &self,
+        py: Python,
+        data: &Bound<'py, PyAny>,
+        strict: Option<bool>,
+        context: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        let mut state = ValidationState::new(py, strict, context.as_ref());
+        match self
+            .validator
+            .validate_python(&mut state, data)
+            .map_err(|e| e.with_py_input(data))
+        {
+            Ok(f) => f.into_py_object(py),
+            Err(e) => Err(e.into_py_err(py, &self.title, self.error_options)),
+        }
+    }
+
+    /// With `allow_partial`, JSON which ends early validates the values which were complete
+    #[pyo3(
+        name = "validate_json",
+        signature = (json_data, *, strict = None, context = None, allow_partial = false)
+    )]
+    fn py_validate_json(
+        &self,
+        py: Python,
+        json_data: &Bound<'_, PyAny>,
+        strict: Option<bool>,
+        context: Option<PyObject>,
+        allow_partial: bool,
+    ) -> PyResult<PyObject> {
+        let json_data = json_data_bytes(json_data)
+            .map_err(|e| e.into_py_err(py, &self.title, self.error_options))?;
+        self.validate_json(py, &json_data, strict, context, allow_partial)
+    }
+
+    /// Validate data where every value is a string, e.g. form or query data
+    #[pyo3(signature = (data, *, strict = None, context = None))]
+    fn validate_strings(
+        &self,
+        py: Python,
+        data: &Bound<'_, PyDict>,
+        strict: Option<bool>,
+        context: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        let mut state = ValidationState::new(py, strict, context.as_ref());
+        match self
+            .validator
+            .validate_strings(&mut state, data)
+            .map_err(|e| e.with_py_input(data))
+        {
+            Ok(f) => f.into_py_object(py),
+            Err(e) => Err(e.into_py_err(py, &self.title, self.error_options)),
+        }
     }
 
-    fn validate_python<'py>(&self, py: Python, data: &Bound<'py, PyAny>) -> PyResult<PyObject> {
-        match self.validator.validate_python(py, data) {
-            Ok(f) => Ok(f.into_py(py)),
-            Err(e) => Err(e.to_py_err(py)),
+    /// Validate a partial dict for a model schema, returning a dict of only the supplied fields
+    #[pyo3(signature = (data, *, strict = None, context = None))]
+    fn validate_partial(
+        &self,
+        py: Python,
+        data: &Bound<'_, PyAny>,
+        strict: Option<bool>,
+        context: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        let mut state = ValidationState::new(py, strict, context.as_ref());
+        let CombinedValidator::Model(validator) = &self.validator else {
+            return Err(PyTypeError::new_err(
+                "validate_partial is only supported for model schemas",
+            ));
+        };
+        match validator
+            .validate_partial(&mut state, data)
+            .map_err(|e| e.with_py_input(data))
+        {
+            Ok(f) => f.into_py_object(py),
+            Err(e) => Err(e.into_py_err(py, &self.title, self.error_options)),
         }
     }
 
-    fn validate_json(&self, py: Python, json_data: &[u8]) -> PyResult<PyObject> {
-        let mut jiter = jiter::Jiter::new(json_data);
-        match self.validator.validate_json(py, &mut jiter) {
-            Ok(f) => Ok(f.into_py(py)),
-            Err(e) => Err(e.to_py_err(py)),
+    /// Like `validate_python` for a model schema, but `(instance, errors)` is returned rather than raising
+    #[pyo3(signature = (data, *, strict = None, context = None))]
+    fn validate_python_collect(
+        &self,
+        py: Python,
+        data: &Bound<'_, PyAny>,
+        strict: Option<bool>,
+        context: Option<PyObject>,
+    ) -> PyResult<(Option<PyObject>, PyObject)> {
+        let mut state = ValidationState::new(py, strict, context.as_ref());
+        let CombinedValidator::Model(validator) = &self.validator else {
+            return Err(PyTypeError::new_err(
+                "validate_python_collect is only supported for model schemas",
+            ));
+        };
+        let (instance, errors) = match validator
+            .validate_collect(&mut state, data)
+            .map_err(|e| e.with_py_input(data))
+        {
+            Ok((instance, errors)) => (Some(instance.into_py_object(py)?), errors),
+            Err(ValError::LineErrors(errors)) => (None, errors),
+            Err(ValError::InternalError(e)) => return Err(e),
+        };
+        Ok((
+            instance,
+            errors_list(py, errors, self.error_options)?.into(),
+        ))
+    }
+
+    /// Like `validate_python` for a model schema, but the `ModelData` is returned without an instance
+    #[pyo3(signature = (data, *, strict = None, context = None))]
+    fn validate_python_to_data(
+        &self,
+        py: Python,
+        data: &Bound<'_, PyAny>,
+        strict: Option<bool>,
+        context: Option<PyObject>,
+    ) -> PyResult<Py<ModelData>> {
+        let mut state = ValidationState::new(py, strict, context.as_ref());
+        let CombinedValidator::Model(validator) = &self.validator else {
+            return Err(PyTypeError::new_err(
+                "validate_python_to_data is only supported for model schemas",
+            ));
+        };
+        match validator
+            .validate_to_data(&mut state, data)
+            .map_err(|e| e.with_py_input(data))
+        {
+            Ok(model_data) => Py::new(py, model_data),
+            Err(e) => Err(e.into_py_err(py, &self.title, self.error_options)),
+        }
+    }
+
+    /// `(name, required, has_default)` for each field of a model schema
+    fn fields(&self) -> PyResult<Vec<(String, bool, bool)>> {
+        let CombinedValidator::Model(validator) = &self.validator else {
+            return Err(PyTypeError::new_err(
+                "fields is only supported for model schemas",
+            ));
+        };
+        Ok(validator
+            .field_info()
+            .iter()
+            .map(|info| {
+                let has_default = !info.required && info.default_factory.is_none();
+                (info.name.clone(), info.required, has_default)
+            })
+            .collect())
+    }
+
+    /// Validate each item of a top level JSON array or of newline delimited JSON
+    #[pyo3(signature = (json_data, *, strict = None, context = None))]
+    fn validate_json_many(
+        &self,
+        py: Python,
+        json_data: &Bound<'_, PyAny>,
+        strict: Option<bool>,
+        context: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        let mut state =
+            ValidationState::new(py, strict, context.as_ref()).with_max_depth(self.max_depth);
+        let title = format!("list[{}]", self.title);
+        let json_data = json_data_bytes(json_data)
+            .map_err(|e| e.into_py_err(py, &title, self.error_options))?;
+        let mut jiter = Jiter::new(&json_data);
+        match self
+            .validate_many(&mut state, &mut jiter)
+            .map_err(|e| e.with_json_position(&json_data))
+        {
+            Ok(items) => Ok(PyList::new_bound(py, items).into()),
+            Err(e) => Err(e.into_py_err(py, &title, self.error_options)),
         }
     }
 
     fn __repr__(&self) -> String {
-        format!("SchemaValidator(validator={:#?})", self.validator)
+        format!(
+            "SchemaValidator(validator={:#?}, strict={})",
+            self.validator, self.strict
+        )
     }
 }
 
+impl SchemaValidator {
+    /// `validate_json` from python once the input is bytes, also used by benchmarks
+    pub fn validate_json(
+        &self,
+        py: Python,
+        json_data: &[u8],
+        strict: Option<bool>,
+        context: Option<PyObject>,
+        allow_partial: bool,
+    ) -> PyResult<PyObject> {
+        // options could change the result, so only validation with the defaults is cached
+        let cache = match (strict, &context, allow_partial) {
+            (None, None, false) => self.cache.as_ref(),
+            _ => None,
+        };
+        if let Some(cache) = cache {
+            if let Some(instance) = cache.get(py, json_data)? {
+                return Ok(instance);
+            }
+        }
+        let mut state = ValidationState::new(py, strict, context.as_ref())
+            .with_allow_partial(allow_partial)
+            .with_max_depth(self.max_depth);
+        let mut jiter = Jiter::new(json_data);
+        let start = jiter.clone();
+        let mut result = self.validator.validate_json(&mut state, &mut jiter);
+        if matches!(&result, Err(ValError::LineErrors(errors)) if errors.is_empty()) {
+            // partial input ended within a union before any choice found an error, that's invalid JSON as when
+            // it ends within a string or number
+            result = start.clone().next_skip().map_err(Into::into).and(result);
+        }
+        match result.map_err(|e| e.with_json_input(start).with_json_position(json_data)) {
+            Ok(FieldValue::Model(instance, model_data)) => {
+                if let Some(cache) = cache {
+                    cache.insert(py, json_data, model_data.bind(py))?;
+                }
+                Ok(instance)
+            }
+            Ok(f) => f.into_py_object(py),
+            Err(e) => Err(e.into_py_err(py, &self.title, self.error_options)),
+        }
+    }
+
+    fn validate_many(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<Vec<PyObject>> {
+        let mut items = Vec::new();
+        let mut errors: Vec<LineError> = Vec::new();
+        // `finish` succeeds once only whitespace is left, so empty input gives an empty list
+        if jiter.finish().is_ok() {
+            return Ok(items);
+        }
+        let mut index: i64 = 0;
+        if jiter.peek()? == Peek::Array {
+            let mut next = jiter.known_array()?;
+            while next.is_some() {
+                self.validate_item(state, jiter, index, &mut items, &mut errors)?;
+                index += 1;
+                next = jiter.array_step()?;
+            }
+            jiter.finish()?;
+        } else {
+            while jiter.finish().is_err() {
+                self.validate_item(state, jiter, index, &mut items, &mut errors)?;
+                index += 1;
+            }
+        }
+        if errors.is_empty() {
+            Ok(items)
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    fn validate_item(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+        index: i64,
+        items: &mut Vec<PyObject>,
+        errors: &mut Vec<LineError>,
+    ) -> ValResult<()> {
+        match self.validator.validate_json_item(state, jiter)? {
+            Some(Ok(item)) => items.push(item.into_py_object(state.py)?),
+            Some(Err(e)) => errors.extend(e.line_errors_with_loc(index)?),
+            None => (),
+        }
+        Ok(())
+    }
+}
+
+/// `json_bytes` where other types are a `TypeError` rather than a validation error
+fn json_data_bytes<'a>(json_data: &'a Bound<'_, PyAny>) -> ValResult<Cow<'a, [u8]>> {
+    json_bytes(json_data)?.ok_or_else(|| {
+        let msg = "json_data should be str, bytes or bytearray";
+        PyTypeError::new_err(msg).into()
+    })
+}
+
 #[pymodule]
 fn rustmodel(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<SchemaValidator>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::run_python;
+
+    #[test]
+    fn validate_json_many() {
+        run_python(
+            r#"
+            v = model_validator(field('a', {'type': 'int'}), field('b', {'type': 'string'}, default='d'))
+            def many(data):
+                return [(m.a, m.b) for m in v.validate_json_many(data)]
+
+            for data in [b'[]', b'  ', b'']:
+                assert many(data) == [], data
+            # either a JSON array or JSON lines
+            for data in [b'[{"a": 1}, {"a": 2, "b": "x"}]', b'{"a": 1}\n{"a": 2, "b": "x"}\n']:
+                assert many(data) == [(1, 'd'), (2, 'x')], data
+            assert errors(many, b'[{"a": 1}, {"a": "x"}]') == [('int_type', (1, 'a'))]
+            assert errors(many, b'{"a": 1}\n{"a": "x"}') == [('int_type', (1, 'a'))]
+            assert errors(many, b'[{"a": "x"}, {"a": 1}, [1,2], 4, {"b": 1}]') == [
+                ('int_type', (0, 'a')), ('dict_type', (2,)), ('dict_type', (3,)), ('missing', (4, 'a')), ('string_type', (4, 'b')),
+            ]
+            for data in [b'[{"a": 1}, {"a": 1', b'[{"a": 1}] x', b'{"a": 1}\n{"a": ', b'[{"a": "x"}, {"a": 1, ]']:
+                assert errors(many, data) == [('json_invalid', ())], data
+            "#,
+        );
+    }
+
+    #[test]
+    fn validate_strings() {
+        run_python(
+            r#"
+            import datetime, uuid
+            nested = {'type': 'model', 'cls': Model, 'fields': [field('x', {'type': 'int'})]}
+            fields = [
+                field('i', {'type': 'int', 'gt': 0}),
+                field('b', {'type': 'bool'}),
+                field('s', {'type': 'string'}),
+                field('u', {'type': 'uuid'}),
+                field('dt', {'type': 'datetime'}),
+                field('l', {'type': 'list', 'items_schema': {'type': 'int'}}, default=None),
+                field('d', {'type': 'dict', 'values_schema': {'type': 'int'}}, default=None),
+                field('o', {'type': 'union', 'choices': [{'type': 'int'}, {'type': 'bool'}]}, default=None),
+                field('n', nested, default=None),
+            ]
+            data = {
+                'i': '5', 'b': 'true', 's': 'x', 'u': str(uuid.UUID(int=1)), 'dt': '2020-01-01T00:00:00Z',
+                'l': ['1', '2'], 'd': {'a': '3'}, 'o': 'yes', 'n': {'x': '7'},
+            }
+            expected = {
+                'i': 5, 'b': True, 's': 'x', 'u': uuid.UUID(int=1),
+                'dt': datetime.datetime(2020, 1, 1, tzinfo=datetime.timezone.utc),
+                'l': [1, 2], 'd': {'a': 3}, 'o': True, 'n': {'x': 7},
+            }
+            # strings are parsed even in strict mode, as all values in form data or query strings are strings
+            for strict in [False, True]:
+                v = model_validator(*fields, strict=strict)
+                assert v.validate_strings(data).__pydantic_model_data__.model_dump() == expected, strict
+                assert errors(v.validate_strings, {'i': '0', 'b': 'maybe', 'l': ['q']}) == [
+                    ('bool_type', ('b',)), ('missing', ('dt',)), ('greater_than', ('i',)),
+                    ('int_type', ('l', 0)), ('missing', ('s',)), ('missing', ('u',)),
+                ]
+            assert ('int_type', ('i',)) in errors(v.validate_python, {k: data[k] for k in ['i', 'b', 's', 'u', 'dt']})
+            "#,
+        );
+    }
+
+    #[test]
+    fn fields() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'int'}, default=None),
+                field('c', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=list),
+            )
+            # `(name, required, has_default)`
+            assert v.fields() == [('a', True, False), ('b', False, True), ('c', False, False)]
+            try:
+                SchemaValidator({'type': 'int'}).fields()
+            except TypeError as e:
+                assert str(e) == 'fields is only supported for model schemas', e
+            else:
+                raise AssertionError('expected a TypeError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn partial_json() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'string'}),
+                field('c', {'type': 'list', 'items_schema': {'type': 'int'}}),
+            )
+            def dump(json):
+                return v.validate_json(json, allow_partial=True).__pydantic_model_data__.model_dump()
+
+            # cut off mid-value, the value is dropped and the missing fields aren't required
+            assert dump(b'{"a": 1, "b": "hel') == {'a': 1, 'b': None, 'c': None}
+            assert dump(b'{"a": 1, "b": "x", "c": [1, 2') == {'a': 1, 'b': 'x', 'c': [1, 2]}
+            assert dump(b'{"a": 1, "b": "x", "c": [1, 2, "3') == {'a': 1, 'b': 'x', 'c': [1, 2]}
+            # a number at the end of the input may be complete
+            assert dump(b'{"a": 12') == {'a': 12, 'b': None, 'c': None}
+            assert dump(b'{"a": 1, "b": "x", "c": [1, 2, 3]}') == {'a': 1, 'b': 'x', 'c': [1, 2, 3]}
+            # complete values are still validated
+            assert errors(dump, b'{"a": "x", "b": "hel') == [('int_type', ('a',))]
+            assert errors(dump, b'{"a": 1, "c": [1, "x", 2') == [('int_type', ('c', 1))]
+            # without allow_partial it's invalid JSON
+            assert errors(v.validate_json, b'{"a": 1, "b": "hel') == [('json_invalid', ())]
+            "#,
+        );
+    }
+
+    #[test]
+    fn partial_json_union() {
+        run_python(
+            r#"
+            union = {'type': 'union', 'choices': [
+                {'type': 'list', 'items_schema': {'type': 'int'}},
+                {'type': 'string'},
+            ]}
+            tagged = {'type': 'tagged-union', 'discriminator': 'k', 'choices': {
+                'a': {'type': 'typed-dict', 'fields': [
+                    field('k', {'type': 'string'}), field('x', {'type': 'int'}),
+                ]},
+            }}
+            v = model_validator(
+                field('u', union, required=False, default=None),
+                field('t', tagged, required=False, default=None),
+            )
+            def dump(json):
+                return v.validate_json(json, allow_partial=True).__pydantic_model_data__.model_dump()
+
+            assert dump(b'{"u": [1, 2') == {'u': [1, 2], 't': None}
+            assert dump(b'{"u": "ab') == {'u': None, 't': None}
+            assert dump(b'{"u": "ab", "t": {"k": "a", "x": 1') == {'u': 'ab', 't': {'k': 'a', 'x': 1}}
+            assert dump(b'{"t": {"x": 1, "k"') == {'u': None, 't': None}
+            assert dump(b'{"t": {"k": "a') == {'u': None, 't': None}
+            # errors for the parts which were complete are reported
+            assert errors(dump, b'{"u": [1, "x", 2') == [('int_type', ('u', 'list', 1))]
+            assert errors(dump, b'{"t": {"k": "b", "x": 1') == [('union_tag_invalid', ('t',))]
+            assert errors(dump, b'{"t": {"k": "a", "x": "y", "z') == [('int_type', ('t', 'a', 'x'))]
+
+            # at the top level a value cut off before any choice could find an error is invalid JSON
+            top = SchemaValidator(union)
+            assert top.validate_json(b'[1, 2', allow_partial=True) == [1, 2]
+            assert errors(top.validate_json, b'"ab', allow_partial=True) == [('json_invalid', ())]
+            assert errors(top.validate_json, b'[1, "x"', allow_partial=True) == [('int_type', ('list', 1))]
+            top = SchemaValidator(tagged)
+            assert errors(top.validate_json, b'{"x": 1', allow_partial=True) == [('json_invalid', ())]
+            "#,
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        run_python(
+            r#"
+            def nested_list(n):
+                schema = {'type': 'int'}
+                for _ in range(n):
+                    schema = {'type': 'list', 'items_schema': schema}
+                return schema
+
+            # the default limit is 100 levels
+            assert len(SchemaValidator(nested_list(100)).validate_json(b'[' * 100 + b'1' + b']' * 100)) == 1
+            e = errors(SchemaValidator(nested_list(101)).validate_json, b'[' * 101 + b'1' + b']' * 101)
+            assert e == [('json_too_deep', (0,) * 100)], e
+
+            v = SchemaValidator({**nested_list(3), 'max_depth': 2})
+            assert errors(v.validate_json, b'[[[1], [2]], [[3]]]') == [
+                ('json_too_deep', (0, 0)), ('json_too_deep', (0, 1)), ('json_too_deep', (1, 0)),
+            ]
+            # only JSON input is limited
+            assert v.validate_python([[[1]]]) == [[[1]]]
+            assert SchemaValidator({**nested_list(3), 'max_depth': 3}).validate_json(b'[[[1], [2]], [[3]]]') == [[[1], [2]], [[3]]]
+
+            # with a recursive schema the error is where the limit was hit, siblings are still validated
+            v = SchemaValidator({
+                'type': 'definition-ref', 'schema_ref': 'node', 'max_depth': 3,
+                'definitions': {'node': {'type': 'model', 'cls': Model, 'fields': [
+                    field('v', {'type': 'int'}),
+                    field('children', {'type': 'list', 'items_schema': {'type': 'definition-ref', 'schema_ref': 'node'}}, default=[]),
+                ]}},
+            })
+            assert errors(v.validate_json, b'{"v": 1, "children": [{"v": 2, "children": [{"v": 3}]}, {"v": 4}]}') == [
+                ('json_too_deep', ('children', 0, 'children')),
+            ]
+            assert v.validate_json(b'{"v": 1, "children": [{"v": 2}]}').children[0].v == 2
+            assert errors(v.validate_json_many, b'{"v": 1, "children": [{"v": 2, "children": []}]}') == [
+                ('json_too_deep', (0, 'children', 0, 'children')),
+            ]
+            "#,
+        );
+    }
+
+    #[test]
+    fn cache() {
+        run_python(
+            r#"
+            import threading
+            inner = {'type': 'model', 'cls': Model, 'fields': [field('x', {'type': 'int'})]}
+            schema = {'type': 'model', 'cls': Model, 'fields': [
+                field('a', {'type': 'int'}),
+                field('tags', {'type': 'list', 'items_schema': {'type': 'string'}}),
+                field('inner', inner),
+                field('d', {'type': 'int'}, default_factory=lambda: 7),
+            ]}
+            v = SchemaValidator(schema, cache_size=2)
+            dump = lambda m: m.__pydantic_model_data__.model_dump()
+            data = b'{"a": 1, "tags": ["x", "y"], "inner": {"x": 2}}'
+            expected = {'a': 1, 'tags': ['x', 'y'], 'inner': {'x': 2}, 'd': 7}
+            m1 = v.validate_json(data)
+            m2 = v.validate_json(data)
+            assert dump(m1) == dump(m2) == expected
+            # each hit is a new model, so mutating one doesn't affect the cache
+            assert m1 is not m2 and m1.inner is not m2.inner and m1.tags is not m2.tags
+            m2.tags.append('z')
+            m2.a = 5
+            assert dump(v.validate_json(data)) == expected
+
+            # errors aren't cached, and neither are results for other options
+            for _ in range(2):
+                assert errors(v.validate_json, b'{"a": "x"}')[0] == ('int_type', ('a',))
+            data = b'{"a": "1", "tags": [], "inner": {"x": 1}}'
+            assert errors(v.validate_json, data, strict=True) == [('int_type', ('a',))]
+            assert v.validate_json(data).a == 1
+            for i in range(5):
+                v.validate_json(b'{"a": %d, "tags": [], "inner": {"x": 1}}' % i)
+            assert dump(v.validate_json(b'{"a": 4, "tags": [], "inner": {"x": 1}}'))['a'] == 4
+
+            def work():
+                for _ in range(200):
+                    assert dump(v.validate_json(b'{"a": 1, "tags": [], "inner": {"x": 1}}'))['a'] == 1
+            threads = [threading.Thread(target=work) for _ in range(4)]
+            for t in threads:
+                t.start()
+            for t in threads:
+                t.join()
+
+            # a hit skips validation, including functions
+            calls = []
+            def count(x):
+                calls.append(x)
+                return x
+            c = SchemaValidator({'type': 'model', 'cls': Model, 'fields': [
+                field('n', {'type': 'function-after', 'function': count, 'schema': {'type': 'int'}}),
+            ]}, cache_size=4)
+            for _ in range(3):
+                assert c.validate_json(b'{"n": 1}').n == 1
+            c.validate_json(b'{"n": 2}')
+            c.validate_json(b'{"n": 1}', strict=False)
+            assert calls == [1, 2, 1]
+
+            assert SchemaValidator({'type': 'list', 'items_schema': {'type': 'int'}}, cache_size=1).validate_json(b'[1]') == [1]
+            try:
+                SchemaValidator(schema, cache_size=-1)
+            except OverflowError:
+                pass
+            else:
+                raise AssertionError('expected an OverflowError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn validate_python_collect() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('name', {'type': 'string'}),
+                field('age', {'type': 'int'}),
+                field('email', {'type': 'string', 'min_length': 3}),
+                field('tags', {'type': 'list', 'items_schema': {'type': 'string'}}, default_factory=list),
+            )
+            # the valid fields are kept alongside the errors
+            m, errs = v.validate_python_collect({'name': 'Ann', 'age': 'old', 'email': 'x'})
+            assert type(m) is Model and m.name == 'Ann' and m.tags == []
+            assert m.__pydantic_model_data__.fields_set() == {'name'}
+            assert m.__pydantic_model_data__.model_dump() == {'name': 'Ann', 'age': None, 'email': None, 'tags': []}
+            assert [(e['error_type'], e['location'], e['input']) for e in errs] == [
+                ('int_type', ['age'], 'old'), ('string_too_short', ['email'], 'x'),
+            ]
+            m, errs = v.validate_python_collect({'name': 'Bo', 'age': 3, 'email': 'b@c'})
+            assert m.age == 3 and errs == []
+            m, errs = v.validate_python_collect({'age': 1})
+            assert m.age == 1 and [e['location'] for e in errs] == [['email'], ['name']]
+            m, errs = v.validate_python_collect([1])
+            assert m is None and [e['error_type'] for e in errs] == ['dict_type']
+
+            td = SchemaValidator({'type': 'typed-dict', 'fields': [field('a', {'type': 'int'}), field('b', {'type': 'int'}, default=2)]})
+            d, errs = td.validate_python_collect({'a': 'x'})
+            assert d == {'b': 2} and [e['error_type'] for e in errs] == ['int_type']
+            try:
+                SchemaValidator({'type': 'int'}).validate_python_collect(1)
+            except TypeError as e:
+                assert str(e) == 'validate_python_collect is only supported for model schemas', e
+            else:
+                raise AssertionError('expected a TypeError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn json_data_types() {
+        run_python(
+            r#"
+            v = model_validator(field('a', {'type': 'int'}), field('s', {'type': 'string'}))
+            json = '{"a": 1, "s": "café \U0001f600"}'
+            for data in [json, json.encode(), bytearray(json.encode())]:
+                m = v.validate_json(data)
+                assert (m.a, m.s) == (1, 'café \U0001f600'), type(data)
+            assert errors(v.validate_json, '{"a": 1, "s": "\ud800"}') == [('string_unicode', ())]
+            assert errors(v.validate_json_many, '\udfff') == [('string_unicode', ())]
+            for data in [123, memoryview(b'{}')]:
+                try:
+                    v.validate_json(data)
+                except TypeError as e:
+                    assert str(e) == 'json_data should be str, bytes or bytearray', e
+                else:
+                    raise AssertionError(f'expected a TypeError for {data!r}')
+            assert [m.a for m in v.validate_json_many('[{"a": 1, "s": ""}, {"a": 2, "s": ""}]')] == [1, 2]
+            assert len(v.validate_json_many(bytearray(b'{"a": 1, "s": ""}\n{"a": 2, "s": ""}'))) == 2
+            assert v.validate_json('{"a": 1, "s": "lo', allow_partial=True).a == 1
+            "#,
+        );
+    }
+
+    #[test]
+    fn validate_python_to_data() {
+        run_python(
+            r#"
+            v = model_validator(field('a', {'type': 'int'}), field('b', {'type': 'string'}, default='x'))
+            data = v.validate_python_to_data({'a': '1'})
+            assert type(data).__name__ == 'ModelData'
+            assert (data.get_attr('a'), data.get_attr('b')) == (1, 'x')
+            assert repr(data) == "Model(a=1, b='x')"
+            assert errors(v.validate_python_to_data, {'b': 1}) == [('missing', ('a',))]
+            assert errors(v.validate_python_to_data, {'a': 'x'}, strict=True) == [('int_type', ('a',))]
+            for schema, msg in [
+                ({'type': 'typed-dict', 'fields': [field('a', {'type': 'int'})]}, 'typed-dict schemas validate to a dict, not model data'),
+                ({'type': 'int'}, 'validate_python_to_data is only supported for model schemas'),
+            ]:
+                try:
+                    SchemaValidator(schema).validate_python_to_data({'a': 1})
+                except TypeError as e:
+                    assert str(e) == msg, e
+                else:
+                    raise AssertionError(f'expected a TypeError for {schema}')
+            "#,
+        );
+    }
+}
@@ -1,131 +1,894 @@
+use std::io::{self, Write};
 use std::sync::Arc;
 
-use pyo3::exceptions::{PyAttributeError, PyValueError};
+use pyo3::exceptions::{PyAttributeError, PyTypeError, PyValueError};
+use pyo3::intern;
 use pyo3::prelude::*;
-use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString};
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{
+    PyBool, PyBytes, PyDateTime, PyDict, PyFloat, PyFrozenSet, PyInt, PyList, PySet, PyString,
+    PyTuple, PyType,
+};
 
-use ahash::AHashMap;
+use ahash::AHashSet;
 use serde::ser::{SerializeMap, SerializeSeq};
 use serde::Serialize;
 
-use crate::field::{FieldInfo, FieldValue};
+use crate::base64;
+use crate::errors::{ErrorOptions, ErrorType, LineError, ValError, ValResult};
+use crate::field::{
+    datetime_from_py, decimal_from_py, get_enum_type, uuid_from_py, FieldInfo, FieldValue, RawData,
+    RawList,
+};
+use crate::key_lookup::KeyLookup;
+use crate::model_validator::{bind_instance, new_instance};
+use crate::validators::{ValidationState, Validator};
+
+/// Model level settings from the schema which affect instances after validation
+#[derive(Debug, Clone)]
+pub struct ModelConfig {
+    pub frozen: bool,
+    pub validate_assignment: bool,
+    // for errors from assignment and validated defaults
+    pub error_options: ErrorOptions,
+    pub computed_fields: Arc<Vec<ComputedField>>,
+}
+
+/// A method on the model class whose result is included in dumps under `name`
+#[derive(Debug)]
+pub struct ComputedField {
+    pub name: String,
+    pub method: Py<PyString>,
+}
 
 #[derive(Debug)]
 #[pyclass(module = "rustmodel")]
 pub struct ModelData {
     field_info: Arc<Vec<FieldInfo>>,
     field_data: Vec<Option<FieldValue>>,
-    key_lookup: Arc<AHashMap<String, usize>>,
+    // indexed like `field_data`, true for fields supplied in the input or assigned since, not defaults
+    fields_set: Vec<bool>,
+    // indexes of fields found in input under their name rather than their validation alias, with
+    // `populate_by_name`, so `round_trip` dumps use the same key
+    fields_by_name: Vec<usize>,
+    // values for unknown keys when `extra_behavior` is "allow"
+    extra: Vec<(String, FieldValue)>,
+    key_lookup: Arc<KeyLookup>,
+    name: Arc<str>,
+    config: ModelConfig,
+    // the model class, so `model_copy` can create new instances
+    cls: Py<PyType>,
 }
 
 #[pymethods]
 impl ModelData {
-    fn get_attr(&self, py: Python, key: String) -> PyResult<PyObject> {
+    /// Values are converted to python objects once, so repeated access returns the same object
+    fn get_attr(&mut self, py: Python, key: String) -> PyResult<PyObject> {
         if let Some(index) = self.key_lookup.get(&key) {
-            match &self.field_data[*index] {
-                Some(f) => Ok(f.to_object(py)),
-                None => Ok(py.None()),
-            }
+            let field_value = match &mut self.field_data[index] {
+                Some(field_value) => field_value,
+                field_value => {
+                    let default = self.field_info[index]
+                        .get_default(py)
+                        .map_err(|e| e.into_py_err(py, &self.name, self.config.error_options))?;
+                    field_value.insert(default)
+                }
+            };
+            field_value.py_value(py)
+        } else if let Some((_, value)) = self.extra.iter_mut().find(|(k, _)| *k == key) {
+            value.py_value(py)
         } else {
             Err(PyAttributeError::new_err(key))
         }
     }
 
-    fn model_dump(&mut self, py: Python) -> PyResult<PyObject> {
-        let dict = PyDict::new_bound(py);
-        for (field_info, field_value) in self.items_update(py) {
-            dict.set_item(&field_info.name_py.clone_ref(py), field_value.to_object(py))?;
+    /// Only validated with the field's validator if `validate_assignment` is set
+    fn set_attr(&mut self, py: Python, key: String, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let Some(index) = self.key_lookup.get(&key) else {
+            return Err(PyAttributeError::new_err(key));
+        };
+        if self.config.frozen {
+            let input = RawData::Py(Arc::new(value.clone().unbind()));
+            let error = LineError::new_loc(ErrorType::FrozenInstance, key).with_input(input);
+            return Err(ValError::from(vec![error]).into_py_err(
+                py,
+                &self.name,
+                self.config.error_options,
+            ));
+        }
+        if self.config.validate_assignment {
+            self.validate_field(py, index, key, value)
+        } else {
+            self.set_field(index, FieldValue::Py(value.clone().unbind()));
+            Ok(())
+        }
+    }
+
+    /// Values in `update` are only validated if `validate` is set
+    #[pyo3(signature = (*, update = None, deep = false, validate = false))]
+    fn model_copy(
+        &self,
+        py: Python,
+        update: Option<&Bound<'_, PyDict>>,
+        deep: bool,
+        validate: bool,
+    ) -> PyResult<PyObject> {
+        let mut copy = self.copy(py, deep)?;
+        if let Some(update) = update {
+            for (key, value) in update.iter() {
+                let key: String = key.extract()?;
+                match copy.key_lookup.get(&key) {
+                    Some(index) if validate => copy.validate_field(py, index, key, &value)?,
+                    Some(index) => copy.set_field(index, FieldValue::Py(value.unbind())),
+                    None => match copy.extra.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, extra_value)) => *extra_value = FieldValue::Py(value.unbind()),
+                        None => copy.extra.push((key, FieldValue::Py(value.unbind()))),
+                    },
+                }
+            }
         }
-        Ok(dict.into())
+        Ok(new_instance(py, copy)?.0)
     }
 
-    fn model_dump_json(&self, py: Python) -> PyResult<String> {
-        let model_data_serializer = ModelDataSerializer {
-            py,
-            field_info: &self.field_info,
-            field_data: &self.field_data,
+    /// Like pydantic's `model_fields_set`, fields left to their defaults aren't included
+    fn fields_set<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PySet>> {
+        let names = self.field_info.iter().zip(&self.fields_set);
+        let names = names.filter(|(_, &set)| set).map(|(info, _)| &info.name_py);
+        PySet::new_bound(py, names)
+    }
+
+    /// `ClassName(a=1, b='x')`, nested values use their own `repr`
+    fn __repr__(slf: &Bound<'_, Self>) -> PyResult<String> {
+        let py = slf.py();
+        // values are collected first so the borrow is released before python code runs
+        let (cls, items) = {
+            let mut model_data = slf.try_borrow_mut()?;
+            model_data.fill_defaults(py)?;
+            let model_data = &mut *model_data;
+            let field_items = model_data.field_info.iter().zip(&mut model_data.field_data);
+            let field_items =
+                field_items.filter_map(|(info, value)| Some((&info.name, value.as_mut()?)));
+            let extra_items = model_data.extra.iter_mut().map(|(k, v)| (&*k, v));
+            let items: Vec<(String, PyObject)> = field_items
+                .chain(extra_items)
+                .map(|(name, value)| Ok((name.clone(), value.py_value(py)?)))
+                .collect::<PyResult<_>>()?;
+            (model_data.cls.clone_ref(py), items)
         };
-        serde_json::to_string(&model_data_serializer)
-            .map_err(|e| PyValueError::new_err(e.to_string()))
+        let mut repr = format!("{}(", cls.bind(py).name()?);
+        for (index, (name, value)) in items.iter().enumerate() {
+            if index > 0 {
+                repr.push_str(", ");
+            }
+            repr.push_str(&format!("{}={}", name, value.bind(py).repr()?));
+        }
+        repr.push(')');
+        Ok(repr)
+    }
+
+    /// Compares field data only, the model class should check `other` is an instance of the same class
+    fn __eq__(slf: &Bound<'_, Self>, other: &Bound<'_, Self>) -> PyResult<bool> {
+        model_data_eq(slf, other)
+    }
+
+    /// Only frozen models are hashable, since other models can be changed with `set_attr`
+    fn __hash__(&mut self, py: Python) -> PyResult<isize> {
+        if !self.config.frozen {
+            return Err(PyTypeError::new_err(format!(
+                "unhashable type: '{}'",
+                self.name
+            )));
+        }
+        self.fill_defaults(py)?;
+        let field_values = self.field_data.iter().flatten();
+        let extra_values = self.extra.iter().map(|(_, v)| v);
+        let values: Vec<PyObject> = field_values
+            .chain(extra_values)
+            .map(|v| v.to_py(py))
+            .collect::<PyResult<_>>()?;
+        PyTuple::new_bound(py, values).hash()
+    }
+
+    /// `round_trip` dumps fields under the key they were validated from
+    #[pyo3(signature = (*, include = None, exclude = None, by_alias = false, round_trip = false, exclude_none = false, exclude_unset = false, exclude_defaults = false))]
+    // one argument per python keyword
+    #[allow(clippy::too_many_arguments)]
+    fn model_dump(
+        slf: &Bound<'_, Self>,
+        include: Option<&Bound<'_, PyAny>>,
+        exclude: Option<&Bound<'_, PyAny>>,
+        by_alias: bool,
+        round_trip: bool,
+        exclude_none: bool,
+        exclude_unset: bool,
+        exclude_defaults: bool,
+    ) -> PyResult<PyObject> {
+        let filter = FieldFilter::new(include, exclude)?;
+        let options = DumpOptions {
+            by_alias,
+            round_trip,
+            exclude_none,
+            exclude_unset,
+            exclude_defaults,
+            ..Default::default()
+        };
+        let computed = computed_values(slf, None)?;
+        slf.try_borrow_mut()?
+            .dump_python(slf.py(), options, Some(&filter), &computed)
+    }
+
+    /// `inf_nan` is "error" (the default), "null" or "strings"
+    #[pyo3(signature = (*, indent = None, include = None, exclude = None, by_alias = false, round_trip = false, exclude_none = false, exclude_unset = false, exclude_defaults = false, inf_nan = "error"))]
+    // one argument per python keyword
+    #[allow(clippy::too_many_arguments)]
+    fn model_dump_json(
+        slf: &Bound<'_, Self>,
+        indent: Option<usize>,
+        include: Option<&Bound<'_, PyAny>>,
+        exclude: Option<&Bound<'_, PyAny>>,
+        by_alias: bool,
+        round_trip: bool,
+        exclude_none: bool,
+        exclude_unset: bool,
+        exclude_defaults: bool,
+        inf_nan: &str,
+    ) -> PyResult<String> {
+        let filter = FieldFilter::new(include, exclude)?;
+        let options = DumpOptions {
+            by_alias,
+            round_trip,
+            exclude_none,
+            exclude_unset,
+            exclude_defaults,
+            inf_nan: InfNan::new(inf_nan)?,
+        };
+        let mut json = Vec::new();
+        write_json(slf, options, &filter, indent, &mut json)?;
+        // serde_json only writes valid UTF-8
+        Ok(String::from_utf8(json).unwrap())
+    }
+
+    /// Like `model_dump_json` but written to `writer` a chunk at a time, or returned as `bytes`
+    #[pyo3(signature = (writer = None, *, indent = None, include = None, exclude = None, by_alias = false, round_trip = false, exclude_none = false, exclude_unset = false, exclude_defaults = false, inf_nan = "error"))]
+    // one argument per python keyword
+    #[allow(clippy::too_many_arguments)]
+    fn dump_json_to_writer(
+        slf: &Bound<'_, Self>,
+        writer: Option<&Bound<'_, PyAny>>,
+        indent: Option<usize>,
+        include: Option<&Bound<'_, PyAny>>,
+        exclude: Option<&Bound<'_, PyAny>>,
+        by_alias: bool,
+        round_trip: bool,
+        exclude_none: bool,
+        exclude_unset: bool,
+        exclude_defaults: bool,
+        inf_nan: &str,
+    ) -> PyResult<Option<PyObject>> {
+        let filter = FieldFilter::new(include, exclude)?;
+        let options = DumpOptions {
+            by_alias,
+            round_trip,
+            exclude_none,
+            exclude_unset,
+            exclude_defaults,
+            inf_nan: InfNan::new(inf_nan)?,
+        };
+        let Some(writer) = writer else {
+            let mut json = Vec::new();
+            write_json(slf, options, &filter, indent, &mut json)?;
+            return Ok(Some(PyBytes::new_bound(slf.py(), &json).into()));
+        };
+        let mut writer = PyWriter::new(writer);
+        let result = write_json(slf, options, &filter, indent, &mut writer).and_then(|()| {
+            writer
+                .flush()
+                .map_err(|e| PyValueError::new_err(e.to_string()))
+        });
+        // an exception from `write` is raised as is, rather than as serde_json's IO error
+        match writer.error.take() {
+            Some(error) => Err(error),
+            None => result.map(|()| None),
+        }
+    }
+}
+
+/// Serialize the model as JSON into `writer`, shared by `model_dump_json` and `dump_json_to_writer`
+fn write_json<W: io::Write>(
+    slf: &Bound<'_, ModelData>,
+    options: DumpOptions,
+    filter: &FieldFilter,
+    indent: Option<usize>,
+    writer: &mut W,
+) -> PyResult<()> {
+    let py = slf.py();
+    let computed = computed_values(slf, None)?;
+    let mut slf = slf.try_borrow_mut()?;
+    let slf = &mut *slf;
+    slf.fill_defaults(py)?;
+    // convert python values once, later dumps reuse the cached raw values
+    let extra_values = slf.extra.iter_mut().map(|(_, v)| v);
+    for field_value in slf.field_data.iter_mut().flatten().chain(extra_values) {
+        field_value.raw_value(py);
+    }
+    let serializer = slf.serializer(py, options, Some(filter), &computed);
+    let result = match indent {
+        Some(indent) => {
+            let indent = vec![b' '; indent];
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+            serializer.serialize(&mut serde_json::Serializer::with_formatter(
+                writer, formatter,
+            ))
+        }
+        None => serializer.serialize(&mut serde_json::Serializer::new(writer)),
+    };
+    result.map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Bytes at which `PyWriter` calls `write`, so python is called once per chunk rather than for every token
+const WRITE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Buffers output for a python file-like object, passing it to `write` as `bytes` a chunk at a time
+struct PyWriter<'py> {
+    file: &'py Bound<'py, PyAny>,
+    buffer: Vec<u8>,
+    // the exception from `write`, `io::Error` can only carry its message
+    error: Option<PyErr>,
+}
+
+impl<'py> PyWriter<'py> {
+    fn new(file: &'py Bound<'py, PyAny>) -> Self {
+        Self {
+            file,
+            buffer: Vec::with_capacity(WRITE_CHUNK_SIZE),
+            error: None,
+        }
+    }
+}
+
+impl io::Write for PyWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= WRITE_CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let py = self.file.py();
+        let chunk = PyBytes::new_bound(py, &self.buffer);
+        self.buffer.clear();
+        match self.file.call_method1(intern!(py, "write"), (chunk,)) {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                let io_error = io::Error::other(error.to_string());
+                self.error = Some(error);
+                Err(io_error)
+            }
+        }
+    }
+}
+
+/// Options for `model_dump` and `model_dump_json` which also apply to nested models
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DumpOptions {
+    pub by_alias: bool,
+    // fields are dumped under the key they were validated from, overriding `by_alias`
+    pub round_trip: bool,
+    pub exclude_none: bool,
+    pub exclude_unset: bool,
+    pub exclude_defaults: bool,
+    pub inf_nan: InfNan,
+}
+
+impl DumpOptions {
+    /// `field_value` is `None` if the field is missing so has its default
+    fn is_excluded(
+        self,
+        py: Python,
+        field_info: &FieldInfo,
+        is_set: bool,
+        field_value: Option<&FieldValue>,
+    ) -> ValResult<bool> {
+        if field_info.exclude || (self.exclude_unset && !is_set) {
+            return Ok(true);
+        }
+        if !self.exclude_defaults || field_info.required {
+            return Ok(false);
+        }
+        match field_value {
+            Some(field_value) => {
+                let default = field_info.get_default(py)?.to_py(py)?;
+                Ok(field_value.to_py(py)?.bind(py).eq(default)?)
+            }
+            None => Ok(true),
+        }
+    }
+}
+
+/// How infinite and NaN floats are serialized to JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum InfNan {
+    Error,
+    #[default]
+    Null,
+    Strings,
+}
+
+impl InfNan {
+    fn new(inf_nan: &str) -> PyResult<Self> {
+        match inf_nan {
+            "error" => Ok(Self::Error),
+            "null" => Ok(Self::Null),
+            "strings" => Ok(Self::Strings),
+            s => Err(PyValueError::new_err(format!("Invalid inf_nan: {s}"))),
+        }
+    }
+
+    fn serialize_f64<S: serde::ser::Serializer>(
+        self,
+        f: f64,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if f.is_finite() {
+            return serializer.serialize_f64(f);
+        }
+        match self {
+            Self::Error => Err(serde::ser::Error::custom(format!(
+                "Out of range float values are not JSON compliant: {}",
+                float_name(f)
+            ))),
+            Self::Null => serializer.serialize_none(),
+            Self::Strings => serializer.serialize_str(float_name(f)),
+        }
     }
 }
 
+/// The names used by python's `json` module and JavaScript
+fn float_name(f: f64) -> &'static str {
+    if f.is_nan() {
+        "NaN"
+    } else if f.is_sign_positive() {
+        "Infinity"
+    } else {
+        "-Infinity"
+    }
+}
+
+/// `include` and `exclude` sets of field names, unlike `DumpOptions` these only apply to the top level model
+#[derive(Debug)]
+struct FieldFilter {
+    include: Option<AHashSet<String>>,
+    exclude: Option<AHashSet<String>>,
+}
+
+impl FieldFilter {
+    fn new(
+        include: Option<&Bound<'_, PyAny>>,
+        exclude: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            include: include.map(extract_names).transpose()?,
+            exclude: exclude.map(extract_names).transpose()?,
+        })
+    }
+
+    /// names in `exclude` win over `include`, unknown names are ignored
+    fn is_included(&self, name: &str) -> bool {
+        if self
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.contains(name))
+        {
+            false
+        } else {
+            self.include
+                .as_ref()
+                .is_none_or(|include| include.contains(name))
+        }
+    }
+}
+
+fn extract_names(names: &Bound<'_, PyAny>) -> PyResult<AHashSet<String>> {
+    names.iter()?.map(|name| name?.extract()).collect()
+}
+
 impl ModelData {
     pub fn new(
         field_info: &Arc<Vec<FieldInfo>>,
         field_data: Vec<Option<FieldValue>>,
-        key_lookup: &Arc<AHashMap<String, usize>>,
+        extra: Vec<(String, FieldValue)>,
+        key_lookup: &Arc<KeyLookup>,
+        name: &Arc<str>,
+        config: ModelConfig,
+        cls: Py<PyType>,
     ) -> Self {
+        // missing fields are only filled with defaults after validation, so these are the fields in the input
+        let fields_set = field_data.iter().map(Option::is_some).collect();
         Self {
             field_info: field_info.clone(),
             field_data,
+            fields_set,
+            fields_by_name: Vec::new(),
+            extra,
             key_lookup: key_lookup.clone(),
+            name: name.clone(),
+            config,
+            cls,
         }
     }
 
-    fn items_update<'py>(
-        &'py mut self,
-        py: Python<'py>,
-    ) -> impl Iterator<Item = (&FieldInfo, &FieldValue)> + 'py {
-        self.field_info
+    pub fn with_fields_by_name(mut self, fields_by_name: Vec<usize>) -> Self {
+        self.fields_by_name = fields_by_name;
+        self
+    }
+
+    pub fn cls(&self) -> &Py<PyType> {
+        &self.cls
+    }
+
+    fn set_field(&mut self, index: usize, value: FieldValue) {
+        self.field_data[index] = Some(value);
+        self.fields_set[index] = true;
+    }
+
+    fn validate_field(
+        &mut self,
+        py: Python,
+        index: usize,
+        key: String,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        match self.field_info[index]
+            .validator
+            .validate_python(&mut ValidationState::new(py, None, None), value)
+        {
+            Ok(field_value) => {
+                self.set_field(index, field_value);
+                Ok(())
+            }
+            Err(e) => {
+                let errors = e.with_py_input(value).line_errors_with_loc(key)?;
+                Err(ValError::from(errors).into_py_err(py, &self.name, self.config.error_options))
+            }
+        }
+    }
+
+    /// Missing fields stay missing, so the copy fills its own defaults
+    pub fn copy(&self, py: Python, deep: bool) -> PyResult<Self> {
+        let copy_value = |value: &FieldValue| copy_field_value(py, value, deep);
+        let field_data = self
+            .field_data
+            .iter()
+            .map(|value| value.as_ref().map(copy_value).transpose())
+            .collect::<PyResult<_>>()?;
+        let extra = self
+            .extra
             .iter()
-            .zip(self.field_data.iter_mut())
-            .map(move |(f, v)| {
-                let value = match v {
-                    Some(f) => f,
-                    None => {
-                        *v = Some(FieldValue::Py(f.default.clone_ref(py)));
-                        v.as_ref().unwrap()
-                    }
-                };
-                (f, value)
-            })
+            .map(|(key, value)| Ok((key.clone(), copy_value(value)?)))
+            .collect::<PyResult<_>>()?;
+        Ok(Self {
+            field_info: self.field_info.clone(),
+            field_data,
+            fields_set: self.fields_set.clone(),
+            fields_by_name: self.fields_by_name.clone(),
+            extra,
+            key_lookup: self.key_lookup.clone(),
+            name: self.name.clone(),
+            config: self.config.clone(),
+            cls: self.cls.clone_ref(py),
+        })
+    }
+
+    fn dump_python(
+        &mut self,
+        py: Python,
+        options: DumpOptions,
+        filter: Option<&FieldFilter>,
+        computed: &[PyObject],
+    ) -> PyResult<PyObject> {
+        self.fill_defaults(py)?;
+        let mut sink = PythonSink {
+            dict: PyDict::new_bound(py),
+            options,
+        };
+        self.serializer(py, options, filter, computed)
+            .dump_entries(&mut sink)?;
+        Ok(sink.dict.into())
+    }
+
+    fn serializer<'py>(
+        &'py self,
+        py: Python<'py>,
+        options: DumpOptions,
+        filter: Option<&'py FieldFilter>,
+        computed: &'py [PyObject],
+    ) -> ModelDataSerializer<'py> {
+        ModelDataSerializer {
+            py,
+            name: &self.name,
+            error_options: self.config.error_options,
+            field_info: &self.field_info,
+            field_data: &self.field_data,
+            fields_set: &self.fields_set,
+            fields_by_name: &self.fields_by_name,
+            extra: &self.extra,
+            computed_fields: &self.config.computed_fields,
+            computed,
+            options,
+            filter,
+        }
+    }
+
+    /// Set missing fields to their defaults, so `default_factory` is only called once per field
+    fn fill_defaults(&mut self, py: Python) -> PyResult<()> {
+        let items = self.field_info.iter().zip(self.field_data.iter_mut());
+        for (field_info, field_value) in items {
+            if field_value.is_none() {
+                let default = field_info
+                    .get_default(py)
+                    .map_err(|e| e.into_py_err(py, &self.name, self.config.error_options))?;
+                *field_value = Some(default);
+            }
+        }
+        Ok(())
     }
 }
 
 struct ModelDataSerializer<'py> {
     py: Python<'py>,
+    // for `ValidationError`s from validated defaults
+    name: &'py str,
+    error_options: ErrorOptions,
     field_info: &'py Arc<Vec<FieldInfo>>,
     field_data: &'py Vec<Option<FieldValue>>,
+    fields_set: &'py [bool],
+    fields_by_name: &'py [usize],
+    extra: &'py Vec<(String, FieldValue)>,
+    computed_fields: &'py [ComputedField],
+    // values of `computed_fields`, called before the model data is borrowed
+    computed: &'py [PyObject],
+    options: DumpOptions,
+    filter: Option<&'py FieldFilter>,
+}
+
+impl ModelDataSerializer<'_> {
+    fn is_included(&self, name: &str) -> bool {
+        self.filter.is_none_or(|filter| filter.is_included(name))
+    }
+
+    /// The key a field is dumped under, see `round_trip` and `by_alias`
+    fn dump_key<'a>(&self, index: usize, field_info: &'a FieldInfo) -> &'a str {
+        match self.options.round_trip {
+            true if self.fields_by_name.contains(&index) => &field_info.name,
+            true => field_info.input_name(),
+            false => field_info.dump_name(self.options.by_alias),
+        }
+    }
+
+    fn default_error(&self, error: ValError) -> PyErr {
+        error.into_py_err(self.py, self.name, self.error_options)
+    }
+
+    /// Shared by `model_dump` and JSON so they can't disagree on which entries are dumped
+    fn dump_entries<D: DumpSink>(&self, sink: &mut D) -> Result<(), D::Error> {
+        let (py, options) = (self.py, self.options);
+        let items = self.field_info.iter().zip(self.fields_set);
+        let items = items.zip(self.field_data.iter()).enumerate();
+
+        for (index, ((field_info, &is_set), opt_field_value)) in items {
+            if !self.is_included(&field_info.name) {
+                continue;
+            }
+            let is_excluded = options
+                .is_excluded(py, field_info, is_set, opt_field_value.as_ref())
+                .map_err(|e| sink.py_error(self.default_error(e)))?;
+            if is_excluded {
+                continue;
+            }
+            let default;
+            let field_value = match opt_field_value {
+                Some(field_value) => field_value,
+                None => {
+                    let value = field_info.get_default(py);
+                    default = value.map_err(|e| sink.py_error(self.default_error(e)))?;
+                    &default
+                }
+            };
+            if options.exclude_none && field_value.is_none(py) {
+                continue;
+            }
+            let key = self.dump_key(index, field_info);
+            sink.entry(key, Some(field_info), DumpValue::Field(field_value))?;
+        }
+        for (key, field_value) in self.extra {
+            if !self.is_included(key) || (options.exclude_none && field_value.is_none(py)) {
+                continue;
+            }
+            sink.entry(key, None, DumpValue::Field(field_value))?;
+        }
+        for (computed_field, value) in self.computed_fields.iter().zip(self.computed) {
+            let value = value.bind(py);
+            if !self.is_included(&computed_field.name) || (options.exclude_none && value.is_none())
+            {
+                continue;
+            }
+            sink.entry(&computed_field.name, None, DumpValue::Py(value))?;
+        }
+        Ok(())
+    }
 }
 
 impl Serialize for ModelDataSerializer<'_> {
     fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut map = serializer.serialize_map(Some(self.field_data.len()))?;
-
-        let items = self.field_info.iter().zip(self.field_data.iter());
-
-        for (field_info, opt_field_value) in items {
-            if let Some(field_value) = opt_field_value {
-                // map.serialize_entry(&field_info.name, field_value.raw_value())?;
-                match field_value {
-                    FieldValue::Py(py_obj) => {
-                        map.serialize_entry(&field_info.name, &SerializePy(py_obj.bind(self.py)))?;
-                    }
-                    FieldValue::Model(model) => todo!("serialize model {model:?}"),
-                    FieldValue::Raw(raw) => {
-                        map.serialize_entry(&field_info.name, raw)?;
-                    }
-                    FieldValue::Both(_, raw) => {
-                        map.serialize_entry(&field_info.name, raw)?;
-                    }
+        let options = self.options;
+        // when fields can be excluded the number of entries isn't known up front
+        let excludes = options.exclude_none || options.exclude_unset || options.exclude_defaults;
+        let excludes = excludes || self.field_info.iter().any(|info| info.exclude);
+        let len = match excludes || self.filter.is_some() {
+            true => None,
+            false => Some(self.field_data.len() + self.extra.len() + self.computed.len()),
+        };
+        let mut sink = JsonSink {
+            py: self.py,
+            map: serializer.serialize_map(len)?,
+            options,
+        };
+        self.dump_entries(&mut sink)?;
+        sink.map.end()
+    }
+}
+
+/// A value passed to a `DumpSink`, computed fields are python objects rather than field values
+enum DumpValue<'a, 'py> {
+    Field(&'a FieldValue),
+    Py(&'a Bound<'py, PyAny>),
+}
+
+/// A dict for `model_dump` or a serde map for JSON
+trait DumpSink {
+    type Error;
+
+    /// `field_info` is only set for fields, so python keys can reuse the field's name string
+    fn entry(
+        &mut self,
+        key: &str,
+        field_info: Option<&FieldInfo>,
+        value: DumpValue,
+    ) -> Result<(), Self::Error>;
+
+    fn py_error(&self, error: PyErr) -> Self::Error;
+}
+
+struct PythonSink<'py> {
+    dict: Bound<'py, PyDict>,
+    options: DumpOptions,
+}
+
+impl DumpSink for PythonSink<'_> {
+    type Error = PyErr;
+
+    fn entry(
+        &mut self,
+        key: &str,
+        field_info: Option<&FieldInfo>,
+        value: DumpValue,
+    ) -> PyResult<()> {
+        let py = self.dict.py();
+        let value = match value {
+            DumpValue::Field(field_value) => dump_field_value(py, field_value, self.options)?,
+            DumpValue::Py(py_value) => dump_py_object(py_value, self.options)?,
+        };
+        match field_info {
+            Some(field_info) if field_info.name == key => {
+                self.dict.set_item(field_info.name_py.clone_ref(py), value)
+            }
+            _ => self.dict.set_item(key, value),
+        }
+    }
+
+    fn py_error(&self, error: PyErr) -> PyErr {
+        error
+    }
+}
+
+struct JsonSink<'py, M> {
+    py: Python<'py>,
+    map: M,
+    options: DumpOptions,
+}
+
+impl<M: SerializeMap> DumpSink for JsonSink<'_, M> {
+    type Error = M::Error;
+
+    fn entry(
+        &mut self,
+        key: &str,
+        _: Option<&FieldInfo>,
+        value: DumpValue,
+    ) -> Result<(), M::Error> {
+        let result = match value {
+            DumpValue::Field(field_value) => self.map.serialize_entry(
+                key,
+                &SerializeFieldValue(self.py, field_value, self.options),
+            ),
+            DumpValue::Py(py_value) => self
+                .map
+                .serialize_entry(key, &SerializePy(py_value, self.options)),
+        };
+        result.map_err(field_error(key))
+    }
+
+    fn py_error(&self, error: PyErr) -> M::Error {
+        serde::ser::Error::custom(error)
+    }
+}
+
+/// Prefix serialization errors with the field name, nested models add their own prefix
+fn field_error<E: serde::ser::Error>(key: &str) -> impl FnOnce(E) -> E + '_ {
+    move |e| E::custom(format!("field \"{key}\": {e}"))
+}
+
+struct SerializeFieldValue<'py>(Python<'py>, &'py FieldValue, DumpOptions);
+
+impl Serialize for SerializeFieldValue<'_> {
+    fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (py, options) = (self.0, self.2);
+        match self.1 {
+            FieldValue::Py(py_obj) => SerializePy(py_obj.bind(py), options).serialize(serializer),
+            FieldValue::Model(instance, model_data) => {
+                let model_data = model_data.bind(py);
+                let computed = computed_values(model_data, Some(instance.bind(py)))
+                    .map_err(serde::ser::Error::custom)?;
+                let model_data = model_data.try_borrow().map_err(serde::ser::Error::custom)?;
+                model_data
+                    .serializer(py, options, None, &computed)
+                    .serialize(serializer)
+            }
+            FieldValue::Raw(raw) | FieldValue::Both(_, raw) => {
+                SerializeRaw(py, raw, options).serialize(serializer)
+            }
+        }
+    }
+}
+
+/// Serialize `RawData` while passing `DumpOptions` on to any models it contains
+struct SerializeRaw<'py>(Python<'py>, &'py RawData, DumpOptions);
+
+impl Serialize for SerializeRaw<'_> {
+    fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (py, options) = (self.0, self.2);
+        match self.1 {
+            RawData::List(v) | RawData::Set(v) | RawData::Tuple(v) => {
+                let mut list_ser = serializer.serialize_seq(Some(v.len()))?;
+                for item in v.iter() {
+                    list_ser.serialize_element(&SerializeRaw(py, item, options))?;
                 }
-            } else {
-                map.serialize_entry(
-                    &field_info.name,
-                    &SerializePy(&field_info.default.clone_ref(self.py).into_bound(self.py)),
-                )?;
+                list_ser.end()
             }
+            RawData::Dict(o) => {
+                let mut map_ser = serializer.serialize_map(Some(o.len()))?;
+                for (k, v) in o.iter() {
+                    map_ser.serialize_entry(k, &SerializeRaw(py, v, options))?;
+                }
+                map_ser.end()
+            }
+            RawData::Py(py_obj) => SerializePy(py_obj.bind(py), options).serialize(serializer),
+            RawData::Float(f) => options.inf_nan.serialize_f64(*f, serializer),
+            raw => raw.serialize(serializer),
         }
-        map.end()
     }
 }
 
-struct SerializePy<'py>(&'py Bound<'py, PyAny>);
+pub struct SerializePy<'py>(pub &'py Bound<'py, PyAny>, pub DumpOptions);
 
 impl Serialize for SerializePy<'_> {
     fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let py_value = self.0;
+        let (py_value, options) = (self.0, self.1);
         if py_value.is_none() {
             serializer.serialize_none()
         } else if let Ok(value) = py_value.downcast::<PyBool>() {
@@ -134,15 +897,43 @@ impl Serialize for SerializePy<'_> {
             let s = value.to_str().map_err(serde::ser::Error::custom)?;
             serializer.serialize_str(s)
         } else if let Ok(value) = py_value.downcast::<PyInt>() {
-            serializer.serialize_i64(value.extract::<i64>().map_err(serde::ser::Error::custom)?)
+            // big ints are only kept from input as extra values, e.g. from JSON
+            if let Ok(i) = value.extract::<i64>() {
+                serializer.serialize_i64(i)
+            } else if let Ok(i) = value.extract::<i128>() {
+                serializer.serialize_i128(i)
+            } else {
+                serializer.serialize_u128(value.extract().map_err(serde::ser::Error::custom)?)
+            }
         } else if let Ok(value) = py_value.downcast::<PyFloat>() {
-            serializer.serialize_f64(value.extract::<f64>().map_err(serde::ser::Error::custom)?)
+            let f = value.extract::<f64>().map_err(serde::ser::Error::custom)?;
+            options.inf_nan.serialize_f64(f, serializer)
+        } else if let Ok(value) = py_value.downcast::<PyBytes>() {
+            serializer.serialize_str(&base64::encode(value.as_bytes()))
         } else if let Ok(value) = py_value.downcast::<PyList>() {
             let mut list_ser = serializer.serialize_seq(Some(value.len()))?;
             for item in value.iter() {
-                list_ser.serialize_element(&SerializePy(&item))?;
+                list_ser.serialize_element(&SerializePy(&item, options))?;
             }
             list_ser.end()
+        } else if let Ok(value) = py_value.downcast::<PyTuple>() {
+            let mut tuple_ser = serializer.serialize_seq(Some(value.len()))?;
+            for item in value.iter() {
+                tuple_ser.serialize_element(&SerializePy(&item, options))?;
+            }
+            tuple_ser.end()
+        } else if let Ok(value) = py_value.downcast::<PySet>() {
+            let mut set_ser = serializer.serialize_seq(Some(value.len()))?;
+            for item in value.iter() {
+                set_ser.serialize_element(&SerializePy(&item, options))?;
+            }
+            set_ser.end()
+        } else if let Ok(value) = py_value.downcast::<PyFrozenSet>() {
+            let mut set_ser = serializer.serialize_seq(Some(value.len()))?;
+            for item in value.iter() {
+                set_ser.serialize_element(&SerializePy(&item, options))?;
+            }
+            set_ser.end()
         } else if let Ok(value) = py_value.downcast::<PyDict>() {
             let mut map_ser = serializer.serialize_map(Some(value.len()))?;
             for (key, value) in value.iter() {
@@ -151,11 +942,818 @@ impl Serialize for SerializePy<'_> {
                     .map_err(serde::ser::Error::custom)?
                     .to_str()
                     .map_err(serde::ser::Error::custom)?;
-                map_ser.serialize_entry(&key, &SerializePy(&value))?;
+                map_ser.serialize_entry(&key, &SerializePy(&value, options))?;
             }
             map_ser.end()
+        } else if let Ok(value) = py_value.downcast::<PyDateTime>() {
+            let dt = datetime_from_py(value).map_err(serde::ser::Error::custom)?;
+            serializer.collect_str(&dt)
+        } else if let Ok(Some(uuid)) = uuid_from_py(py_value) {
+            serializer.collect_str(&uuid.hyphenated())
+        } else if let Ok(Some(decimal)) = decimal_from_py(py_value) {
+            serializer.serialize_str(&decimal)
+        } else if py_value
+            .is_instance(get_enum_type(py_value.py()).map_err(serde::ser::Error::custom)?)
+            .map_err(serde::ser::Error::custom)?
+        {
+            // str and int enums are already handled as strings and ints
+            let value = py_value
+                .getattr(intern!(py_value.py(), "value"))
+                .map_err(serde::ser::Error::custom)?;
+            SerializePy(&value, options).serialize(serializer)
+        } else if let Some(model_data) = get_model_data(py_value) {
+            let computed =
+                computed_values(&model_data, Some(py_value)).map_err(serde::ser::Error::custom)?;
+            let model_data = model_data.try_borrow().map_err(serde::ser::Error::custom)?;
+            model_data
+                .serializer(py_value.py(), options, None, &computed)
+                .serialize(serializer)
         } else {
             Err(serde::ser::Error::custom("unsupported type"))
         }
     }
 }
+
+/// A missing field equals one set to its default, extra values are compared regardless of order
+pub fn model_data_eq(a: &Bound<'_, ModelData>, b: &Bound<'_, ModelData>) -> PyResult<bool> {
+    if a.is(b) {
+        return Ok(true);
+    }
+    let py = a.py();
+    a.try_borrow_mut()?.fill_defaults(py)?;
+    b.try_borrow_mut()?.fill_defaults(py)?;
+    let (a, b) = (a.try_borrow()?, b.try_borrow()?);
+
+    let same_fields = a
+        .field_info
+        .iter()
+        .map(|f| &f.name)
+        .eq(b.field_info.iter().map(|f| &f.name));
+    if !same_fields || a.extra.len() != b.extra.len() {
+        return Ok(false);
+    }
+    // after `fill_defaults` every field has a value
+    for (a_value, b_value) in a
+        .field_data
+        .iter()
+        .flatten()
+        .zip(b.field_data.iter().flatten())
+    {
+        if !a_value.py_eq(py, b_value)? {
+            return Ok(false);
+        }
+    }
+    for (key, a_value) in &a.extra {
+        match b.extra.iter().find(|(k, _)| k == key) {
+            Some((_, b_value)) if a_value.py_eq(py, b_value)? => (),
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+/// `RawData` is immutable, so only python objects and models within it need copying
+fn copy_field_value(py: Python, value: &FieldValue, deep: bool) -> PyResult<FieldValue> {
+    match (value, deep) {
+        (FieldValue::Model(_, model_data), true) => {
+            let copy = model_data.bind(py).try_borrow()?.copy(py, true)?;
+            let (instance, model_data) = new_instance(py, copy)?;
+            Ok(FieldValue::Model(instance, model_data))
+        }
+        (FieldValue::Py(py_obj), true) => Ok(FieldValue::Py(deep_copy_py(py, py_obj)?)),
+        (FieldValue::Raw(raw), true) => Ok(FieldValue::Raw(deep_copy_raw(py, raw)?)),
+        (FieldValue::Both(py_obj, raw), true) => Ok(FieldValue::Both(
+            deep_copy_py(py, py_obj)?,
+            deep_copy_raw(py, raw)?,
+        )),
+        (FieldValue::Model(py_obj, model_data), false) => Ok(FieldValue::Model(
+            py_obj.clone_ref(py),
+            model_data.clone_ref(py),
+        )),
+        (FieldValue::Py(py_obj), false) => Ok(FieldValue::Py(py_obj.clone_ref(py))),
+        (FieldValue::Raw(raw), false) => Ok(FieldValue::Raw(raw.clone())),
+        (FieldValue::Both(py_obj, raw), false) => {
+            Ok(FieldValue::Both(py_obj.clone_ref(py), raw.clone()))
+        }
+    }
+}
+
+fn deep_copy_raw(py: Python, raw: &RawData) -> PyResult<RawData> {
+    let copy_items = |items: &[RawData]| -> PyResult<RawList> {
+        let items = items.iter().map(|item| deep_copy_raw(py, item));
+        Ok(Arc::new(items.collect::<PyResult<_>>()?))
+    };
+    match raw {
+        RawData::List(items) => Ok(RawData::List(copy_items(items)?)),
+        RawData::Set(items) => Ok(RawData::Set(copy_items(items)?)),
+        RawData::Tuple(items) => Ok(RawData::Tuple(copy_items(items)?)),
+        RawData::Dict(items) => {
+            let items = items
+                .iter()
+                .map(|(k, v)| Ok((deep_copy_raw(py, k)?, deep_copy_raw(py, v)?)));
+            Ok(RawData::Dict(Arc::new(items.collect::<PyResult<_>>()?)))
+        }
+        RawData::Py(py_obj) => Ok(RawData::Py(Arc::new(deep_copy_py(py, py_obj)?))),
+        raw => Ok(raw.clone()),
+    }
+}
+
+static DEEPCOPY: GILOnceCell<PyObject> = GILOnceCell::new();
+
+/// Models are copied directly since `ModelData` doesn't support `copy.deepcopy`
+fn deep_copy_py(py: Python, py_obj: &PyObject) -> PyResult<PyObject> {
+    match get_model_data(py_obj.bind(py)) {
+        Some(model_data) => {
+            let copy = model_data.try_borrow()?.copy(py, true)?;
+            Ok(new_instance(py, copy)?.0)
+        }
+        None => {
+            let deepcopy = DEEPCOPY.get_or_try_init(py, || {
+                Ok::<_, PyErr>(py.import_bound("copy")?.getattr("deepcopy")?.unbind())
+            })?;
+            deepcopy.call1(py, (py_obj,))
+        }
+    }
+}
+
+/// Find the `ModelData` of a model instance held as a plain python object, e.g. as an item in a list
+fn get_model_data<'py>(py_value: &Bound<'py, PyAny>) -> Option<Bound<'py, ModelData>> {
+    // avoid a failing getattr for the common case of strings from python input
+    if py_value.is_instance_of::<PyString>() {
+        return None;
+    }
+    let model_data = py_value
+        .getattr(intern!(py_value.py(), "__pydantic_model_data__"))
+        .ok()?;
+    model_data.downcast_into::<ModelData>().ok()
+}
+
+/// Convert a field value to python for `model_dump`, nested models become dicts
+fn dump_field_value(
+    py: Python,
+    field_value: &FieldValue,
+    options: DumpOptions,
+) -> PyResult<PyObject> {
+    match field_value {
+        FieldValue::Model(instance, model_data) => {
+            dump_model(model_data.bind(py), instance.bind(py), options)
+        }
+        // the raw data of `Both` is used so models in containers are dumped too
+        FieldValue::Raw(raw) | FieldValue::Both(_, raw) => dump_raw_data(py, raw, options),
+        FieldValue::Py(py_obj) => dump_py_object(py_obj.bind(py), options),
+    }
+}
+
+fn dump_raw_data(py: Python, raw: &RawData, options: DumpOptions) -> PyResult<PyObject> {
+    match raw {
+        RawData::List(v) => {
+            let items = v
+                .iter()
+                .map(|v| dump_raw_data(py, v, options))
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(PyList::new_bound(py, items).into())
+        }
+        RawData::Tuple(v) => {
+            let items = v
+                .iter()
+                .map(|v| dump_raw_data(py, v, options))
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(PyTuple::new_bound(py, items).into())
+        }
+        RawData::Set(v) => {
+            let items = v
+                .iter()
+                .map(|v| dump_raw_data(py, v, options))
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(PySet::new_bound(py, &items)?.into())
+        }
+        RawData::Dict(o) => {
+            let dict = PyDict::new_bound(py);
+            for (k, v) in o.iter() {
+                dict.set_item(k.to_py(py)?, dump_raw_data(py, v, options)?)?;
+            }
+            Ok(dict.into())
+        }
+        RawData::Py(py_obj) => dump_py_object(py_obj.bind(py), options),
+        _ => raw.to_py(py),
+    }
+}
+
+fn dump_py_object(py_obj: &Bound<'_, PyAny>, options: DumpOptions) -> PyResult<PyObject> {
+    match get_model_data(py_obj) {
+        Some(model_data) => dump_model(&model_data, py_obj, options),
+        None => Ok(py_obj.clone().unbind()),
+    }
+}
+
+fn dump_model(
+    model_data: &Bound<'_, ModelData>,
+    instance: &Bound<'_, PyAny>,
+    options: DumpOptions,
+) -> PyResult<PyObject> {
+    let computed = computed_values(model_data, Some(instance))?;
+    model_data
+        .try_borrow_mut()?
+        .dump_python(model_data.py(), options, None, &computed)
+}
+
+/// Called before `model_data` is borrowed for the dump, since the methods will usually access fields
+fn computed_values(
+    model_data: &Bound<'_, ModelData>,
+    instance: Option<&Bound<'_, PyAny>>,
+) -> PyResult<Vec<PyObject>> {
+    let computed_fields = model_data.try_borrow()?.config.computed_fields.clone();
+    if computed_fields.is_empty() {
+        return Ok(Vec::new());
+    }
+    let instance = match instance {
+        Some(instance) => instance.clone(),
+        None => bind_instance(model_data)?,
+    };
+    computed_fields
+        .iter()
+        .map(|computed_field| {
+            Ok(instance
+                .call_method0(computed_field.method.bind(model_data.py()))?
+                .unbind())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::run_python;
+
+    #[test]
+    fn dump() {
+        run_python(
+            r#"
+            default = {'x': [1, 2.5, None, True, b'ab']}
+            v = model_validator(
+                field('s', {'type': 'string'}),
+                field('l', {'type': 'list', 'items_schema': {'type': 'string'}}),
+                field('d', {'type': 'string'}, default=default),
+            )
+            data = v.validate_python({'s': 'héllo', 'l': ['a', 'b']}).__pydantic_model_data__
+            assert data.model_dump() == {'s': 'héllo', 'l': ['a', 'b'], 'd': default}
+            # bytes are base64 encoded in JSON
+            assert data.model_dump_json() == '{"s":"héllo","l":["a","b"],"d":{"x":[1,2.5,null,true,"YWI="]}}'
+            assert data.model_dump_json() == data.model_dump_json()
+            "#,
+        );
+    }
+
+    #[test]
+    fn dump_by_alias() {
+        run_python(
+            r#"
+            import json
+            child = {'type': 'model', 'cls': Model, 'fields': [
+                field('user_id', {'type': 'int'}, serialization_alias='userId'), field('tag', {'type': 'string'}, default=None),
+            ]}
+            v = model_validator(
+                field('user_id', {'type': 'int'}, serialization_alias='userId'),
+                field('name', {'type': 'string'}, default='n'),
+                field('kids', {'type': 'list', 'items_schema': child}),
+                field('kid', child),
+                extra_behavior='allow',
+            )
+            for m in [
+                v.validate_python({'user_id': 1, 'kids': [{'user_id': 2}], 'kid': {'user_id': 3, 'tag': 't'}, 'extra': 'e'}),
+                v.validate_json(b'{"user_id": 1, "kids": [{"user_id": 2}], "kid": {"user_id": 3, "tag": "t"}, "extra": "e"}'),
+            ]:
+                data = m.__pydantic_model_data__
+                assert data.model_dump()['kids'] == [{'user_id': 2, 'tag': None}]
+                for kwargs, expected in [
+                    ({}, {'userId': 1, 'name': 'n', 'kids': [{'userId': 2, 'tag': None}], 'kid': {'userId': 3, 'tag': 't'}, 'extra': 'e'}),
+                    ({'exclude_none': True}, {'userId': 1, 'name': 'n', 'kids': [{'userId': 2}], 'kid': {'userId': 3, 'tag': 't'}, 'extra': 'e'}),
+                    ({'exclude_unset': True}, {'userId': 1, 'kids': [{'userId': 2}], 'kid': {'userId': 3, 'tag': 't'}, 'extra': 'e'}),
+                    ({'exclude': {'name'}}, {'userId': 1, 'kids': [{'userId': 2, 'tag': None}], 'kid': {'userId': 3, 'tag': 't'}, 'extra': 'e'}),
+                ]:
+                    # python and JSON dumps agree
+                    assert data.model_dump(by_alias=True, **kwargs) == expected, kwargs
+                    assert json.loads(data.model_dump_json(by_alias=True, **kwargs)) == expected, kwargs
+            "#,
+        );
+    }
+
+    #[test]
+    fn exclude_none() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(
+                field('a', {'type': 'int'}), field('b', {'type': 'string'}, default=None), field('c', {'type': 'string'}, default='x'),
+                extra_behavior='allow',
+            )
+            data = v.validate_json(b'{"a": 1, "e": 2, "n": null}').__pydantic_model_data__
+            assert data.model_dump() == {'a': 1, 'b': None, 'c': 'x', 'e': 2, 'n': None}
+            assert data.model_dump(exclude_none=True) == {'a': 1, 'c': 'x', 'e': 2}
+            assert json.loads(data.model_dump_json(exclude_none=True)) == {'a': 1, 'c': 'x', 'e': 2}
+            "#,
+        );
+    }
+
+    #[test]
+    fn include_exclude() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(
+                field('a', {'type': 'int'}), field('b', {'type': 'string'}, default=None), field('c', {'type': 'string'}, default='x'),
+                extra_behavior='allow',
+            )
+            data = v.validate_json(b'{"a": 1, "e": 2}').__pydantic_model_data__
+            for kwargs, expected in [
+                ({}, {'a': 1, 'b': None, 'c': 'x', 'e': 2}),
+                ({'exclude_none': True}, {'a': 1, 'c': 'x', 'e': 2}),
+                ({'include': {'a', 'c', 'unknown'}}, {'a': 1, 'c': 'x'}),
+                ({'exclude': ['a', 'e']}, {'b': None, 'c': 'x'}),
+                ({'include': {'a', 'b'}, 'exclude': {'b'}}, {'a': 1}),
+                ({'include': set()}, {}),
+                ({'include': ('e',), 'exclude_none': True}, {'e': 2}),
+            ]:
+                assert data.model_dump(**kwargs) == expected, kwargs
+                assert json.loads(data.model_dump_json(**kwargs)) == expected, kwargs
+            try:
+                data.model_dump(include=1)
+            except TypeError:
+                pass
+            else:
+                raise AssertionError('expected a TypeError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn eq_and_hash() {
+        run_python(
+            r#"
+            nested = {'type': 'model', 'cls': Model, 'frozen': True, 'fields': [field('x', {'type': 'int'})]}
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('l', {'type': 'list', 'items_schema': {'type': 'int'}}, default=[]),
+                field('n', nested, default=None),
+                field('d', {'type': 'dict', 'keys_schema': {'type': 'string'}, 'values_schema': {'type': 'int'}}, default={}),
+                extra_behavior='allow',
+            )
+            a = v.validate_json(b'{"a": 1, "l": [1, 2], "n": {"x": 1}, "d": {"p": 1, "q": 2}, "z": 1}')
+            b = v.validate_python({'a': 1, 'l': [1, 2], 'n': {'x': 1}, 'd': {'q': 2, 'p': 1}, 'z': 1.0})
+            assert a == b and not a != b
+            assert a != v.validate_json(b'{"a": 1, "l": [1, 2], "n": {"x": 2}, "d": {"p": 1, "q": 2}, "z": 1}')
+            assert a != v.validate_json(b'{"a": 1, "l": [1, 2], "n": {"x": 1}, "d": {"p": 1, "q": 2}, "z": 2}')
+            # a default equals the same value given explicitly
+            assert v.validate_json(b'{"a": 1}') == v.validate_python({'a': 1, 'l': [], 'd': {}})
+            assert a != 1 and a.__pydantic_model_data__ != 1
+            try:
+                hash(a)
+            except TypeError:
+                pass
+            else:
+                raise AssertionError('only frozen models are hashable')
+
+            frozen = model_validator(field('a', {'type': 'int'}), field('n', nested, default=None), frozen=True)
+            models = {
+                frozen.validate_json(b'{"a": 1}'), frozen.validate_python({'a': 1}),
+                frozen.validate_json(b'{"a": 2}'), frozen.validate_json(b'{"a": 2, "n": {"x": 1}}'),
+            }
+            assert len(models) == 3
+            "#,
+        );
+    }
+
+    #[test]
+    fn assignment() {
+        run_python(
+            r#"
+            import json
+            fields = [field('a', {'type': 'int'}), field('s', {'type': 'string'}, default='x')]
+
+            # without validate_assignment values are stored as given
+            m = model_validator(*fields).validate_json(b'{"a": 1}')
+            m.a = '42'
+            m.s = 'y'
+            assert m.a == '42'
+            assert m.__pydantic_model_data__.model_dump() == {'a': '42', 's': 'y'}
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'a': '42', 's': 'y'}
+
+            m = model_validator(*fields, validate_assignment=True).validate_python({'a': 1})
+            m.a = '7'
+            assert m.a == 7 and type(m.a) is int
+            assert errors(setattr, m, 'a', 'bad') == [('int_type', ('a',))]
+            assert m.a == 7
+            try:
+                m.q = 1
+            except AttributeError as e:
+                assert str(e) == 'q'
+            else:
+                raise AssertionError('expected an AttributeError')
+
+            frozen = model_validator(*fields, frozen=True)
+            m = frozen.validate_python({'a': 1})
+            assert errors(setattr, m, 'a', 2) == [('frozen_instance', ('a',))]
+            assert m.a == 1
+            assert hash(m) == hash(frozen.validate_json(b'{"a": 1, "s": "x"}'))
+            "#,
+        );
+    }
+
+    #[test]
+    fn model_copy() {
+        run_python(
+            r#"
+            class Nested(Model):
+                __slots__ = ()
+
+            nested = {'type': 'model', 'cls': Nested, 'fields': [field('x', {'type': 'int'})]}
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('n', nested),
+                field('ns', {'type': 'list', 'items_schema': nested}, default=[]),
+                field('o', {'type': 'string'}, default_factory=lambda: 'fac'),
+                extra_behavior='allow',
+            )
+            m = v.validate_python({'a': 1, 'n': {'x': 1}, 'ns': [{'x': 2}], 'e': [1, 2]})
+            dump = {'a': 1, 'n': {'x': 1}, 'ns': [{'x': 2}], 'o': 'fac', 'e': [1, 2]}
+            copy = m.__pydantic_model_data__.model_copy()
+            assert type(copy) is Model and copy.__pydantic_model_data__.model_dump() == dump
+            # a shallow copy shares values but not its fields
+            assert copy.n is m.n and copy.e is m.e
+            copy.a = 5
+            assert m.a == 1
+            copy.n.x = 50
+            assert m.n.x == 50
+
+            update = m.__pydantic_model_data__.model_copy(update={'a': 'not validated', 'e': 'new', 'z': 9})
+            assert update.__pydantic_model_data__.model_dump() == {**dump, 'n': {'x': 50}, 'a': 'not validated', 'e': 'new', 'z': 9}
+            assert m.__pydantic_model_data__.model_copy(update={'a': '7'}, validate=True).a == 7
+            assert errors(m.__pydantic_model_data__.model_copy, update={'a': 'x'}, validate=True) == [('int_type', ('a',))]
+
+            deep = m.__pydantic_model_data__.model_copy(deep=True)
+            assert deep == m
+            assert deep.n is not m.n and type(deep.n) is Nested
+            assert deep.ns[0] is not m.ns[0] and type(deep.ns[0]) is Nested
+            assert deep.e is not m.e
+            deep.n.x = 100
+            deep.e.append(3)
+            assert (m.n.x, m.e) == (50, [1, 2])
+            "#,
+        );
+    }
+
+    #[test]
+    fn computed_fields() {
+        run_python(
+            r#"
+            import json
+
+            class User(Model):
+                __slots__ = ()
+
+                def full_name(self):
+                    return f'{self.first} {self.last}'
+
+                def nothing(self):
+                    return None
+
+            fields = [field('first', {'type': 'string'}), field('last', {'type': 'string'}, default='Smith')]
+            user = {'type': 'model', 'cls': User, 'fields': fields, 'computed_fields': [{'name': 'full_name'}]}
+            v = SchemaValidator({**user, 'computed_fields': [{'name': 'full_name'}, {'name': 'none', 'method': 'nothing'}]})
+            data = v.validate_python({'first': 'John'}).__pydantic_model_data__
+            expected = {'first': 'John', 'last': 'Smith', 'full_name': 'John Smith', 'none': None}
+            assert data.model_dump() == expected
+            assert json.loads(data.model_dump_json()) == expected
+            assert data.model_dump(exclude={'full_name'}, exclude_none=True) == {'first': 'John', 'last': 'Smith'}
+            assert data.model_dump_json(include={'full_name'}) == '{"full_name":"John Smith"}'
+
+            v = model_validator(field('m', user), field('ms', {'type': 'list', 'items_schema': user}))
+            data = v.validate_json(b'{"m": {"first": "a", "last": "b"}, "ms": [{"first": "c"}]}').__pydantic_model_data__
+            expected = {'m': {'first': 'a', 'last': 'b', 'full_name': 'a b'}, 'ms': [{'first': 'c', 'last': 'Smith', 'full_name': 'c Smith'}]}
+            assert data.model_dump() == expected
+            assert json.loads(data.model_dump_json()) == expected
+            "#,
+        );
+    }
+
+    #[test]
+    fn inf_nan() {
+        run_python(
+            r#"
+            v = model_validator(field('a', {'type': 'int'}), extra_behavior='allow')
+            data = v.validate_python({'a': 1, 'x': float('nan'), 'y': [float('-inf')]}).__pydantic_model_data__
+            assert data.model_dump_json(inf_nan='null') == '{"a":1,"x":null,"y":[null]}'
+            assert data.model_dump_json(inf_nan='strings') == '{"a":1,"x":"NaN","y":["-Infinity"]}'
+            assert data.model_dump_json(exclude={'x'}, inf_nan='strings') == '{"a":1,"y":["-Infinity"]}'
+            # the default is to error, naming the field
+            for kwargs in [{}, {'inf_nan': 'error'}]:
+                try:
+                    data.model_dump_json(**kwargs)
+                except ValueError as e:
+                    assert str(e) == 'field "x": Out of range float values are not JSON compliant: NaN', str(e)
+                else:
+                    raise AssertionError(f'expected a ValueError for {kwargs}')
+            try:
+                data.model_dump_json(inf_nan='bad')
+            except ValueError as e:
+                assert str(e) == 'Invalid inf_nan: bad', str(e)
+            else:
+                raise AssertionError('expected a ValueError')
+
+            outer = model_validator(field('m', {'type': 'model', 'cls': Model, 'fields': [field('a', {'type': 'int'})], 'extra_behavior': 'allow'}))
+            data = outer.validate_python({'m': {'a': 1, 'z': {'k': float('inf')}}}).__pydantic_model_data__
+            try:
+                data.model_dump_json()
+            except ValueError as e:
+                assert str(e) == 'field "m": field "z": Out of range float values are not JSON compliant: Infinity', str(e)
+            else:
+                raise AssertionError('expected a ValueError')
+            assert data.model_dump_json(inf_nan='null') == '{"m":{"a":1,"z":{"k":null}}}'
+            # NaN isn't valid JSON input
+            assert errors(v.validate_json, b'{"a": 1, "x": NaN}') == [('json_invalid', ())]
+            "#,
+        );
+    }
+
+    #[test]
+    fn fields_set() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'int'}, default=2),
+                field('c', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=list),
+                extra_behavior='allow',
+            )
+            m = v.validate_python({'a': 1, 'c': [1], 'z': 3})
+            data = m.__pydantic_model_data__
+            assert data.fields_set() == {'a', 'c'}
+            # reading or dumping a default doesn't set it
+            assert m.b == 2 and data.model_dump()['b'] == 2
+            assert data.fields_set() == {'a', 'c'}
+            assert v.validate_json(b'{"b": 5, "a": 1}').__pydantic_model_data__.fields_set() == {'a', 'b'}
+            m.b = 3
+            assert data.fields_set() == {'a', 'b', 'c'}
+            copy = v.validate_python({'a': 1}).__pydantic_model_data__.model_copy(update={'b': 7})
+            assert copy.__pydantic_model_data__.fields_set() == {'a', 'b'}
+            "#,
+        );
+    }
+
+    #[test]
+    fn exclude_unset_and_defaults() {
+        run_python(
+            r#"
+            import json
+            sub = {'type': 'model', 'cls': Model, 'fields': [field('x', {'type': 'int'}, default=0), field('y', {'type': 'int'}, default=5)]}
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'int'}, default=2),
+                field('c', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=list),
+                field('d', {'type': 'int'}, default=3),
+                field('s', sub, default=None),
+            )
+            data = v.validate_python({'a': 1, 'b': 2, 'd': 4, 's': {'x': 0}}).__pydantic_model_data__
+            for kwargs, expected in [
+                ({}, {'a': 1, 'b': 2, 'c': [], 'd': 4, 's': {'x': 0, 'y': 5}}),
+                # `b` was given, even though it's the default, and `s.x` likewise
+                ({'exclude_unset': True}, {'a': 1, 'b': 2, 'd': 4, 's': {'x': 0}}),
+                ({'exclude_defaults': True}, {'a': 1, 'd': 4, 's': {}}),
+                ({'exclude_unset': True, 'exclude_defaults': True}, {'a': 1, 'd': 4, 's': {}}),
+            ]:
+                assert data.model_dump(**kwargs) == expected, kwargs
+                assert json.loads(data.model_dump_json(**kwargs)) == expected, kwargs
+
+            data = v.validate_json(b'{"a": 1, "c": []}').__pydantic_model_data__
+            assert data.model_dump_json(exclude_unset=True) == '{"a":1,"c":[]}'
+            assert data.model_dump_json(exclude_defaults=True) == '{"a":1}'
+            assert data.model_dump(exclude_unset=True, exclude_defaults=True) == {'a': 1}
+            "#,
+        );
+    }
+
+    #[test]
+    fn indent() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}), field('b', {'type': 'string'}), field('l', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=list),
+            )
+            data = v.validate_python({'a': 1, 'b': 'x'}).__pydantic_model_data__
+            assert data.model_dump_json(indent=2, exclude={'l'}) == '{\n  "a": 1,\n  "b": "x"\n}'
+            assert data.model_dump_json() == '{"a":1,"b":"x","l":[]}'
+            data = v.validate_python({'a': 1, 'b': 'x', 'l': [1, 2]}).__pydantic_model_data__
+            assert data.model_dump_json(indent=4) == '{\n    "a": 1,\n    "b": "x",\n    "l": [\n        1,\n        2\n    ]\n}'
+            "#,
+        );
+    }
+
+    #[test]
+    fn get_attr_identity() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('l', {'type': 'list', 'items_schema': {'type': 'int'}}),
+                field('d', {'type': 'dict', 'values_schema': {'type': 'int'}}, default=None),
+                validate_assignment=True,
+                extra_behavior='allow',
+            )
+            for m in [v.validate_python({'l': [1, '2'], 'd': {'a': 1}}), v.validate_json(b'{"l": [1, 2], "d": {"a": 1}, "e": [1]}')]:
+                # the same object is returned each time, so mutations stick
+                l = m.l
+                assert l == [1, 2] and m.l is l and m.d is m.d
+            data = m.__pydantic_model_data__
+            assert data.get_attr('e') is data.get_attr('e')
+            data.set_attr('l', ['3'])
+            assert m.l == [3] and m.l is not l and m.l is m.l
+            assert data.model_dump_json() == '{"l":[3],"d":{"a":1},"e":[1]}'
+            "#,
+        );
+    }
+
+    #[test]
+    fn dump_json_to_writer() {
+        run_python(
+            r#"
+            import io
+            v = model_validator(field('a', {'type': 'int'}), field('l', {'type': 'list', 'items_schema': {'type': 'int'}}))
+            data = v.validate_python({'a': 1, 'l': list(range(50000))}).__pydantic_model_data__
+            dumped = data.dump_json_to_writer()
+            assert type(dumped) is bytes and dumped == data.model_dump_json().encode()
+
+            class Writer(io.BytesIO):
+                calls = 0
+
+                def write(self, b):
+                    Writer.calls += 1
+                    return super().write(b)
+
+            # written in chunks rather than one call or one per value
+            writer = Writer()
+            assert data.dump_json_to_writer(writer) is None
+            assert writer.getvalue() == dumped
+            assert 1 < Writer.calls < 10, Writer.calls
+
+            writer = io.BytesIO()
+            v.validate_python({'a': 1, 'l': [1]}).__pydantic_model_data__.dump_json_to_writer(writer, indent=2, exclude={'a'})
+            assert writer.getvalue() == b'{\n  "l": [\n    1\n  ]\n}'
+
+            class Full:
+                def write(self, b):
+                    raise OSError('disk full')
+
+            for writer, exc in [(Full(), OSError), (io.StringIO(), TypeError)]:
+                try:
+                    data.dump_json_to_writer(writer)
+                except exc:
+                    pass
+                else:
+                    raise AssertionError(f'expected {exc.__name__}')
+            "#,
+        );
+    }
+
+    #[test]
+    fn unsupported_type() {
+        run_python(
+            r#"
+            class X:
+                pass
+
+            data = model_validator(
+                field('a', {'type': 'int'}), field('weird', {'type': 'int'}, default=X(), serialization_alias='W'),
+            ).validate_python({'a': 1}).__pydantic_model_data__
+            for kwargs, name in [({}, 'weird'), ({'by_alias': True}, 'W')]:
+                for dump in [data.model_dump_json, data.dump_json_to_writer]:
+                    try:
+                        dump(**kwargs)
+                    except ValueError as e:
+                        assert str(e) == f'field "{name}": unsupported type', str(e)
+                    else:
+                        raise AssertionError('expected a ValueError')
+
+            inner = {'type': 'model', 'cls': Model, 'fields': [field('weird', {'type': 'int'}, default=X())]}
+            data = model_validator(field('items', {'type': 'list', 'items_schema': inner})).validate_python({'items': [{}]}).__pydantic_model_data__
+            try:
+                data.model_dump_json()
+            except ValueError as e:
+                assert str(e) == 'field "items": field "weird": unsupported type', str(e)
+            else:
+                raise AssertionError('expected a ValueError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn exclude_fields() {
+        run_python(
+            r#"
+            import json
+            inner = {'type': 'model', 'cls': Model, 'fields': [
+                field('x', {'type': 'int'}), field('secret', {'type': 'string'}, exclude=True, default='s'),
+            ]}
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('password', {'type': 'string'}, exclude=True),
+                field('items', {'type': 'list', 'items_schema': inner}, default=[]),
+            )
+            # excluded fields are still validated and readable, just never dumped
+            assert errors(v.validate_python, {'a': 1}) == [('missing', ('password',))]
+            m = v.validate_python({'a': 1, 'password': 'hunter2', 'items': [{'x': 1, 'secret': 'k'}, {'x': 2}]})
+            assert (m.password, m.items[0].secret, m.items[1].secret) == ('hunter2', 'k', 's')
+            data = m.__pydantic_model_data__
+            expected = {'a': 1, 'items': [{'x': 1}, {'x': 2}]}
+            assert data.model_dump() == expected
+            assert json.loads(data.model_dump_json()) == expected
+            assert json.loads(data.dump_json_to_writer(indent=1)) == expected
+            assert data.model_dump(include={'a', 'password'}) == {'a': 1}
+            assert data.model_dump(exclude_unset=True) == expected
+            assert data.fields_set() == {'a', 'items', 'password'}
+            "#,
+        );
+    }
+
+    #[test]
+    fn repr() {
+        run_python(
+            r#"
+            class User(Model):
+                __slots__ = ()
+
+            user = {'type': 'model', 'cls': User, 'fields': [field('id', {'type': 'int'}), field('name', {'type': 'string'}, default='anon')]}
+            v = SchemaValidator(user)
+            assert repr(v.validate_python({'id': 1, 'name': 'x'})) == "User(id=1, name='x')"
+            assert repr(v.validate_json(b'{"id": 2}')) == "User(id=2, name='anon')"
+            v = SchemaValidator({'type': 'model', 'cls': User, 'extra_behavior': 'allow', 'fields': [
+                field('owner', user), field('members', {'type': 'list', 'items_schema': user}), field('tag', {'type': 'string'}, default=None),
+            ]})
+            m = v.validate_json(b'{"owner": {"id": 1}, "members": [{"id": 2, "name": "b"}], "x": [1, "y"]}')
+            assert repr(m) == "User(owner=User(id=1, name='anon'), members=[User(id=2, name='b')], tag=None, x=[1, 'y'])", repr(m)
+            "#,
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        run_python(
+            r#"
+            import datetime, decimal, uuid
+            u = uuid.UUID('12345678-1234-5678-1234-567812345678')
+            dt = datetime.datetime(2024, 1, 2, 3, 4, 5, 600000, tzinfo=datetime.timezone.utc)
+            d = decimal.Decimal('1.10')
+            v = model_validator(
+                field('dt', {'type': 'datetime'}), field('u', {'type': 'uuid'}), field('d', {'type': 'decimal'}),
+                field('dt2', {'type': 'datetime'}, default=dt), field('u2', {'type': 'uuid'}, default=u), field('d2', {'type': 'decimal'}, default=d),
+            )
+            expected = (
+                '{"dt":"2024-01-02T03:04:05.600000Z","u":"12345678-1234-5678-1234-567812345678","d":"1.10",'
+                '"dt2":"2024-01-02T03:04:05.600000Z","u2":"12345678-1234-5678-1234-567812345678","d2":"1.10"}'
+            )
+            for m in [
+                v.validate_json(b'{"dt": "2024-01-02T03:04:05.6Z", "u": "12345678123456781234567812345678", "d": "1.10"}'),
+                v.validate_python({'dt': dt, 'u': u, 'd': d}),
+                v.validate_python({'dt': '2024-01-02T03:04:05.600Z', 'u': str(u), 'd': '1.10'}),
+            ]:
+                dumped = m.__pydantic_model_data__.model_dump_json()
+                assert dumped == expected, dumped
+                m = v.validate_json(dumped)
+                assert (m.dt, m.u, m.d) == (dt, u, d)
+                assert m.__pydantic_model_data__.model_dump_json() == dumped
+            "#,
+        );
+    }
+
+    #[test]
+    fn round_trip_aliases() {
+        run_python(
+            r#"
+            import json
+            from types import SimpleNamespace
+            child = {'type': 'model', 'cls': Model, 'populate_by_name': True, 'fields': [
+                field('item_id', {'type': 'int'}, validation_alias='itemId', serialization_alias='ITEM'),
+            ]}
+            v = model_validator(
+                field('user_id', {'type': 'int'}, validation_alias='userId', serialization_alias='uid'),
+                field('first_name', {'type': 'string'}, validation_alias='firstName', default='n'),
+                field('kid', child, default=None),
+                populate_by_name=True,
+                from_attributes=True,
+            )
+            data = v.validate_json('{"userId": 1, "kid": {"itemId": 2}}').__pydantic_model_data__
+            assert data.model_dump_json(by_alias=True) == '{"uid":1,"first_name":"n","kid":{"ITEM":2}}'
+            assert data.model_dump_json(by_alias=True, round_trip=True) == '{"userId":1,"firstName":"n","kid":{"itemId":2}}'
+
+            # round trip dumps use the key each field was given with, so they validate back to the same model
+            for source in ['{"userId": 1, "kid": {"itemId": 2}}', '{"user_id": 1, "firstName": "a", "kid": {"item_id": 2}}']:
+                data = v.validate_json(source).__pydantic_model_data__
+                dumped = data.model_dump_json(round_trip=True, exclude_unset=True)
+                assert json.loads(dumped) == json.loads(source), dumped
+                assert data.model_dump(round_trip=True, exclude_unset=True) == json.loads(source)
+                assert v.validate_json(dumped).__pydantic_model_data__.model_dump() == data.model_dump()
+
+            data = v.validate_python(SimpleNamespace(user_id=3, firstName='b')).__pydantic_model_data__
+            assert data.model_dump(round_trip=True) == {'user_id': 3, 'firstName': 'b', 'kid': None}
+            assert data.model_copy().__pydantic_model_data__.model_dump(round_trip=True) == {'user_id': 3, 'firstName': 'b', 'kid': None}
+            "#,
+        );
+    }
+}
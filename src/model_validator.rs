@@ -2,62 +2,245 @@ use std::fmt::Debug;
 use std::ptr::null_mut;
 use std::sync::Arc;
 
-use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::{PyAttributeError, PyTypeError, PyValueError};
 use pyo3::intern;
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyString, PyTuple, PyType};
+use pyo3::types::{PyDict, PyList, PyString, PyTuple, PyType};
 
-use ahash::AHashMap;
-use jiter::Jiter;
+use ahash::AHashSet;
+use jiter::{Jiter, Peek};
 
-use crate::errors::{ErrorType, LineError, ValResult};
-use crate::field::{get_as_req, parse_fields, FieldInfo, FieldValue};
-use crate::model_data::ModelData;
-use crate::validators::Validator;
+use crate::errors::{ErrorOptions, ErrorType, LineError, LocItem, ValError, ValResult};
+use crate::field::{
+    get_as_opt, get_as_req, parse_fields, AliasGenerator, FieldInfo, FieldValue, RawData,
+};
+use crate::key_lookup::KeyLookup;
+use crate::model_data::{ComputedField, ModelConfig, ModelData};
+use crate::validators::{py_str_to_str, Definitions, PyInput, ValidationState, Validator};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtraBehavior {
+    Ignore,
+    Forbid,
+    Allow,
+}
+
+impl ExtraBehavior {
+    fn new(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let extra_behavior: Option<String> = get_as_opt(schema, "extra_behavior")?;
+        match extra_behavior.as_deref() {
+            None | Some("ignore") => Ok(Self::Ignore),
+            Some("forbid") => Ok(Self::Forbid),
+            Some("allow") => Ok(Self::Allow),
+            Some(s) => Err(PyValueError::new_err(format!(
+                "Invalid extra_behavior: {s}"
+            ))),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct ModelValidator {
     field_info: Arc<Vec<FieldInfo>>,
-    key_lookup: Arc<AHashMap<String, usize>>,
-    cls: Py<PyType>,
+    // field names, shared with `ModelData` for attribute access
+    key_lookup: Arc<KeyLookup>,
+    // keys in input, validation aliases or else field names
+    input_lookup: KeyLookup,
+    // `None` for "typed-dict" schemas, which validate to a dict rather than an instance
+    cls: Option<Py<PyType>>,
+    // shared with `ModelData` as the title of errors from `set_attr`
+    name: Arc<str>,
+    extra_behavior: ExtraBehavior,
+    // JSON objects with a repeated key are an error, otherwise the last value wins
+    forbid_duplicate_keys: bool,
+    // python input which isn't a dict is validated from its attributes, e.g. ORM objects
+    from_attributes: bool,
+    // empty strings are treated as missing, so the field's default applies, e.g. for form data
+    coerce_empty_to_none: bool,
+    // only dicts are accepted, otherwise other mappings are too
+    strict: bool,
+    // fields with a validation alias are also found under their name, the alias wins if both are in input
+    populate_by_name: bool,
+    config: ModelConfig,
 }
 
 impl ModelValidator {
-    pub fn new(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
         let fields = get_as_req(schema, "fields")?;
-        let field_info = parse_fields(schema.py(), fields)?;
-        let key_lookup: AHashMap<String, usize> = field_info
-            .iter()
-            .enumerate()
-            .map(|(i, f)| (f.name.clone(), i))
-            .collect();
+        let alias_generator: Option<String> = get_as_opt(schema, "alias_generator")?;
+        let alias_generator = alias_generator
+            .as_deref()
+            .map(AliasGenerator::new)
+            .transpose()?;
+        let field_info = parse_fields(schema.py(), fields, strict, alias_generator, definitions)?;
+        let key_lookup = KeyLookup::new(field_info.iter().map(|f| f.name.as_str()));
+        let input_lookup = KeyLookup::new(field_info.iter().map(FieldInfo::input_name));
 
-        let class: Bound<PyType> = get_as_req(schema, "cls")?;
+        let schema_type: String = get_as_req(schema, "type")?;
+        let (cls, name) = match schema_type.as_str() {
+            "typed-dict" => (None, "typed-dict".into()),
+            _ => {
+                let class: Bound<PyType> = get_as_req(schema, "cls")?;
+                let name = class.name()?.to_str()?.into();
+                (Some(class.unbind()), name)
+            }
+        };
 
         Ok(Self {
             field_info: Arc::new(field_info),
             key_lookup: Arc::new(key_lookup),
-            cls: class.into(),
+            input_lookup,
+            cls,
+            name,
+            extra_behavior: ExtraBehavior::new(schema)?,
+            forbid_duplicate_keys: get_as_opt(schema, "forbid_duplicate_keys")?.unwrap_or(false),
+            from_attributes: get_as_opt(schema, "from_attributes")?.unwrap_or(false),
+            coerce_empty_to_none: get_as_opt(schema, "coerce_empty_to_none")?.unwrap_or(false),
+            strict,
+            populate_by_name: get_as_opt(schema, "populate_by_name")?.unwrap_or(false),
+            config: ModelConfig {
+                frozen: get_as_opt(schema, "frozen")?.unwrap_or(false),
+                validate_assignment: get_as_opt(schema, "validate_assignment")?.unwrap_or(false),
+                error_options: ErrorOptions::new(schema)?,
+                computed_fields: Arc::new(parse_computed_fields(schema)?),
+            },
         })
     }
 }
 
+/// Each computed field names a method on the class, `method` defaults to `name`
+fn parse_computed_fields(schema: &Bound<'_, PyDict>) -> PyResult<Vec<ComputedField>> {
+    let Some(computed_fields) = get_as_opt::<Bound<PyList>>(schema, "computed_fields")? else {
+        return Ok(Vec::new());
+    };
+    computed_fields
+        .iter()
+        .map(|computed_field| {
+            let computed_field: &Bound<PyDict> = computed_field.downcast()?;
+            let name: String = get_as_req(computed_field, "name")?;
+            let method: Option<String> = get_as_opt(computed_field, "method")?;
+            let method = PyString::new_bound(schema.py(), method.as_deref().unwrap_or(&name));
+            Ok(ComputedField {
+                name,
+                method: method.unbind(),
+            })
+        })
+        .collect()
+}
+
+impl ModelValidator {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn field_info(&self) -> &[FieldInfo] {
+        &self.field_info
+    }
+
+    /// Returns the model built from the valid fields and defaults along with the errors
+    pub fn validate_collect<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<(FieldValue, Vec<LineError>)> {
+        let mut model_validate = ModelValidate::new(self);
+        model_validate.read_python(state, data, PyInput::Python)?;
+        model_validate.finish_collect(state.py)
+    }
+
+    /// Only for "model" schemas, "typed-dict" schemas have no model data
+    pub fn validate_to_data<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<ModelData> {
+        let Some(cls) = &self.cls else {
+            let msg = "typed-dict schemas validate to a dict, not model data";
+            return Err(PyTypeError::new_err(msg).into());
+        };
+        let mut model_validate = ModelValidate::new(self);
+        model_validate.read_python(state, data, PyInput::Python)?;
+        model_validate.check_missing();
+        if model_validate.errors.is_empty() {
+            Ok(model_validate.into_model_data(cls.clone_ref(state.py)))
+        } else {
+            Err(model_validate.errors.into())
+        }
+    }
+
+    /// Fields aren't required, only those supplied are returned in a dict
+    pub fn validate_partial<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let mut model_validate = ModelValidate::new(self);
+        model_validate.partial = true;
+        model_validate.validate_python(state, data, PyInput::Python)
+    }
+}
+
 impl Validator for ModelValidator {
-    fn validate_python<'py>(&self, py: Python, data: &Bound<'py, PyAny>) -> ValResult<FieldValue> {
-        ModelValidate::new(self).validate_python(py, data)
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        ModelValidate::new(self).validate_python(state, data, PyInput::Python)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        ModelValidate::new(self).validate_json(state, jiter)
     }
 
-    fn validate_json(&self, py: Python, jiter: &mut Jiter) -> ValResult<FieldValue> {
-        ModelValidate::new(self).validate_json(py, jiter)
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        ModelValidate::new(self).validate_python(state, data, PyInput::Strings)
     }
 }
 
+enum JsonField {
+    Known(usize),
+    ByName(usize),
+    Ignored,
+    Forbidden(String),
+    Extra(String),
+}
+
 struct ModelValidate<'a> {
     validator: &'a ModelValidator,
     errors: Vec<LineError>,
     data: Vec<Option<FieldValue>>,
+    extra: Vec<(String, FieldValue)>,
     field_count: usize,
+    // includes fields which failed validation
     fields_found: usize,
+    // indexes of fields which failed validation, so they aren't also reported as missing
+    fields_failed: Vec<usize>,
+    // the field expected next in JSON input, see `json_field`
+    next_index: usize,
+    // missing fields aren't reported and the result is a dict, see `validate_partial`
+    partial: bool,
+    // partial JSON input ended within this model, so missing fields aren't reported
+    truncated: bool,
+    // keys of the JSON object so far, only with `forbid_duplicate_keys`
+    seen_keys: AHashSet<String>,
+    // results for fields found by name with `populate_by_name`, only used if the alias isn't found,
+    // see `set_by_name`
+    by_name: Vec<(usize, ValResult<FieldValue>)>,
+    // indexes of fields set from `by_name`, kept by `ModelData` for `round_trip` dumps
+    fields_by_name: Vec<usize>,
 }
 
 impl<'a> ModelValidate<'a> {
@@ -68,91 +251,481 @@ impl<'a> ModelValidate<'a> {
             errors: Vec::new(),
             // can't clone `FieldValue`
             data: (0..field_count).map(|_| None).collect(),
+            extra: Vec::new(),
             field_count,
             fields_found: 0,
+            fields_failed: Vec::new(),
+            next_index: 0,
+            partial: false,
+            truncated: false,
+            seen_keys: AHashSet::new(),
+            by_name: Vec::new(),
+            fields_by_name: Vec::new(),
         }
     }
 
-    fn validate_python<'py>(mut self, py: Python, data: &Bound<'py, PyAny>) -> ValResult<FieldValue> {
-        let dict = data.downcast::<PyDict>().map_err(|_| ErrorType::DictType)?;
+    fn validate_python<'py>(
+        mut self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        self.read_python(state, data, input)?;
+        self.finish(state.py)
+    }
+
+    /// Errors for fields are collected, only invalid input as a whole is returned as an error
+    fn read_python<'py>(
+        &mut self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<()> {
+        let py = state.py;
+        let dict = match data.downcast::<PyDict>() {
+            Ok(dict) => dict,
+            Err(_) if !state.strict_or(self.validator.strict) && is_mapping(data) => {
+                for key in data.call_method0(intern!(py, "keys"))?.iter()? {
+                    let key = key?;
+                    let value = data.get_item(&key)?;
+                    self.read_item(state, &key, value, input)?;
+                }
+                return self.set_by_name();
+            }
+            Err(_) if self.validator.from_attributes => {
+                return self.read_attributes(state, data, input)
+            }
+            Err(_) => return Err(ErrorType::DictType.into()),
+        };
 
         for (key, value) in dict.iter() {
-            if let Ok(key_py_str) = key.downcast::<PyString>() {
-                let key_str = key_py_str.to_str()?;
-                if let Some((index, field_info)) = self.find_validator(key_str) {
-                    match field_info.validator.validate_python(py, &value) {
-                        Ok(field_value) => self.set_value(index, field_value),
-                        Err(e) => self.errors.extend(e.line_errors_with_loc(key_str)?),
+            self.read_item(state, &key, value, input)?;
+        }
+        self.set_by_name()
+    }
+
+    /// Keys which aren't strings are ignored, keys which aren't valid unicode are errors
+    fn read_item<'py>(
+        &mut self,
+        state: &mut ValidationState,
+        key: &Bound<'py, PyAny>,
+        value: Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<()> {
+        let Ok(key_py_str) = key.downcast::<PyString>() else {
+            return Ok(());
+        };
+        let key_str = match py_str_to_str(key_py_str) {
+            Ok(key_str) => key_str,
+            Err(e) => {
+                let loc = key_py_str.to_string_lossy().into_owned();
+                self.errors
+                    .extend(e.with_py_input(key).line_errors_with_loc(loc)?);
+                return Ok(());
+            }
+        };
+        if let Some((index, field_info)) = self.find_validator(key_str) {
+            if self.is_empty_str(&value) {
+                return Ok(());
+            }
+            match input.validate(&field_info.validator, state, &value) {
+                Ok(field_value) => self.set_value(index, field_value),
+                Err(e) => {
+                    let loc = field_info.input_loc();
+                    self.set_error(index, e.with_py_input(&value), loc)?;
+                }
+            }
+        } else if let Some((index, field_info)) = self.find_by_name(key_str) {
+            if self.is_found(index) || self.is_empty_str(&value) {
+                return Ok(());
+            }
+            let result = input.validate(&field_info.validator, state, &value);
+            self.by_name
+                .push((index, result.map_err(|e| e.with_py_input(&value))));
+        } else {
+            match self.validator.extra_behavior {
+                ExtraBehavior::Ignore => (),
+                ExtraBehavior::Forbid => {
+                    self.forbid_extra(key_str, RawData::Py(Arc::new(value.unbind())));
+                }
+                ExtraBehavior::Allow => self
+                    .extra
+                    .push((key_str.to_owned(), FieldValue::Py(value.unbind()))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Fields are read with `getattr`, missing attributes are missing fields and other attributes are ignored
+    fn read_attributes<'py>(
+        &mut self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<()> {
+        let fields = self.validator.field_info.iter().enumerate();
+        for (index, field_info) in fields {
+            let (value, by_name) = match get_attribute(data, field_info.input_name())? {
+                Some(value) => (value, false),
+                None if self.validator.populate_by_name => {
+                    match get_attribute(data, &field_info.name)? {
+                        Some(value) => (value, true),
+                        None => continue,
+                    }
+                }
+                None => continue,
+            };
+            if self.is_empty_str(&value) {
+                continue;
+            }
+            match input.validate(&field_info.validator, state, &value) {
+                Ok(field_value) => {
+                    self.set_value(index, field_value);
+                    if by_name {
+                        self.fields_by_name.push(index);
                     }
                 }
+                Err(e) => {
+                    let loc = match by_name {
+                        true => field_info.name.as_str().into(),
+                        false => field_info.input_loc(),
+                    };
+                    self.set_error(index, e.with_py_input(&value), loc)?;
+                }
             }
         }
 
-        self.finish(py)
+        Ok(())
     }
 
-    fn validate_json(mut self, py: Python, jiter: &mut Jiter) -> ValResult<FieldValue> {
-        if let Some(first_key) = jiter.next_object()? {
-            self.validate_json_field(py, first_key.to_string(), jiter)?;
-
-            while let Some(key) = jiter.next_key()? {
-                self.validate_json_field(py, key.to_string(), jiter)?;
+    fn validate_json(
+        mut self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::Object {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::DictType.into());
+        }
+        let mut next_key = state.truncate(jiter.known_object())?.flatten();
+        while let Some(key) = next_key {
+            let json_field = self.json_field(key);
+            if self.is_duplicate(key, &json_field) {
+                let loc = match json_field {
+                    JsonField::Known(index) => self.validator.field_info[index].input_loc(),
+                    JsonField::ByName(index) => {
+                        self.validator.field_info[index].name.as_str().into()
+                    }
+                    JsonField::Forbidden(key) | JsonField::Extra(key) => key.into(),
+                    JsonField::Ignored => key.into(),
+                };
+                if let Some(value) = state.truncate(jiter.next_value())? {
+                    let error = LineError::new_loc(ErrorType::DuplicateKey, loc);
+                    self.errors.push(error.with_input(value.into()));
+                }
+            } else {
+                self.validate_json_field(state, json_field, jiter)?;
+            }
+            if state.truncated() {
+                break;
             }
+            next_key = state.truncate(jiter.next_key())?.flatten();
         }
 
-        self.finish(py)
+        self.set_by_name()?;
+        // fields after partial JSON input ends aren't required
+        self.truncated = state.truncated();
+        self.finish(state.py)
     }
 
-    fn validate_json_field(&mut self, py: Python, k: String, jiter: &mut Jiter) -> ValResult<()> {
-        if let Some((index, field_info)) = self.find_validator(&k) {
-            match field_info.validator.validate_json(py, jiter) {
-                Ok(field_value) => self.set_value(index, field_value),
-                Err(e) => self.errors.extend(e.line_errors_with_loc(k.as_str())?),
-            };
-        } else {
-            jiter.next_skip()?;
+    /// JSON from the same schema has keys in field order, so the field after the last one is checked first
+    fn json_field(&mut self, key: &str) -> JsonField {
+        let index = match self.validator.field_info.get(self.next_index) {
+            Some(field_info) if field_info.input_name() == key => Some(self.next_index),
+            _ => self.validator.input_lookup.get(key),
+        };
+        match index {
+            Some(index) => {
+                self.next_index = index + 1;
+                JsonField::Known(index)
+            }
+            None => match self.find_by_name(key) {
+                Some((index, _)) => JsonField::ByName(index),
+                None => match self.validator.extra_behavior {
+                    ExtraBehavior::Ignore => JsonField::Ignored,
+                    ExtraBehavior::Forbid => JsonField::Forbidden(key.to_owned()),
+                    ExtraBehavior::Allow => JsonField::Extra(key.to_owned()),
+                },
+            },
+        }
+    }
+
+    fn validate_json_field(
+        &mut self,
+        state: &mut ValidationState,
+        json_field: JsonField,
+        jiter: &mut Jiter,
+    ) -> ValResult<()> {
+        match json_field {
+            JsonField::Known(index) => {
+                if self.skip_empty_json(state, jiter)? {
+                    return Ok(());
+                }
+                let field_info = &self.validator.field_info[index];
+                match field_info.validator.validate_json_item(state, jiter)? {
+                    Some(Ok(field_value)) => self.set_value(index, field_value),
+                    Some(Err(e)) => self.set_error(index, e, field_info.input_loc())?,
+                    None => (),
+                };
+            }
+            JsonField::ByName(index) => {
+                if self.is_found(index) {
+                    state.truncate(jiter.next_skip())?;
+                    return Ok(());
+                } else if self.skip_empty_json(state, jiter)? {
+                    return Ok(());
+                }
+                let field_info = &self.validator.field_info[index];
+                if let Some(result) = field_info.validator.validate_json_item(state, jiter)? {
+                    self.by_name.push((index, result));
+                }
+            }
+            JsonField::Ignored => {
+                state.truncate(jiter.next_skip())?;
+            }
+            JsonField::Forbidden(k) => {
+                if let Some(value) = state.truncate(jiter.next_value())? {
+                    self.forbid_extra(k, value.into());
+                }
+            }
+            JsonField::Extra(k) => {
+                if let Some(value) = state.truncate(jiter.next_value())? {
+                    self.extra.push((k, FieldValue::new_raw(value)));
+                }
+            }
         }
         Ok(())
     }
 
+    /// With `coerce_empty_to_none`, an empty string for a field is as if the field wasn't in the input
+    fn is_empty_str(&self, value: &Bound<'_, PyAny>) -> bool {
+        self.validator.coerce_empty_to_none
+            && value
+                .downcast::<PyString>()
+                .is_ok_and(|py_str| py_str.to_str().is_ok_and(str::is_empty))
+    }
+
+    /// `is_empty_str` for JSON, an empty string is skipped and `true` returned
+    fn skip_empty_json(&self, state: &mut ValidationState, jiter: &mut Jiter) -> ValResult<bool> {
+        if !self.validator.coerce_empty_to_none
+            || state.truncate(jiter.peek())? != Some(Peek::String)
+        {
+            return Ok(false);
+        }
+        let mut lookahead = jiter.clone();
+        match lookahead.known_str() {
+            Ok("") => {
+                *jiter = lookahead;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Known fields are only found under one key, so only other keys need to be copied to check them
+    fn is_duplicate(&mut self, key: &str, json_field: &JsonField) -> bool {
+        if !self.validator.forbid_duplicate_keys {
+            return false;
+        }
+        match json_field {
+            JsonField::Known(index) => self.is_found(*index),
+            _ => !self.seen_keys.insert(key.to_owned()),
+        }
+    }
+
+    fn forbid_extra(&mut self, key: impl Into<LocItem>, input: RawData) {
+        let error = LineError::new_loc(ErrorType::ExtraForbidden, key).with_input(input);
+        self.errors.push(error);
+    }
+
     fn find_validator(&self, key: &str) -> Option<(usize, &FieldInfo)> {
-        self.validator.key_lookup.get(key).map(|index| (*index, &self.validator.field_info[*index]))
+        self.validator
+            .input_lookup
+            .get(key)
+            .map(|index| (index, &self.validator.field_info[index]))
+    }
+
+    /// With `populate_by_name`, the field for a key which is a field's name but not its input name
+    fn find_by_name(&self, key: &str) -> Option<(usize, &FieldInfo)> {
+        if !self.validator.populate_by_name {
+            return None;
+        }
+        self.validator
+            .key_lookup
+            .get(key)
+            .map(|index| (index, &self.validator.field_info[index]))
+    }
+
+    /// Fields found by name are set once all input is read, unless they were also found by alias
+    fn set_by_name(&mut self) -> ValResult<()> {
+        let by_name = std::mem::take(&mut self.by_name);
+        for (index, result) in by_name.into_iter().rev() {
+            if self.is_found(index) {
+                continue;
+            }
+            match result {
+                Ok(field_value) => {
+                    self.set_value(index, field_value);
+                    self.fields_by_name.push(index);
+                }
+                Err(e) => {
+                    let loc = self.validator.field_info[index].name.as_str().into();
+                    self.set_error(index, e, loc)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// a repeated JSON key is still one field found, the last value wins
+    fn is_found(&self, index: usize) -> bool {
+        self.data[index].is_some() || self.fields_failed.contains(&index)
     }
 
     fn set_value(&mut self, index: usize, value: FieldValue) {
+        if !self.is_found(index) {
+            self.fields_found += 1;
+        }
         self.data[index] = Some(value);
-        self.fields_found += 1;
+    }
+
+    fn set_error(&mut self, index: usize, error: ValError, loc: LocItem) -> PyResult<()> {
+        self.errors.extend(error.line_errors_with_loc(loc)?);
+        if !self.is_found(index) {
+            self.fields_found += 1;
+        }
+        self.fields_failed.push(index);
+        Ok(())
     }
 
     fn finish(mut self, py: Python) -> ValResult<FieldValue> {
-        if self.fields_found != self.field_count {
-            for (info, value) in self.validator.field_info.iter().zip(self.data.iter()) {
-                if value.is_none() && info.required {
+        if self.partial {
+            return self.finish_dict(py, false);
+        }
+        self.check_missing();
+        if self.errors.is_empty() {
+            self.finish_valid(py)
+        } else {
+            Err(self.errors.into())
+        }
+    }
+
+    /// Like `finish` but the errors are returned with the model built from the valid fields
+    fn finish_collect(mut self, py: Python) -> ValResult<(FieldValue, Vec<LineError>)> {
+        self.check_missing();
+        let errors = std::mem::take(&mut self.errors);
+        Ok((self.finish_valid(py)?, errors))
+    }
+
+    fn check_missing(&mut self) {
+        if self.fields_found != self.field_count && !self.truncated {
+            let fields = self.validator.field_info.iter().zip(self.data.iter());
+            for (index, (info, value)) in fields.enumerate() {
+                if value.is_none() && info.required && !self.fields_failed.contains(&index) {
+                    // in a nested model the parent's `set_error` adds its own field name as this error bubbles
+                    // up, giving the full location like `["address", "zipcode"]`
                     self.errors.push(LineError::new_loc(
                         ErrorType::MissingField,
-                        info.name.as_str(),
+                        info.input_loc(),
                     ));
                 }
             }
         }
+    }
 
-        let instance = create_class(self.validator.cls.bind(py))?;
+    /// The model instance, or dict for "typed-dict" schemas, once there are no errors
+    fn finish_valid(self, py: Python) -> ValResult<FieldValue> {
+        let Some(cls) = &self.validator.cls else {
+            return self.finish_dict(py, true);
+        };
+        let model_data = self.into_model_data(cls.clone_ref(py));
+        let (instance, model_data) = new_instance(py, model_data)?;
+        Ok(FieldValue::Model(instance, model_data))
+    }
 
-        if self.errors.is_empty() {
-            let model_data = ModelData::new(&self.validator.field_info, self.data, &self.validator.key_lookup);
-            force_setattr(
-                py,
-                &instance,
-                intern!(py, "__pydantic_model_data__"),
-                Py::new(py, model_data)?,
-            )?;
-            Ok(FieldValue::Model(instance.into_py(py)))
-        } else {
-            Err(self.errors.into())
+    fn into_model_data(self, cls: Py<PyType>) -> ModelData {
+        ModelData::new(
+            &self.validator.field_info,
+            self.data,
+            self.extra,
+            &self.validator.key_lookup,
+            &self.validator.name,
+            self.validator.config.clone(),
+            cls,
+        )
+        .with_fields_by_name(self.fields_by_name)
+    }
+
+    /// Missing fields are set to their defaults for "typed-dict" schemas, left out for `validate_partial`
+    fn finish_dict(self, py: Python, with_defaults: bool) -> ValResult<FieldValue> {
+        if !self.errors.is_empty() {
+            return Err(self.errors.into());
+        }
+        let dict = PyDict::new_bound(py);
+        let fields = self.validator.field_info.iter().zip(self.data);
+        for (info, value) in fields {
+            let value = match value {
+                Some(value) => value,
+                // required fields can only be missing once partial JSON input has ended
+                None if with_defaults && !info.required => info.get_default(py)?,
+                None => continue,
+            };
+            dict.set_item(info.name_py.clone_ref(py), value.into_py_object(py)?)?;
+        }
+        for (key, value) in self.extra {
+            dict.set_item(key, value.into_py_object(py)?)?;
         }
+        Ok(FieldValue::Py(dict.into()))
     }
 }
 
+/// Like `collections.abc.Mapping`, without requiring the class to be registered
+fn is_mapping(data: &Bound<'_, PyAny>) -> bool {
+    let py = data.py();
+    data.hasattr(intern!(py, "keys")).unwrap_or(false)
+        && data.hasattr(intern!(py, "__getitem__")).unwrap_or(false)
+}
+
+/// `getattr`, `None` for a missing attribute rather than an `AttributeError`
+fn get_attribute<'py>(data: &Bound<'py, PyAny>, name: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+    match data.getattr(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.is_instance_of::<PyAttributeError>(data.py()) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create an instance of the model class holding `model_data`, without calling `__init__`
+pub fn new_instance(py: Python, model_data: ModelData) -> PyResult<(PyObject, Py<ModelData>)> {
+    let model_data = Py::new(py, model_data)?;
+    let instance = bind_instance(model_data.bind(py))?;
+    Ok((instance.unbind(), model_data))
+}
+
+/// Create another instance of the model class sharing existing `model_data`
+pub fn bind_instance<'py>(model_data: &Bound<'py, ModelData>) -> PyResult<Bound<'py, PyAny>> {
+    let py = model_data.py();
+    let instance = create_class(model_data.try_borrow()?.cls().bind(py))?;
+    force_setattr(
+        py,
+        &instance,
+        intern!(py, "__pydantic_model_data__"),
+        model_data,
+    )?;
+    Ok(instance)
+}
+
 /// The rest here is taken directly from pydantic-core
 fn create_class<'py>(class: &Bound<'py, PyType>) -> PyResult<Bound<'py, PyAny>> {
     let py = class.py();
@@ -196,3 +769,496 @@ fn py_error_on_minusone(py: Python<'_>, result: std::os::raw::c_int) -> PyResult
         Err(PyErr::fetch(py))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::run_python;
+
+    #[test]
+    fn nested_models() {
+        run_python(
+            r#"
+            import json
+            address = {'type': 'model', 'cls': Model, 'fields': [
+                field('street', {'type': 'string'}), field('zip', {'type': 'int'}, default=0),
+            ]}
+            v = model_validator(
+                field('name', {'type': 'string'}),
+                field('address', address),
+                field('others', {'type': 'list', 'items_schema': address}, default=[]),
+            )
+            m = v.validate_python({'name': 'x', 'address': {'street': 'main', 'zip': 1}, 'others': [{'street': 'a'}]})
+            assert m.address.street == 'main'
+            expected = {'name': 'x', 'address': {'street': 'main', 'zip': 1}, 'others': [{'street': 'a', 'zip': 0}]}
+            assert m.__pydantic_model_data__.model_dump() == expected
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == expected
+            m = v.validate_json(b'{"name": "x", "address": {"street": "main"}, "others": [{"street": "a", "zip": 5}]}')
+            assert m.__pydantic_model_data__.model_dump() == {
+                'name': 'x', 'address': {'street': 'main', 'zip': 0}, 'others': [{'street': 'a', 'zip': 5}],
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn extra_behavior() {
+        run_python(
+            r#"
+            import json
+            python = {'a': 1, 'foo': [1, 'x']}
+            json_ = b'{"a": 1, "foo": [1, "x"]}'
+            for kwargs in [{}, {'extra_behavior': 'ignore'}]:
+                v = model_validator(field('a', {'type': 'int'}), **kwargs)
+                for m in [v.validate_python(python), v.validate_json(json_)]:
+                    assert m.__pydantic_model_data__.model_dump() == {'a': 1}, kwargs
+                    assert not hasattr(m, 'foo')
+
+            v = model_validator(field('a', {'type': 'int'}), extra_behavior='forbid')
+            assert errors(v.validate_python, python) == [('extra_forbidden', ('foo',))]
+            assert errors(v.validate_json, json_) == [('extra_forbidden', ('foo',))]
+
+            v = model_validator(field('a', {'type': 'int'}), extra_behavior='allow')
+            for m in [v.validate_python(python), v.validate_json(b'{"a": 1, "foo": [1, "x", {"y": null}]}')]:
+                assert m.foo[:2] == [1, 'x']
+                assert json.loads(m.__pydantic_model_data__.model_dump_json()) == m.__pydantic_model_data__.model_dump()
+
+            try:
+                model_validator(extra_behavior='x')
+            except ValueError as e:
+                assert 'Invalid extra_behavior: x' in str(e), e
+            else:
+                raise AssertionError('expected a ValueError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('req', {'type': 'int'}),
+                field('d', {'type': 'int'}, default=None),
+                field('f', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=list),
+            )
+            # a field is required unless it has a default
+            assert errors(v.validate_python, {}) == [('missing', ('req',))]
+            assert errors(v.validate_json, b'{}') == [('missing', ('req',))]
+            m1 = v.validate_python({'req': 1})
+            m2 = v.validate_json(b'{"req": 2}')
+            assert m1.__pydantic_model_data__.model_dump() == {'req': 1, 'd': None, 'f': []}
+            # each model gets its own value from the factory
+            assert m1.f is not m2.f
+            m1.f.append(5)
+            assert m2.f == [] and v.validate_python({'req': 1}).f == []
+
+            calls = []
+
+            def factory():
+                calls.append(1)
+                return [len(calls)]
+
+            m = model_validator(field('l', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=factory)).validate_python({})
+            assert (m.l, m.l, len(calls)) == ([1], [1], 1)
+
+            try:
+                model_validator(field('x', {'type': 'int'}, default=1, default_factory=list))
+            except ValueError as e:
+                assert 'field "x": cannot set both default and default_factory' in str(e), e
+            else:
+                raise AssertionError('expected a ValueError')
+
+            def boom():
+                raise RuntimeError('boom')
+
+            try:
+                model_validator(field('x', {'type': 'int'}, default_factory=boom)).validate_python({}).x
+            except RuntimeError as e:
+                assert str(e) == 'boom'
+            else:
+                raise AssertionError('expected a RuntimeError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn json_all_errors() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('s', {'type': 'string'}),
+                field('b', {'type': 'bool'}),
+                field('l', {'type': 'list', 'items_schema': {'type': 'string'}}, default=None),
+                field('n', {'type': 'model', 'cls': Model, 'fields': [field('x', {'type': 'int'})]}, default=None),
+            )
+            # values of the wrong type are skipped and validation carries on
+            assert errors(v.validate_json, b'{"a": 1, "s": 2, "b": true, "l": ["x", 3, {"k": [1]}], "n": {"x": 1}}') == [
+                ('string_type', ('l', 1)), ('string_type', ('l', 2)), ('string_type', ('s',)),
+            ]
+            assert errors(v.validate_json, b'{"a": [1, 2], "s": {"q": "w"}, "b": true, "n": [1]}') == [
+                ('int_type', ('a',)), ('dict_type', ('n',)), ('string_type', ('s',)),
+            ]
+            assert errors(v.validate_json, b'{"a": 1, "s": "x", "b": true, "n": {"x": "q"}}') == [('int_type', ('n', 'x'))]
+            # invalid JSON is a single error however far through it is
+            for json in [b'{"a": 1, "s": "x", "b": tru}', b'{"a": 1, "s": "x", "b": true']:
+                assert errors(v.validate_json, json) == [('json_invalid', ())], json
+            assert errors(v.validate_json, b'[{"a": 1}]') == [('dict_type', ())]
+            "#,
+        );
+    }
+
+    #[test]
+    fn required_fields() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'int'}, default=None),
+                field('c', {'type': 'int'}, default_factory=lambda: 3),
+            )
+            # only a field with neither a default nor a default_factory is required, `None` is a default
+            assert errors(v.validate_python, {}) == [('missing', ('a',))]
+            assert errors(v.validate_json, b'{"b": 2}') == [('missing', ('a',))]
+            m = v.validate_json(b'{"a": 1}')
+            assert (m.a, m.b, m.c) == (1, None, 3)
+            "#,
+        );
+    }
+
+    #[test]
+    fn nested_missing_locations() {
+        run_python(
+            r#"
+            address = {'type': 'model', 'cls': Model, 'fields': [field('street', {'type': 'string'}), field('zipcode', {'type': 'string'})]}
+            person = {'type': 'model', 'cls': Model, 'fields': [field('name', {'type': 'string'}), field('address', address)]}
+            v = model_validator(field('person', person), field('people', {'type': 'list', 'items_schema': person}, default=[]))
+            expected = [
+                ('missing', ('people', 0, 'address', 'street')),
+                ('missing', ('people', 0, 'address', 'zipcode')),
+                ('missing', ('person', 'address', 'zipcode')),
+            ]
+            data = {'person': {'name': 'x', 'address': {'street': 's'}}, 'people': [{'name': 'y', 'address': {}}]}
+            assert errors(v.validate_python, data) == expected
+            assert errors(v.validate_json, b'{"person": {"name": "x", "address": {"street": "s"}}, "people": [{"name": "y", "address": {}}]}') == expected
+            try:
+                v.validate_python({'person': {'name': 'x', 'address': {'street': 's'}}})
+            except ValidationError as e:
+                assert str(e) == '1 validation error for Model\nperson.address.zipcode\n  Field required [type=missing]', str(e)
+            "#,
+        );
+    }
+
+    #[test]
+    fn json_key_order() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}), field('b', {'type': 'string'}), field('c', {'type': 'int'}, default=0),
+                extra_behavior='allow',
+            )
+            for json in [b'{"a": 1, "b": "x", "c": 2}', b'{"c": 2, "b": "x", "a": 1}']:
+                assert v.validate_json(json).__pydantic_model_data__.model_dump() == {'a': 1, 'b': 'x', 'c': 2}, json
+            assert v.validate_json(b'{"b": "x", "a": 1}').__pydantic_model_data__.model_dump() == {'a': 1, 'b': 'x', 'c': 0}
+            assert v.validate_json(b'{"a": 1, "z": 5, "b": "x"}').__pydantic_model_data__.model_dump() == {'a': 1, 'b': 'x', 'c': 0, 'z': 5}
+            assert errors(v.validate_json, b'{"a": 1, "c": 3}') == [('missing', ('b',))]
+            assert errors(v.validate_json, b'{"b": 1, "a": "q"}') == [('int_type', ('a',)), ('string_type', ('b',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn validate_partial() {
+        run_python(
+            r#"
+            fields = [field('name', {'type': 'string'}), field('age', {'type': 'int'}), field('x', {'type': 'int'}, default=1)]
+            v = model_validator(*fields)
+            # only the fields given are validated, there are no defaults or missing errors
+            assert v.validate_partial({'name': 'x'}) == {'name': 'x'}
+            assert v.validate_partial({'name': 'x', 'age': '3', 'other': 1}) == {'name': 'x', 'age': 3}
+            assert errors(v.validate_partial, {'age': 'bad'}) == [('int_type', ('age',))]
+            assert errors(v.validate_partial, [1]) == [('dict_type', ())]
+            assert errors(v.validate_python, {'name': 'x'}) == [('missing', ('age',))]
+            assert errors(model_validator(*fields, extra_behavior='forbid').validate_partial, {'q': 1}) == [('extra_forbidden', ('q',))]
+            assert model_validator(*fields, extra_behavior='allow').validate_partial({'q': 1}) == {'q': 1}
+            try:
+                SchemaValidator({'type': 'int'}).validate_partial(1)
+            except TypeError as e:
+                assert 'validate_partial is only supported for model schemas' in str(e), e
+            else:
+                raise AssertionError('expected a TypeError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn alias_generator() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(
+                field('user_id', {'type': 'int'}),
+                field('first_name', {'type': 'string'}, serialization_alias='fname'),
+                field('x', {'type': 'int'}, default=0, validation_alias='X'),
+                alias_generator='camel',
+            )
+            # explicit aliases win over generated ones
+            m = v.validate_python({'userId': 1, 'firstName': 'a', 'X': 2})
+            assert (m.user_id, m.first_name, m.x) == (1, 'a', 2)
+            data = m.__pydantic_model_data__
+            assert data.model_dump() == {'user_id': 1, 'first_name': 'a', 'x': 2}
+            assert data.model_dump(by_alias=True) == {'userId': 1, 'fname': 'a', 'x': 2}
+            assert json.loads(data.model_dump_json(by_alias=True)) == {'userId': 1, 'fname': 'a', 'x': 2}
+            assert v.validate_json(b'{"userId": 2, "firstName": "b"}').user_id == 2
+            assert errors(v.validate_python, {'user_id': 1, 'firstName': 'a'}) == [('missing', ('userId',))]
+            assert errors(v.validate_json, b'{"userId": "z", "firstName": "a"}') == [('int_type', ('userId',))]
+
+            v = model_validator(field('a_b_c', {'type': 'int'}), field('_p', {'type': 'int'}), alias_generator='camel')
+            assert v.validate_python({'aBC': 1, 'p': 2}).__pydantic_model_data__.model_dump(by_alias=True) == {'aBC': 1, 'p': 2}
+            try:
+                model_validator(alias_generator='kebab')
+            except ValueError as e:
+                assert 'Invalid alias_generator: kebab' in str(e), e
+            else:
+                raise AssertionError('expected a ValueError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        run_python(
+            r#"
+            fields = [field('a', {'type': 'int'}), field('b', {'type': 'int'})]
+            # by default the last value wins, as in python's `json`
+            assert model_validator(*fields).validate_json(b'{"a":1,"a":2,"b":3}').a == 2
+            v = model_validator(*fields, forbid_duplicate_keys=True, extra_behavior='allow')
+            assert errors(v.validate_json, b'{"a":1,"a":2}') == [('duplicate_key', ('a',)), ('missing', ('b',))]
+            assert errors(v.validate_json, b'{"a":1,"b":2,"x":1,"x":[2]}') == [('duplicate_key', ('x',))]
+            assert v.validate_json(b'{"a":1,"b":2}').b == 2
+            # a truncated key isn't a duplicate yet
+            assert v.validate_json(b'{"a":1,"b":2,"a":', allow_partial=True).a == 1
+            "#,
+        );
+    }
+
+    #[test]
+    fn validate_default() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'int'}, default='x', validate_default=True),
+                field('c', {'type': 'int'}, default='5', validate_default=True),
+                field('d', {'type': 'int'}, default='x'),
+                field('e', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=lambda: ['1', 2], validate_default=True),
+            )
+            m = v.validate_python({'a': 1})
+            assert (m.c, m.d, m.e) == (5, 'x', [1, 2])
+            # an invalid default only fails when it's used
+            for f in [lambda: m.b, m.__pydantic_model_data__.model_dump]:
+                assert errors(f) == [('int_type', ('b',))]
+            m = v.validate_python({'a': 1, 'b': 2})
+            assert m.__pydantic_model_data__.model_dump() == {'a': 1, 'b': 2, 'c': 5, 'd': 'x', 'e': [1, 2]}
+            assert errors(m.__pydantic_model_data__.model_dump, exclude_defaults=True) == [('int_type', ('b',))]
+
+            # the validated default is what `exclude_defaults` compares against
+            v = model_validator(field('c', {'type': 'int'}, default='5', validate_default=True))
+            assert v.validate_python({'c': 5}).__pydantic_model_data__.model_dump(exclude_defaults=True) == {}
+            assert v.validate_python({'c': 6}).__pydantic_model_data__.model_dump_json(exclude_defaults=True) == '{"c":6}'
+            "#,
+        );
+    }
+
+    #[test]
+    fn from_attributes() {
+        run_python(
+            r#"
+            class User:
+                def __init__(self, **kwargs):
+                    self.__dict__.update(kwargs)
+
+            class Boom:
+                name = 'x'
+
+                @property
+                def age(self):
+                    raise RuntimeError('boom')
+
+            fields = [
+                field('name', {'type': 'string'}),
+                field('age', {'type': 'int'}, default=0),
+                field('user_id', {'type': 'int'}, validation_alias='id'),
+            ]
+            v = model_validator(*fields, from_attributes=True)
+            m = v.validate_python(User(name='a', age='3', id=7, other=1))
+            assert m.__pydantic_model_data__.model_dump() == {'name': 'a', 'age': 3, 'user_id': 7}
+            assert v.validate_python(User(name='b', id=1)).age == 0
+            assert v.validate_python({'name': 'c', 'id': 2}).name == 'c'
+            assert errors(v.validate_python, User(age='x')) == [('int_type', ('age',)), ('missing', ('id',)), ('missing', ('name',))]
+            assert errors(v.validate_python, 42) == [('missing', ('id',)), ('missing', ('name',))]
+            # only `AttributeError` means a missing attribute
+            try:
+                v.validate_python(Boom())
+            except RuntimeError as e:
+                assert str(e) == 'boom'
+            else:
+                raise AssertionError('expected a RuntimeError')
+            assert errors(model_validator(*fields).validate_python, User(name='a', id=1)) == [('dict_type', ())]
+            "#,
+        );
+    }
+
+    #[test]
+    fn typed_dict() {
+        run_python(
+            r#"
+            import json
+
+            def typed_dict(*fields, **kwargs):
+                return {'type': 'typed-dict', 'fields': list(fields), **kwargs}
+
+            v = SchemaValidator(typed_dict(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'string'}, default='x'),
+                field('c', {'type': 'list', 'items_schema': {'type': 'int'}}, default_factory=list),
+            ))
+            r = v.validate_python({'a': '1', 'ignored': 2})
+            assert type(r) is dict and r == {'a': 1, 'b': 'x', 'c': []}
+            assert v.validate_json(b'{"a": 2, "b": "y", "c": ["3"]}') == {'a': 2, 'b': 'y', 'c': [3]}
+            assert errors(v.validate_python, {'b': 1}) == [('missing', ('a',))]
+            assert errors(v.validate_json, b'[]') == [('dict_type', ())]
+            assert v.validate_partial({'b': 'q'}) == {'b': 'q'}
+            assert v.validate_json(b'{"a": 1, "b": "long', allow_partial=True) == {'a': 1, 'b': 'x', 'c': []}
+            forbid = SchemaValidator(typed_dict(field('a', {'type': 'int'}), extra_behavior='forbid'))
+            assert errors(forbid.validate_python, {'a': 1, 'z': 2}) == [('extra_forbidden', ('z',))]
+            allow = SchemaValidator(typed_dict(field('a', {'type': 'int'}), extra_behavior='allow'))
+            assert allow.validate_json(b'{"a": 1, "z": [2]}') == {'a': 1, 'z': [2]}
+
+            v = model_validator(
+                field('inner', typed_dict(field('x', {'type': 'int'}))),
+                field('items', {'type': 'list', 'items_schema': typed_dict(field('y', {'type': 'bool'}, default='yes', validate_default=True))}),
+            )
+            m = v.validate_python({'inner': {'x': '5'}, 'items': [{}, {'y': False}]})
+            expected = {'inner': {'x': 5}, 'items': [{'y': True}, {'y': False}]}
+            assert m.__pydantic_model_data__.model_dump() == expected
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == expected
+            assert errors(v.validate_json, b'{"inner": {}, "items": [{"y": 3}]}') == [('missing', ('inner', 'x')), ('bool_type', ('items', 0, 'y'))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn coerce_empty_to_none() {
+        run_python(
+            r#"
+            fields = [field('name', {'type': 'string'}), field('age', {'type': 'int'}, default=None)]
+            v = model_validator(*fields, coerce_empty_to_none=True)
+            m = v.validate_python({'name': 'x', 'age': ''})
+            assert m.age is None
+            # an empty string counts as not given
+            assert m.__pydantic_model_data__.fields_set() == {'name'}
+            assert v.validate_json(b'{"name": "x", "age": ""}').age is None
+            assert v.validate_strings({'name': 'x', 'age': ''}).age is None
+            assert v.validate_json(b'{"name": " ", "age": "5"}').name == ' '
+            assert errors(v.validate_python, {'name': '', 'age': 3}) == [('missing', ('name',))]
+            assert errors(v.validate_json, b'{"name": "", "age": "x"}') == [('int_type', ('age',)), ('missing', ('name',))]
+            assert v.validate_json(b'{"name": "y", "age": "', allow_partial=True).age is None
+            assert errors(model_validator(*fields).validate_python, {'name': 'x', 'age': ''}) == [('int_type', ('age',))]
+
+            class Obj:
+                name = ''
+
+            attrs = model_validator(*fields, coerce_empty_to_none=True, from_attributes=True)
+            assert errors(attrs.validate_python, Obj()) == [('missing', ('name',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn mapping_input() {
+        run_python(
+            r#"
+            from collections import OrderedDict
+            from types import MappingProxyType
+
+            class Custom:
+                def __init__(self, d):
+                    self.d = d
+
+                def keys(self):
+                    return self.d.keys()
+
+                def __getitem__(self, key):
+                    return self.d[key]
+
+            v = model_validator(field('a', {'type': 'int'}), field('b', {'type': 'string'}, default='d'))
+            m = v.validate_python(OrderedDict([('a', 1), ('b', 'x')]))
+            assert (m.a, m.b) == (1, 'x')
+            m = v.validate_python(Custom({'a': '2'}))
+            assert (m.a, m.b) == (2, 'd')
+            assert v.validate_python(MappingProxyType({'a': 3})).a == 3
+            assert errors(v.validate_python, Custom({'a': 'x'})) == [('int_type', ('a',))]
+            # strict mode accepts dict subclasses, but not other mappings
+            assert v.validate_python(OrderedDict(a=1), strict=True).a == 1
+            assert errors(v.validate_python, Custom({'a': 1}), strict=True) == [('dict_type', ())]
+            for value in ['ab', [('a', 1)]]:
+                assert errors(v.validate_python, value) == [('dict_type', ())], value
+            assert model_validator(field('a', {'type': 'int'}), from_attributes=True).validate_python(Custom({'a': 4})).a == 4
+            "#,
+        );
+    }
+
+    #[test]
+    fn field_strict() {
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'int'}),
+                field('b', {'type': 'int'}, strict=True),
+                field('c', {'type': 'list', 'items_schema': {'type': 'int'}}, strict=True),
+            )
+            # a strict field is strict all the way down
+            assert errors(v.validate_python, {'a': '5', 'b': '5', 'c': ['1']}) == [('int_type', ('b',)), ('int_type', ('c', 0))]
+            m = v.validate_python({'a': '5', 'b': 5, 'c': [1]})
+            assert (m.a, m.b, m.c) == (5, 5, [1])
+            assert errors(v.validate_json, b'{"a": "5", "b": "5", "c": [1]}') == [('int_type', ('b',))]
+            # the per-call flag still wins
+            assert v.validate_python({'a': '5', 'b': '5', 'c': [1]}, strict=False).b == 5
+
+            v = model_validator(field('a', {'type': 'int'}), field('b', {'type': 'int'}, strict=False), strict=True)
+            assert errors(v.validate_python, {'a': '5', 'b': '5'}) == [('int_type', ('a',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn populate_by_name() {
+        run_python(
+            r#"
+            import json
+            fields = [field('user_id', {'type': 'int'}, validation_alias='userId'), field('name', {'type': 'string'}, default='n')]
+            plain = model_validator(*fields, extra_behavior='forbid')
+            both = model_validator(*fields, extra_behavior='forbid', populate_by_name=True)
+            for validate in [lambda v, data: v.validate_python(data), lambda v, data: v.validate_json(json.dumps(data))]:
+                assert validate(plain, {'userId': 1}).user_id == 1
+                assert validate(both, {'userId': 1}).user_id == 1
+                assert validate(both, {'user_id': 2}).user_id == 2
+                # the alias wins when both are given
+                assert validate(both, {'user_id': 2, 'userId': 1}).user_id == 1
+                assert validate(both, {'user_id': 'x', 'userId': 3}).user_id == 3
+                assert errors(validate, both, {'user_id': 'x'}) == [('int_type', ('user_id',))]
+                assert errors(validate, both, {'userId': 'x', 'user_id': 3}) == [('int_type', ('userId',))]
+                assert errors(validate, plain, {'user_id': 2}) == [('missing', ('userId',)), ('extra_forbidden', ('user_id',))]
+
+            from types import SimpleNamespace
+            attrs = model_validator(*fields, populate_by_name=True, from_attributes=True)
+            assert attrs.validate_python(SimpleNamespace(user_id=5)).user_id == 5
+            assert attrs.validate_python(SimpleNamespace(user_id=5, userId=6)).user_id == 6
+            assert both.validate_json('{"user_id": 1, "user_id": 2}').user_id == 2
+            dups = model_validator(*fields, populate_by_name=True, forbid_duplicate_keys=True)
+            assert errors(dups.validate_json, '{"user_id": 1, "user_id": 2}') == [('duplicate_key', ('user_id',))]
+            "#,
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::errors::ValidationError;
+use crate::SchemaValidator;
+
+/// Run before each test's code, `Model` stands in for a pydantic model class
+const PRELUDE: &str = r#"
+class Model:
+    __slots__ = ('__pydantic_model_data__',)
+
+    def __getattr__(self, name):
+        return self.__pydantic_model_data__.get_attr(name)
+
+    def __setattr__(self, name, value):
+        self.__pydantic_model_data__.set_attr(name, value)
+
+    def __eq__(self, other):
+        if type(other) is not type(self):
+            return NotImplemented
+        return self.__pydantic_model_data__ == other.__pydantic_model_data__
+
+    def __hash__(self):
+        return hash(self.__pydantic_model_data__)
+
+    def __repr__(self):
+        return repr(self.__pydantic_model_data__)
+
+def field(name, schema, **kwargs):
+    return {'name': name, 'schema': schema, **kwargs}
+
+def model_validator(*fields, **kwargs):
+    return SchemaValidator({'type': 'model', 'cls': Model, 'fields': list(fields), **kwargs})
+
+def errors(f, *args, **kwargs):
+    """`(error_type, location)` of each error from `f`, which must raise a `ValidationError`"""
+    try:
+        result = f(*args, **kwargs)
+    except ValidationError as e:
+        return [(error['error_type'], tuple(error['location'])) for error in e.errors()]
+    raise AssertionError(f'expected a ValidationError, got {result!r}')
+"#;
+
+/// Runs `code`, dedented, after the prelude, panicking with the traceback if it raises
+pub fn run_python(code: &str) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let run = || -> PyResult<()> {
+            let globals = PyDict::new_bound(py);
+            globals.set_item("SchemaValidator", py.get_type_bound::<SchemaValidator>())?;
+            globals.set_item("ValidationError", py.get_type_bound::<ValidationError>())?;
+            py.run_bound(PRELUDE, Some(&globals), None)?;
+            let textwrap = py.import_bound("textwrap")?;
+            let code: String = textwrap.call_method1("dedent", (code,))?.extract()?;
+            py.run_bound(&code, Some(&globals), None)
+        };
+        if let Err(e) = run() {
+            let traceback = e.traceback_bound(py).and_then(|tb| tb.format().ok());
+            panic!("{}{e}", traceback.unwrap_or_default());
+        }
+    });
+}
@@ -0,0 +1,98 @@
+use std::sync::Mutex;
+
+use ahash::AHashMap;
+use pyo3::prelude::*;
+
+use crate::model_data::ModelData;
+use crate::model_validator::new_instance;
+
+/// Models validated from JSON keyed by the input bytes, evicting the least recently used once full
+#[derive(Debug)]
+pub struct ValidationCache {
+    capacity: usize,
+    // never held while python code might run, e.g. copying or dropping python values, as another thread could
+    // take the GIL and wait for the lock
+    entries: Mutex<CacheEntries>,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    map: AHashMap<Box<[u8]>, CacheEntry>,
+    // incremented on each use, so the entry with the lowest `last_used` is the least recently used
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    // never bound to an instance, so it can't be changed
+    model_data: Py<ModelData>,
+    last_used: u64,
+}
+
+impl ValidationCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(CacheEntries::default()),
+        }
+    }
+
+    /// A new instance copied from the model validated from `input`, if it's cached
+    pub fn get(&self, py: Python, input: &[u8]) -> PyResult<Option<PyObject>> {
+        let model_data = {
+            let mut entries = self.entries.lock().unwrap();
+            let tick = entries.next_tick();
+            match entries.map.get_mut(input) {
+                Some(entry) => {
+                    entry.last_used = tick;
+                    entry.model_data.clone_ref(py)
+                }
+                None => return Ok(None),
+            }
+        };
+        let copy = model_data.bind(py).try_borrow()?.copy(py, true)?;
+        Ok(Some(new_instance(py, copy)?.0))
+    }
+
+    /// Store a copy of `model_data` validated from `input`, evicting the least recently used entry if full
+    pub fn insert(
+        &self,
+        py: Python,
+        input: &[u8],
+        model_data: &Bound<'_, ModelData>,
+    ) -> PyResult<()> {
+        let copy = Py::new(py, model_data.try_borrow()?.copy(py, true)?)?;
+        // dropped after the lock is released
+        let evicted = {
+            let mut entries = self.entries.lock().unwrap();
+            let last_used = entries.next_tick();
+            let is_full = entries.map.len() >= self.capacity;
+            let evicted = match is_full && !entries.map.contains_key(input) {
+                true => entries.remove_least_recent(),
+                false => None,
+            };
+            let entry = CacheEntry {
+                model_data: copy,
+                last_used,
+            };
+            let replaced = entries.map.insert(input.into(), entry);
+            (evicted, replaced)
+        };
+        drop(evicted);
+        Ok(())
+    }
+}
+
+impl CacheEntries {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// A scan of every entry, caches are expected to be small
+    fn remove_least_recent(&mut self) -> Option<CacheEntry> {
+        let (key, _) = self.map.iter().min_by_key(|(_, entry)| entry.last_used)?;
+        let key = key.clone();
+        self.map.remove(&key)
+    }
+}
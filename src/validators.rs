@@ -1,86 +1,3582 @@
-use jiter::{Jiter, NumberInt};
-use pyo3::exceptions::PyValueError;
+use jiter::{
+    Jiter, JiterError, JiterErrorType, JiterResult, JsonErrorType, NumberAny, NumberInt, Peek,
+};
+use pyo3::exceptions::{PyAttributeError, PyRuntimeError, PyValueError};
+use pyo3::intern;
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyString};
+use pyo3::types::{
+    PyBool, PyByteArray, PyBytes, PyDateTime, PyDict, PyFloat, PyFrozenSet, PyInt, PyList, PySet,
+    PyString, PyTuple, PyType,
+};
+use smallvec::SmallVec;
+use speedate::DateTime;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::num::{IntErrorKind, ParseIntError};
+use std::sync::{Arc, OnceLock, RwLock, Weak};
+use strum::EnumMessage;
+use uuid::Uuid;
 
-use crate::errors::{ErrorType, ValResult};
-use crate::field::{get_as_req, FieldValue};
+use crate::base64;
+use crate::errors::{ErrorType, LineError, LocItem, ValError, ValResult};
+use crate::field::{
+    datetime_from_py, get_as_opt, get_as_req, get_decimal_type, uuid_from_py, FieldValue, RawData,
+};
+use crate::key_lookup::KeyLookup;
 use crate::model_validator::ModelValidator;
 
+/// Maximum depth of nested validators, the same as jiter's default limit for nested JSON
+const RECURSION_LIMIT: u16 = 200;
+
+/// Keys of a "with-metadata" schema's "metadata" which are merged into its inner schema
+const METADATA_CONSTRAINTS: [&str; 9] = [
+    "min_length",
+    "max_length",
+    "gt",
+    "ge",
+    "lt",
+    "le",
+    "multiple_of",
+    "max_digits",
+    "decimal_places",
+];
+
+/// Maximum number of nested JSON arrays and objects validated by containers, unless the schema sets "max_depth"
+pub const DEFAULT_MAX_DEPTH: u16 = 100;
+
+fn is_eof(error: &JiterError) -> bool {
+    matches!(
+        error.error_type,
+        JiterErrorType::JsonError(
+            JsonErrorType::EofWhileParsingList
+                | JsonErrorType::EofWhileParsingObject
+                | JsonErrorType::EofWhileParsingString
+                | JsonErrorType::EofWhileParsingValue
+        )
+    )
+}
+
+/// State for one call to validate, passed down to every nested validator
+pub struct ValidationState<'a> {
+    pub py: Python<'a>,
+    // overrides each validator's `strict` from the schema when set
+    strict: Option<bool>,
+    // passed to function validators which set `with_context`
+    pub context: Option<&'a PyObject>,
+    depth: u16,
+    // arrays and objects entered by container validators, see `nest_json`
+    json_depth: u16,
+    max_depth: u16,
+    // JSON input which ends early isn't an error, see `truncate`
+    allow_partial: bool,
+    // set once partial JSON input has ended, containers stop and keep the values already validated
+    truncated: bool,
+}
+
+impl<'a> ValidationState<'a> {
+    pub fn new(py: Python<'a>, strict: Option<bool>, context: Option<&'a PyObject>) -> Self {
+        Self {
+            py,
+            strict,
+            context,
+            depth: 0,
+            json_depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            allow_partial: false,
+            truncated: false,
+        }
+    }
+
+    pub fn with_allow_partial(mut self, allow_partial: bool) -> Self {
+        self.allow_partial = allow_partial;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: u16) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// With `allow_partial`, the JSON input ending gives `None` and marks the state as truncated
+    pub fn truncate<T>(&mut self, result: JiterResult<T>) -> ValResult<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if self.allow_partial && is_eof(&error) => {
+                self.truncated = true;
+                Ok(None)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn strict_or(&self, schema_strict: bool) -> bool {
+        self.strict.unwrap_or(schema_strict)
+    }
+
+    /// Call `f` one level deeper, failing with `ErrorType::RecursionLimit` rather than overflowing the stack
+    fn recurse<T>(&mut self, f: impl FnOnce(&mut Self) -> ValResult<T>) -> ValResult<T> {
+        if self.depth >= RECURSION_LIMIT {
+            return Err(ErrorType::RecursionLimit.into());
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Past `max_depth` the value is skipped and `ErrorType::JsonTooDeep` returned
+    fn nest_json<T>(
+        &mut self,
+        jiter: &mut Jiter,
+        f: impl FnOnce(&mut Self, &mut Jiter) -> ValResult<T>,
+    ) -> ValResult<T> {
+        if self.json_depth >= self.max_depth {
+            self.truncate(jiter.next_skip())?;
+            let max_depth = self.max_depth;
+            return Err(ErrorType::JsonTooDeep { max_depth }.into());
+        }
+        self.json_depth += 1;
+        let result = f(self, jiter);
+        self.json_depth -= 1;
+        result
+    }
+}
+
 pub trait Validator: Debug {
-    fn validate_python<'py>(&self, py: Python, data: &Bound<'py, PyAny>) -> ValResult<FieldValue>;
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue>;
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue>;
+
+    /// Values are coerced from strings even in strict mode, e.g. for form or query data
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_python(state, data)
+    }
+}
 
-    fn validate_json(&self, py: Python, jiter: &mut Jiter) -> ValResult<FieldValue>;
+/// The `Validator` method used for nested values, so containers share their logic for python input
+#[derive(Debug, Clone, Copy)]
+pub enum PyInput {
+    Python,
+    Strings,
+}
+
+impl PyInput {
+    pub fn validate<'py>(
+        self,
+        validator: &CombinedValidator,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        match self {
+            PyInput::Python => validator.validate_python(state, data),
+            PyInput::Strings => validator.validate_strings(state, data),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum CombinedValidator {
     String(StringValidator),
     Int(IntValidator),
+    Float(FloatValidator),
+    Bool(BoolValidator),
+    Bytes(BytesValidator),
+    List(ListValidator),
+    Set(SetValidator),
+    Tuple(TupleValidator),
+    Dict(DictValidator),
+    Uuid(UuidValidator),
+    Datetime(DatetimeValidator),
+    Decimal(DecimalValidator),
+    Literal(LiteralValidator),
+    Enum(EnumValidator),
+    Json(JsonFieldValidator),
+    FunctionBefore(FunctionBeforeValidator),
+    FunctionAfter(FunctionAfterValidator),
+    Union(UnionValidator),
+    TaggedUnion(TaggedUnionValidator),
     Model(ModelValidator),
+    DefinitionRef(DefinitionRefValidator),
+    // a validator from `register_validator`, with its schema "type" as the title
+    Custom(String, Box<dyn Validator + Send + Sync>),
 }
 
 impl CombinedValidator {
-    pub fn new(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
+    /// `strict` disables lax coercion, e.g. `"5"` or `5.0` to an int, it applies to all nested validators
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
         let schema_type: String = get_as_req(schema, "type")?;
         match schema_type.as_ref() {
-            "string" => Ok(Self::String(StringValidator)),
-            "int" => Ok(Self::Int(IntValidator)),
-            "model" => Ok(Self::Model(ModelValidator::new(schema)?)),
-            _ => Err(PyValueError::new_err(format!(
-                "Unknown validator: {schema_type}",
-            ))),
+            "string" => Ok(Self::String(StringValidator::new(schema, strict)?)),
+            "int" => Ok(Self::Int(IntValidator::new(schema, strict)?)),
+            "float" => Ok(Self::Float(FloatValidator::new(schema, strict)?)),
+            "bool" => Ok(Self::Bool(BoolValidator { strict })),
+            "bytes" => Ok(Self::Bytes(BytesValidator { strict })),
+            "list" => Ok(Self::List(ListValidator::new(schema, strict, definitions)?)),
+            "set" => Ok(Self::Set(SetValidator::new(schema, strict, definitions)?)),
+            "tuple" => Ok(Self::Tuple(TupleValidator::new(
+                schema,
+                strict,
+                definitions,
+            )?)),
+            "dict" => Ok(Self::Dict(DictValidator::new(schema, strict, definitions)?)),
+            "uuid" => Ok(Self::Uuid(UuidValidator { strict })),
+            "datetime" => Ok(Self::Datetime(DatetimeValidator { strict })),
+            "decimal" => Ok(Self::Decimal(DecimalValidator::new(schema, strict)?)),
+            "literal" => Ok(Self::Literal(LiteralValidator::new(schema)?)),
+            "enum" => Ok(Self::Enum(EnumValidator::new(schema, strict)?)),
+            "json" => Ok(Self::Json(JsonFieldValidator::new(
+                schema,
+                strict,
+                definitions,
+            )?)),
+            "function-before" => Ok(Self::FunctionBefore(FunctionBeforeValidator::new(
+                schema,
+                strict,
+                definitions,
+            )?)),
+            "function-after" => Ok(Self::FunctionAfter(FunctionAfterValidator::new(
+                schema,
+                strict,
+                definitions,
+            )?)),
+            "union" => Ok(Self::Union(UnionValidator::new(
+                schema,
+                strict,
+                definitions,
+            )?)),
+            "tagged-union" => Ok(Self::TaggedUnion(TaggedUnionValidator::new(
+                schema,
+                strict,
+                definitions,
+            )?)),
+            "model" | "typed-dict" => Ok(Self::Model(ModelValidator::new(
+                schema,
+                strict,
+                definitions,
+            )?)),
+            "definition-ref" => Ok(Self::DefinitionRef(DefinitionRefValidator::new(
+                schema,
+                definitions,
+            )?)),
+            "with-metadata" => Self::new_with_metadata(schema, strict, definitions),
+            _ => match custom_validator_factory(&schema_type) {
+                Some(factory) => Ok(Self::Custom(schema_type, factory(schema, strict)?)),
+                None => Err(PyValueError::new_err(format!(
+                    "Unknown validator: {schema_type}",
+                ))),
+            },
         }
     }
+
+    fn new_with_metadata(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        Self::new(&merge_metadata(schema)?, strict, definitions)
+    }
 }
 
-impl Validator for CombinedValidator {
-    fn validate_python<'py>(&self, py: Python, data: &Bound<'py, PyAny>) -> ValResult<FieldValue> {
-        match self {
-            CombinedValidator::String(v) => v.validate_python(py, data),
-            CombinedValidator::Int(v) => v.validate_python(py, data),
-            CombinedValidator::Model(v) => v.validate_python(py, data),
+/// Builds a validator from its schema dict and whether it's strict, see `register_validator`
+pub type ValidatorFactory =
+    fn(&Bound<'_, PyDict>, bool) -> PyResult<Box<dyn Validator + Send + Sync>>;
+
+static CUSTOM_VALIDATORS: OnceLock<RwLock<HashMap<String, ValidatorFactory>>> = OnceLock::new();
+
+/// Let schemas with "type" `schema_type` use validators from `factory`, the registry is process-wide
+pub fn register_validator(schema_type: &str, factory: ValidatorFactory) {
+    let custom_validators = CUSTOM_VALIDATORS.get_or_init(Default::default);
+    custom_validators
+        .write()
+        .unwrap()
+        .insert(schema_type.to_owned(), factory);
+}
+
+fn custom_validator_factory(schema_type: &str) -> Option<ValidatorFactory> {
+    let custom_validators = CUSTOM_VALIDATORS.get()?;
+    custom_validators.read().unwrap().get(schema_type).copied()
+}
+
+/// The inner "schema" of a "with-metadata" schema with constraints from its "metadata" dict
+fn merge_metadata<'py>(schema: &Bound<'py, PyDict>) -> PyResult<Bound<'py, PyDict>> {
+    let inner_schema: Bound<PyDict> = get_as_req(schema, "schema")?;
+    let metadata: Bound<PyDict> = get_as_req(schema, "metadata")?;
+    let inner_type: String = get_as_req(&inner_schema, "type")?;
+    let merged = match inner_type.as_str() {
+        "with-metadata" => merge_metadata(&inner_schema)?,
+        _ => inner_schema.copy()?,
+    };
+    for key in METADATA_CONSTRAINTS {
+        if let Some(value) = metadata.get_item(key)? {
+            merged.set_item(key, value)?;
         }
     }
+    Ok(merged)
+}
 
-    fn validate_json(&self, py: Python, jiter: &mut Jiter) -> ValResult<FieldValue> {
+impl CombinedValidator {
+    /// The name used in `ValidationError` messages, models use their class name
+    pub fn title(&self) -> &str {
         match self {
-            CombinedValidator::String(v) => v.validate_json(py, jiter),
-            CombinedValidator::Int(v) => v.validate_json(py, jiter),
-            CombinedValidator::Model(v) => v.validate_json(py, jiter),
+            CombinedValidator::String(_) => "str",
+            CombinedValidator::Int(_) => "int",
+            CombinedValidator::Float(_) => "float",
+            CombinedValidator::Bool(_) => "bool",
+            CombinedValidator::Bytes(_) => "bytes",
+            CombinedValidator::List(_) => "list",
+            CombinedValidator::Set(_) => "set",
+            CombinedValidator::Tuple(_) => "tuple",
+            CombinedValidator::Dict(_) => "dict",
+            CombinedValidator::Uuid(_) => "uuid",
+            CombinedValidator::Datetime(_) => "datetime",
+            CombinedValidator::Decimal(_) => "decimal",
+            CombinedValidator::Literal(_) => "literal",
+            CombinedValidator::Enum(v) => &v.name,
+            CombinedValidator::Json(_) => "json",
+            CombinedValidator::FunctionBefore(v) => v.validator.title(),
+            CombinedValidator::FunctionAfter(v) => v.validator.title(),
+            CombinedValidator::Union(_) => "union",
+            CombinedValidator::TaggedUnion(_) => "tagged-union",
+            CombinedValidator::Model(v) => v.name(),
+            CombinedValidator::DefinitionRef(v) => &v.name,
+            CombinedValidator::Custom(schema_type, _) => schema_type,
+        }
+    }
+
+    /// `None` if partial input ended within the value, a failed value is skipped so the caller can carry on
+    pub fn validate_json_item(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<Option<ValResult<FieldValue>>> {
+        let start = jiter.clone();
+        match self.validate_json(state, jiter) {
+            Ok(field_value) => Ok(Some(Ok(field_value))),
+            // partial input ended within a union before any choice found an error
+            Err(ValError::LineErrors(errors)) if state.truncated() && errors.is_empty() => Ok(None),
+            // a nested container was truncated, its errors are for the values which were complete
+            Err(e) if state.truncated() => Ok(Some(Err(e.with_json_input(start)))),
+            Err(e) => {
+                let e = e.with_json_input(start.clone());
+                *jiter = start;
+                Ok(state.truncate(jiter.next_skip())?.map(|()| Err(e)))
+            }
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct StringValidator;
+impl Validator for CombinedValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        state.recurse(|state| match self {
+            CombinedValidator::String(v) => v.validate_python(state, data),
+            CombinedValidator::Int(v) => v.validate_python(state, data),
+            CombinedValidator::Float(v) => v.validate_python(state, data),
+            CombinedValidator::Bool(v) => v.validate_python(state, data),
+            CombinedValidator::Bytes(v) => v.validate_python(state, data),
+            CombinedValidator::List(v) => v.validate_python(state, data),
+            CombinedValidator::Set(v) => v.validate_python(state, data),
+            CombinedValidator::Tuple(v) => v.validate_python(state, data),
+            CombinedValidator::Dict(v) => v.validate_python(state, data),
+            CombinedValidator::Uuid(v) => v.validate_python(state, data),
+            CombinedValidator::Datetime(v) => v.validate_python(state, data),
+            CombinedValidator::Decimal(v) => v.validate_python(state, data),
+            CombinedValidator::Literal(v) => v.validate_python(state, data),
+            CombinedValidator::Enum(v) => v.validate_python(state, data),
+            CombinedValidator::Json(v) => v.validate_python(state, data),
+            CombinedValidator::FunctionBefore(v) => v.validate_python(state, data),
+            CombinedValidator::FunctionAfter(v) => v.validate_python(state, data),
+            CombinedValidator::Union(v) => v.validate_python(state, data),
+            CombinedValidator::TaggedUnion(v) => v.validate_python(state, data),
+            CombinedValidator::Model(v) => v.validate_python(state, data),
+            CombinedValidator::DefinitionRef(v) => v.validate_python(state, data),
+            CombinedValidator::Custom(_, v) => v.validate_python(state, data),
+        })
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        state.recurse(|state| match self {
+            CombinedValidator::String(v) => v.validate_json(state, jiter),
+            CombinedValidator::Int(v) => v.validate_json(state, jiter),
+            CombinedValidator::Float(v) => v.validate_json(state, jiter),
+            CombinedValidator::Bool(v) => v.validate_json(state, jiter),
+            CombinedValidator::Bytes(v) => v.validate_json(state, jiter),
+            CombinedValidator::List(v) => state.nest_json(jiter, |s, j| v.validate_json(s, j)),
+            CombinedValidator::Set(v) => state.nest_json(jiter, |s, j| v.validate_json(s, j)),
+            CombinedValidator::Tuple(v) => state.nest_json(jiter, |s, j| v.validate_json(s, j)),
+            CombinedValidator::Dict(v) => state.nest_json(jiter, |s, j| v.validate_json(s, j)),
+            CombinedValidator::Uuid(v) => v.validate_json(state, jiter),
+            CombinedValidator::Datetime(v) => v.validate_json(state, jiter),
+            CombinedValidator::Decimal(v) => v.validate_json(state, jiter),
+            CombinedValidator::Literal(v) => v.validate_json(state, jiter),
+            CombinedValidator::Enum(v) => v.validate_json(state, jiter),
+            CombinedValidator::Json(v) => v.validate_json(state, jiter),
+            CombinedValidator::FunctionBefore(v) => v.validate_json(state, jiter),
+            CombinedValidator::FunctionAfter(v) => v.validate_json(state, jiter),
+            CombinedValidator::Union(v) => v.validate_json(state, jiter),
+            CombinedValidator::TaggedUnion(v) => v.validate_json(state, jiter),
+            CombinedValidator::Model(v) => state.nest_json(jiter, |s, j| v.validate_json(s, j)),
+            CombinedValidator::DefinitionRef(v) => v.validate_json(state, jiter),
+            CombinedValidator::Custom(_, v) => v.validate_json(state, jiter),
+        })
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        state.recurse(|state| match self {
+            CombinedValidator::String(v) => v.validate_strings(state, data),
+            CombinedValidator::Int(v) => v.validate_strings(state, data),
+            CombinedValidator::Float(v) => v.validate_strings(state, data),
+            CombinedValidator::Bool(v) => v.validate_strings(state, data),
+            CombinedValidator::Bytes(v) => v.validate_strings(state, data),
+            CombinedValidator::List(v) => v.validate_strings(state, data),
+            CombinedValidator::Set(v) => v.validate_strings(state, data),
+            CombinedValidator::Tuple(v) => v.validate_strings(state, data),
+            CombinedValidator::Dict(v) => v.validate_strings(state, data),
+            CombinedValidator::Uuid(v) => v.validate_strings(state, data),
+            CombinedValidator::Datetime(v) => v.validate_strings(state, data),
+            CombinedValidator::Decimal(v) => v.validate_strings(state, data),
+            CombinedValidator::Literal(v) => v.validate_strings(state, data),
+            CombinedValidator::Enum(v) => v.validate_strings(state, data),
+            CombinedValidator::Json(v) => v.validate_strings(state, data),
+            CombinedValidator::FunctionBefore(v) => v.validate_strings(state, data),
+            CombinedValidator::FunctionAfter(v) => v.validate_strings(state, data),
+            CombinedValidator::Union(v) => v.validate_strings(state, data),
+            CombinedValidator::TaggedUnion(v) => v.validate_strings(state, data),
+            CombinedValidator::Model(v) => v.validate_strings(state, data),
+            CombinedValidator::DefinitionRef(v) => v.validate_strings(state, data),
+            CombinedValidator::Custom(_, v) => v.validate_strings(state, data),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StringValidator {
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    coerce_numbers_to_str: bool,
+    strict: bool,
+}
+
+impl StringValidator {
+    pub fn new(schema: &Bound<'_, PyDict>, strict: bool) -> PyResult<Self> {
+        Ok(Self {
+            min_length: get_as_opt(schema, "min_length")?,
+            max_length: get_as_opt(schema, "max_length")?,
+            coerce_numbers_to_str: get_as_opt(schema, "coerce_numbers_to_str")?.unwrap_or(false),
+            strict,
+        })
+    }
+
+    /// lengths are measured in unicode scalar values, not bytes
+    fn check_length(&self, s: &str) -> ValResult<()> {
+        if self.min_length.is_none() && self.max_length.is_none() {
+            return Ok(());
+        }
+        let length = s.chars().count();
+        if let Some(min) = self.min_length {
+            if length < min {
+                return Err(ErrorType::StringTooShort { min }.into());
+            }
+        }
+        if let Some(max) = self.max_length {
+            if length > max {
+                return Err(ErrorType::StringTooLong { max }.into());
+            }
+        }
+        Ok(())
+    }
+}
 
 impl Validator for StringValidator {
-    fn validate_python<'py>(&self, py: Python, data: &Bound<'py, PyAny>) -> ValResult<FieldValue> {
-        let py_str: &Bound<PyString> = data.downcast().map_err(|_| ErrorType::StringType)?;
-        Ok(FieldValue::Py(py_str.into_py(py)))
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let strict = state.strict_or(self.strict);
+        if let Ok(py_str) = data.downcast::<PyString>() {
+            self.check_length(py_str_to_str(py_str)?)?;
+            Ok(FieldValue::Py(py_str.into_py(py)))
+        } else if let (false, Ok(py_bytes)) = (strict, data.downcast::<PyBytes>()) {
+            let s =
+                std::str::from_utf8(py_bytes.as_bytes()).map_err(|e| ErrorType::StringUnicode {
+                    error: e.to_string(),
+                })?;
+            self.check_length(s)?;
+            Ok(FieldValue::new_raw(s))
+        } else if !strict && (data.is_instance_of::<PyInt>() || data.is_instance_of::<PyFloat>()) {
+            // bools are ints, so `True` becomes "True" as with `str()`
+            let py_str = data.str()?;
+            self.check_length(py_str.to_str()?)?;
+            Ok(FieldValue::Py(py_str.into_py(py)))
+        } else {
+            Err(ErrorType::StringType.into())
+        }
     }
 
-    fn validate_json(&self, _: Python, jiter: &mut Jiter) -> ValResult<FieldValue> {
-        let s = jiter.next_str()?;
+    /// with `coerce_numbers_to_str` in lax mode, numbers keep their exact text from the JSON
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        let s = if peek == Peek::String {
+            jiter.known_str()?
+        } else if peek.is_num() && self.coerce_numbers_to_str && !state.strict_or(self.strict) {
+            std::str::from_utf8(jiter.next_number_bytes()?).map_err(|e| {
+                ErrorType::StringUnicode {
+                    error: e.to_string(),
+                }
+            })?
+        } else {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::StringType.into());
+        };
+        self.check_length(s)?;
         Ok(FieldValue::new_raw(s))
     }
 }
 
+/// Lax mode also accepts integral floats and numeric strings
 #[derive(Debug, Clone)]
-pub struct IntValidator;
+pub struct IntValidator {
+    gt: Option<i64>,
+    ge: Option<i64>,
+    lt: Option<i64>,
+    le: Option<i64>,
+    multiple_of: Option<i64>,
+    strict: bool,
+}
 
-impl Validator for IntValidator {
-    fn validate_python<'py>(&self, _: Python, data: &Bound<'py, PyAny>) -> ValResult<FieldValue> {
-        let int: i64 = data.extract().map_err(|_| ErrorType::IntType)?;
+impl IntValidator {
+    pub fn new(schema: &Bound<'_, PyDict>, strict: bool) -> PyResult<Self> {
+        let multiple_of = get_as_opt(schema, "multiple_of")?;
+        if multiple_of == Some(0) {
+            return Err(PyValueError::new_err("multiple_of must not be zero"));
+        }
+        Ok(Self {
+            gt: get_as_opt(schema, "gt")?,
+            ge: get_as_opt(schema, "ge")?,
+            lt: get_as_opt(schema, "lt")?,
+            le: get_as_opt(schema, "le")?,
+            multiple_of,
+            strict,
+        })
+    }
+
+    /// only the first failing constraint is reported
+    fn check_constraints(&self, int: i64) -> ValResult<FieldValue> {
+        if let Some(limit) = self.gt {
+            if int <= limit {
+                return Err(ErrorType::GreaterThan { limit }.into());
+            }
+        }
+        if let Some(limit) = self.ge {
+            if int < limit {
+                return Err(ErrorType::GreaterThanEqual { limit }.into());
+            }
+        }
+        if let Some(limit) = self.lt {
+            if int >= limit {
+                return Err(ErrorType::LessThan { limit }.into());
+            }
+        }
+        if let Some(limit) = self.le {
+            if int > limit {
+                return Err(ErrorType::LessThanEqual { limit }.into());
+            }
+        }
+        if let Some(multiple_of) = self.multiple_of {
+            // wrapping so `i64::MIN % -1` can't overflow
+            if int.wrapping_rem(multiple_of) != 0 {
+                return Err(ErrorType::MultipleOf { multiple_of }.into());
+            }
+        }
         Ok(FieldValue::new_raw(int))
     }
+}
+
+impl Validator for IntValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let strict = state.strict_or(self.strict);
+        let int = if let Ok(py_int) = data.downcast::<PyInt>() {
+            // bool is a subclass of int
+            if strict && data.is_instance_of::<PyBool>() {
+                return Err(ErrorType::IntType.into());
+            }
+            py_int.extract().map_err(|_| ErrorType::IntTooBig)?
+        } else if strict {
+            return Err(ErrorType::IntType.into());
+        } else if let Ok(py_float) = data.downcast::<PyFloat>() {
+            float_as_int(py_float.value())?
+        } else if let Ok(py_str) = data.downcast::<PyString>() {
+            str_as_int(py_str.to_str()?)?
+        } else {
+            return Err(ErrorType::IntType.into());
+        };
+        self.check_constraints(int)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let strict = state.strict_or(self.strict);
+        let peek = jiter.peek()?;
+        let int = match peek {
+            Peek::String if !strict => str_as_int(jiter.known_str()?)?,
+            Peek::True | Peek::False if !strict => i64::from(jiter.known_bool(peek)?),
+            _ if peek.is_num() => match jiter.known_number(peek)? {
+                NumberAny::Int(NumberInt::Int(i)) => i,
+                // jiter gives a `BigInt` for any integer of 19 or more digits, even if it fits in an `i64`
+                NumberAny::Int(NumberInt::BigInt(big_int)) => big_int
+                    .to_string()
+                    .parse()
+                    .map_err(|_| ErrorType::IntTooBig)?,
+                // lax mode accepts integral floats like `5.0`, strict mode rejects any float token, even `5.0`
+                NumberAny::Float(f) if !strict => float_as_int(f)?,
+                NumberAny::Float(_) => return Err(ErrorType::IntType.into()),
+            },
+            _ => {
+                jiter.known_skip(peek)?;
+                return Err(ErrorType::IntType.into());
+            }
+        };
+        self.check_constraints(int)
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        match data.downcast::<PyString>() {
+            Ok(py_str) => self.check_constraints(str_as_int(py_str.to_str()?)?),
+            Err(_) => self.validate_python(state, data),
+        }
+    }
+}
+
+/// Ints are accepted in strict mode too, lax mode also accepts numeric strings
+#[derive(Debug, Clone)]
+pub struct FloatValidator {
+    gt: Option<f64>,
+    ge: Option<f64>,
+    lt: Option<f64>,
+    le: Option<f64>,
+    strict: bool,
+}
+
+impl FloatValidator {
+    pub fn new(schema: &Bound<'_, PyDict>, strict: bool) -> PyResult<Self> {
+        // every comparison with a NaN bound would be false
+        let get_limit = |key: &str| match get_as_opt::<f64>(schema, key)? {
+            Some(limit) if limit.is_nan() => {
+                Err(PyValueError::new_err(format!("{key} must not be NaN")))
+            }
+            limit => Ok(limit),
+        };
+        Ok(Self {
+            gt: get_limit("gt")?,
+            ge: get_limit("ge")?,
+            lt: get_limit("lt")?,
+            le: get_limit("le")?,
+            strict,
+        })
+    }
+
+    /// only the first failing constraint is reported, a NaN input fails every bound
+    fn check_constraints(&self, float: f64) -> ValResult<FieldValue> {
+        if let Some(limit) = self.gt {
+            if float.is_nan() || float <= limit {
+                return Err(ErrorType::FloatGreaterThan { limit }.into());
+            }
+        }
+        if let Some(limit) = self.ge {
+            if float.is_nan() || float < limit {
+                return Err(ErrorType::FloatGreaterThanEqual { limit }.into());
+            }
+        }
+        if let Some(limit) = self.lt {
+            if float.is_nan() || float >= limit {
+                return Err(ErrorType::FloatLessThan { limit }.into());
+            }
+        }
+        if let Some(limit) = self.le {
+            if float.is_nan() || float > limit {
+                return Err(ErrorType::FloatLessThanEqual { limit }.into());
+            }
+        }
+        Ok(FieldValue::new_raw(float))
+    }
+}
+
+impl Validator for FloatValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let strict = state.strict_or(self.strict);
+        let float = if let Ok(py_float) = data.downcast::<PyFloat>() {
+            py_float.value()
+        } else if let Ok(py_int) = data.downcast::<PyInt>() {
+            // bool is a subclass of int
+            if strict && data.is_instance_of::<PyBool>() {
+                return Err(ErrorType::FloatType.into());
+            }
+            py_int.extract().map_err(|_| ErrorType::FloatType)?
+        } else if strict {
+            return Err(ErrorType::FloatType.into());
+        } else if let Ok(py_str) = data.downcast::<PyString>() {
+            str_as_float(py_str.to_str()?)?
+        } else {
+            return Err(ErrorType::FloatType.into());
+        };
+        self.check_constraints(float)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let strict = state.strict_or(self.strict);
+        let peek = jiter.peek()?;
+        let float = match peek {
+            Peek::String if !strict => str_as_float(jiter.known_str()?)?,
+            Peek::True | Peek::False if !strict => f64::from(u8::from(jiter.known_bool(peek)?)),
+            _ if peek.is_num() => jiter.known_float(peek)?,
+            _ => {
+                jiter.known_skip(peek)?;
+                return Err(ErrorType::FloatType.into());
+            }
+        };
+        self.check_constraints(float)
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        match data.downcast::<PyString>() {
+            Ok(py_str) => self.check_constraints(str_as_float(py_str.to_str()?)?),
+            Err(_) => self.validate_python(state, data),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BoolValidator {
+    strict: bool,
+}
+
+impl Validator for BoolValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        if let Ok(py_bool) = data.downcast::<PyBool>() {
+            return Ok(FieldValue::new_raw(py_bool.is_true()));
+        }
+        let b = if state.strict_or(self.strict) {
+            None
+        } else if let Ok(py_int) = data.downcast::<PyInt>() {
+            py_int.extract::<i64>().ok().and_then(int_as_bool)
+        } else if let Ok(py_str) = data.downcast::<PyString>() {
+            str_as_bool(py_str.to_str()?)
+        } else {
+            None
+        };
+        match b {
+            Some(b) => Ok(FieldValue::new_raw(b)),
+            None => Err(ErrorType::BoolType.into()),
+        }
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let strict = state.strict_or(self.strict);
+        let peek = jiter.peek()?;
+        let b = match peek {
+            Peek::True | Peek::False => Some(jiter.known_bool(peek)?),
+            Peek::String if !strict => str_as_bool(jiter.known_str()?),
+            _ if peek.is_num() && !strict => match jiter.known_number(peek)? {
+                NumberAny::Int(NumberInt::Int(i)) => int_as_bool(i),
+                _ => None,
+            },
+            _ => {
+                jiter.known_skip(peek)?;
+                None
+            }
+        };
+        match b {
+            Some(b) => Ok(FieldValue::new_raw(b)),
+            None => Err(ErrorType::BoolType.into()),
+        }
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        match data.downcast::<PyString>() {
+            Ok(py_str) => match str_as_bool(py_str.to_str()?) {
+                Some(b) => Ok(FieldValue::new_raw(b)),
+                None => Err(ErrorType::BoolType.into()),
+            },
+            Err(_) => self.validate_python(state, data),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BytesValidator {
+    strict: bool,
+}
+
+impl Validator for BytesValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        if let Ok(py_bytes) = data.downcast::<PyBytes>() {
+            Ok(FieldValue::new_raw(py_bytes.as_bytes().to_vec()))
+        } else if let (false, Ok(py_str)) =
+            (state.strict_or(self.strict), data.downcast::<PyString>())
+        {
+            Ok(FieldValue::new_raw(py_str.to_str()?.as_bytes().to_vec()))
+        } else {
+            Err(ErrorType::BytesType.into())
+        }
+    }
+
+    /// bytes are represented as base64 strings in JSON, matching `RawData::Bytes` serialization
+    fn validate_json(&self, _: &mut ValidationState, jiter: &mut Jiter) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek == Peek::String {
+            if let Some(bytes) = base64::decode(jiter.known_str()?) {
+                return Ok(FieldValue::new_raw(bytes));
+            }
+        } else {
+            jiter.known_skip(peek)?;
+        }
+        Err(ErrorType::BytesType.into())
+    }
+}
+
+#[derive(Debug)]
+pub struct ListValidator {
+    items_validator: Box<CombinedValidator>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    coerce_single_to_list: bool,
+    strict: bool,
+}
+
+impl ListValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let items_schema = get_as_req(schema, "items_schema")?;
+        Ok(Self {
+            items_validator: Box::new(CombinedValidator::new(&items_schema, strict, definitions)?),
+            min_length: get_as_opt(schema, "min_length")?,
+            max_length: get_as_opt(schema, "max_length")?,
+            coerce_single_to_list: get_as_opt(schema, "coerce_single_to_list")?.unwrap_or(false),
+            strict,
+        })
+    }
+
+    /// With `coerce_single_to_list` in lax mode, a value other than a list or `None` is a list of that one item
+    fn coerce_single(&self, state: &ValidationState, is_none: bool) -> bool {
+        self.coerce_single_to_list && !is_none && !state.strict_or(self.strict)
+    }
+
+    /// Too many python items is an error before any are validated, too few only once the items are valid
+    fn check_max_length(&self, length: usize) -> ValResult<()> {
+        match self.max_length {
+            Some(max) if length > max => Err(ErrorType::TooLong { max }.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_min_length(&self, length: usize) -> ValResult<()> {
+        match self.min_length {
+            Some(min) if length < min => Err(ErrorType::TooShort { min }.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ListValidator {
+    fn validate_py_list<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        let py_list = match data.downcast::<PyList>() {
+            Ok(py_list) => py_list.clone(),
+            Err(_) if self.coerce_single(state, data.is_none()) => {
+                PyList::new_bound(state.py, [data])
+            }
+            Err(_) => return Err(ErrorType::ListType.into()),
+        };
+        self.check_max_length(py_list.len())?;
+        let mut items = SmallVec::with_capacity(py_list.len());
+        let mut errors: Vec<LineError> = Vec::new();
+        for (index, item) in py_list.iter().enumerate() {
+            match input.validate(&self.items_validator, state, &item) {
+                Ok(field_value) => items.push(field_value.into_raw()),
+                Err(e) => errors.extend(e.with_py_input(&item).line_errors_with_loc(index as i64)?),
+            }
+        }
+        if errors.is_empty() {
+            self.check_min_length(items.len())?;
+            Ok(FieldValue::new_raw(RawData::List(Arc::new(items))))
+        } else {
+            Err(errors.into())
+        }
+    }
+}
+
+impl Validator for ListValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_list(state, data, PyInput::Python)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::Array && self.coerce_single(state, peek == Peek::Null) {
+            let mut items = SmallVec::new();
+            match self.items_validator.validate_json_item(state, jiter)? {
+                Some(Ok(field_value)) => items.push(field_value.into_raw()),
+                Some(Err(e)) => return Err(e.line_errors_with_loc(0)?.into()),
+                // partial input ended within the value
+                None => (),
+            }
+            self.check_max_length(items.len())?;
+            if !state.truncated() {
+                self.check_min_length(items.len())?;
+            }
+            return Ok(FieldValue::new_raw(RawData::List(Arc::new(items))));
+        } else if peek != Peek::Array {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::ListType.into());
+        }
+        let mut items = SmallVec::new();
+        let mut errors: Vec<LineError> = Vec::new();
+        let mut next = state.truncate(jiter.known_array())?.flatten();
+        let mut index: i64 = 0;
+        while next.is_some() {
+            // the length isn't known up front, so stop at the first item past `max_length`, keeping the errors of
+            // the items before it, the caller skips the rest of the array
+            if let Some(max) = self.max_length.filter(|max| index as usize >= *max) {
+                errors.push(LineError::new(ErrorType::TooLong { max }));
+                break;
+            }
+            match self.items_validator.validate_json_item(state, jiter)? {
+                Some(Ok(field_value)) => items.push(field_value.into_raw()),
+                Some(Err(e)) => errors.extend(e.line_errors_with_loc(index)?),
+                None => (),
+            }
+            if state.truncated() {
+                break;
+            }
+            index += 1;
+            next = state.truncate(jiter.array_step())?.flatten();
+        }
+        if errors.is_empty() {
+            // partial input may have ended before more items
+            if !state.truncated() {
+                self.check_min_length(items.len())?;
+            }
+            Ok(FieldValue::new_raw(RawData::List(Arc::new(items))))
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_list(state, data, PyInput::Strings)
+    }
+}
+
+/// Keeps the first of equal items, python items use python equality so must be hashable
+#[derive(Debug)]
+pub struct SetValidator {
+    items_validator: Box<CombinedValidator>,
+}
+
+impl SetValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let items_schema = get_as_req(schema, "items_schema")?;
+        Ok(Self {
+            items_validator: Box::new(CombinedValidator::new(&items_schema, strict, definitions)?),
+        })
+    }
+
+    fn validate_py_set<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        // a list is accepted as it's how sets are represented in JSON
+        let py_iter = if data.is_instance_of::<PySet>()
+            || data.is_instance_of::<PyFrozenSet>()
+            || data.is_instance_of::<PyList>()
+        {
+            data.iter()?
+        } else {
+            return Err(ErrorType::SetType.into());
+        };
+        let mut set_items = SetItems::new(py)?;
+        let mut errors: Vec<LineError> = Vec::new();
+        for (index, item) in py_iter.enumerate() {
+            let item = item?;
+            match input.validate(&self.items_validator, state, &item) {
+                Ok(field_value) => set_items.push(py, field_value)?,
+                Err(e) => errors.extend(e.with_py_input(&item).line_errors_with_loc(index as i64)?),
+            }
+        }
+        if errors.is_empty() {
+            Ok(set_items.into_field_value())
+        } else {
+            Err(errors.into())
+        }
+    }
+}
+
+impl Validator for SetValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_set(state, data, PyInput::Python)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::Array {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::SetType.into());
+        }
+        let mut items: SmallVec<[RawData; 8]> = SmallVec::new();
+        let mut errors: Vec<LineError> = Vec::new();
+        let mut next = state.truncate(jiter.known_array())?.flatten();
+        let mut index: i64 = 0;
+        while next.is_some() {
+            match self.items_validator.validate_json_item(state, jiter)? {
+                Some(Ok(field_value)) => {
+                    let raw = field_value.into_raw();
+                    if !items.contains(&raw) {
+                        items.push(raw);
+                    }
+                }
+                Some(Err(e)) => errors.extend(e.line_errors_with_loc(index)?),
+                None => (),
+            }
+            if state.truncated() {
+                break;
+            }
+            index += 1;
+            next = state.truncate(jiter.array_step())?.flatten();
+        }
+        if errors.is_empty() {
+            Ok(FieldValue::new_raw(RawData::Set(Arc::new(items))))
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_set(state, data, PyInput::Strings)
+    }
+}
+
+/// A fixed length tuple where each position has its own validator, errors are located by index
+#[derive(Debug)]
+pub struct TupleValidator {
+    items_validators: Vec<CombinedValidator>,
+}
+
+impl TupleValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let items_schemas: Bound<PyList> = get_as_req(schema, "items_schemas")?;
+        let items_validators = items_schemas
+            .iter()
+            .map(|items_schema| {
+                CombinedValidator::new(items_schema.downcast()?, strict, definitions)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Self { items_validators })
+    }
+
+    fn check_length(&self, length: usize) -> ValResult<()> {
+        match length == self.items_validators.len() {
+            true => Ok(()),
+            false => Err(ErrorType::TupleLength {
+                expected: self.items_validators.len(),
+            }
+            .into()),
+        }
+    }
+
+    fn validate_py_tuple<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        let py_seq = if let Ok(py_tuple) = data.downcast::<PyTuple>() {
+            py_tuple.as_sequence()
+        } else if let Ok(py_list) = data.downcast::<PyList>() {
+            py_list.as_sequence()
+        } else {
+            return Err(ErrorType::TupleType.into());
+        };
+        self.check_length(py_seq.len()?)?;
+        let mut items = SmallVec::with_capacity(self.items_validators.len());
+        let mut errors: Vec<LineError> = Vec::new();
+        for (index, validator) in self.items_validators.iter().enumerate() {
+            let item = py_seq.get_item(index)?;
+            match input.validate(validator, state, &item) {
+                Ok(field_value) => items.push(field_value.into_raw()),
+                Err(e) => errors.extend(e.with_py_input(&item).line_errors_with_loc(index as i64)?),
+            }
+        }
+        if errors.is_empty() {
+            Ok(FieldValue::new_raw(RawData::Tuple(Arc::new(items))))
+        } else {
+            Err(errors.into())
+        }
+    }
+}
+
+impl Validator for TupleValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_tuple(state, data, PyInput::Python)
+    }
+
+    /// the whole array is read before checking its length, errors from items are dropped if it's wrong
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::Array {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::TupleType.into());
+        }
+        let mut items = SmallVec::with_capacity(self.items_validators.len());
+        let mut errors: Vec<LineError> = Vec::new();
+        let mut next = state.truncate(jiter.known_array())?.flatten();
+        let mut index: usize = 0;
+        while next.is_some() {
+            match self.items_validators.get(index) {
+                Some(validator) => match validator.validate_json_item(state, jiter)? {
+                    Some(Ok(field_value)) => items.push(field_value.into_raw()),
+                    Some(Err(e)) => errors.extend(e.line_errors_with_loc(index as i64)?),
+                    None => (),
+                },
+                None => {
+                    state.truncate(jiter.next_skip())?;
+                }
+            }
+            if state.truncated() {
+                break;
+            }
+            index += 1;
+            next = state.truncate(jiter.array_step())?.flatten();
+        }
+        // like missing model fields, the items after partial JSON input ends aren't required
+        if !state.truncated() {
+            self.check_length(index)?;
+        }
+        if errors.is_empty() {
+            Ok(FieldValue::new_raw(RawData::Tuple(Arc::new(items))))
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_tuple(state, data, PyInput::Strings)
+    }
+}
+
+/// Validated items of python input, a python set of those seen so far is used to skip duplicates
+struct SetItems<'py> {
+    seen: Bound<'py, PySet>,
+    items: SmallVec<[RawData; 8]>,
+}
+
+impl<'py> SetItems<'py> {
+    fn new(py: Python<'py>) -> PyResult<Self> {
+        Ok(Self {
+            seen: PySet::empty_bound(py)?,
+            items: SmallVec::new(),
+        })
+    }
+
+    /// unhashable items raise a `TypeError`, as with a python set
+    fn push(&mut self, py: Python, field_value: FieldValue) -> PyResult<()> {
+        let py_value = field_value.to_py(py)?;
+        if !self.seen.contains(&py_value)? {
+            self.seen.add(py_value)?;
+            self.items.push(field_value.into_raw());
+        }
+        Ok(())
+    }
+
+    fn into_field_value(self) -> FieldValue {
+        FieldValue::new_raw(RawData::Set(Arc::new(self.items)))
+    }
+}
+
+#[derive(Debug)]
+pub struct DictValidator {
+    keys_validator: Box<CombinedValidator>,
+    values_validator: Box<CombinedValidator>,
+}
+
+impl DictValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let keys_validator = match get_as_opt::<Bound<PyDict>>(schema, "keys_schema")? {
+            Some(keys_schema) => CombinedValidator::new(&keys_schema, strict, definitions)?,
+            None => CombinedValidator::String(StringValidator {
+                strict,
+                ..StringValidator::default()
+            }),
+        };
+        let values_schema = get_as_req(schema, "values_schema")?;
+        Ok(Self {
+            keys_validator: Box::new(keys_validator),
+            values_validator: Box::new(CombinedValidator::new(
+                &values_schema,
+                strict,
+                definitions,
+            )?),
+        })
+    }
+
+    fn validate_key<'py>(
+        &self,
+        state: &mut ValidationState,
+        key: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        input
+            .validate(&self.keys_validator, state, key)
+            .map_err(|e| e.with_py_input(key))
+    }
+
+    /// Int keys are kept as ints, bools and ints too large for a `LocItem` are converted with `str()` like other keys
+    fn key_loc(key: &Bound<'_, PyAny>) -> PyResult<LocItem> {
+        if key.is_instance_of::<PyInt>() && !key.is_instance_of::<PyBool>() {
+            if let Ok(i) = key.extract() {
+                return Ok(LocItem::I(i));
+            }
+        }
+        Ok(key.str()?.to_str()?.into())
+    }
+
+    /// Errors for both the key and value are located by the key, see `key_loc`
+    fn add_item(
+        key: &Bound<'_, PyAny>,
+        key_result: ValResult<FieldValue>,
+        value: ValResult<FieldValue>,
+        items: &mut SmallVec<[(RawData, RawData); 8]>,
+        errors: &mut Vec<LineError>,
+    ) -> PyResult<()> {
+        match (key_result, value) {
+            (Ok(k), Ok(v)) => items.push((k.into_raw(), v.into_raw())),
+            (key_result, value) => {
+                let loc = Self::key_loc(key)?;
+                for e in [key_result.err(), value.err()].into_iter().flatten() {
+                    errors.extend(e.line_errors_with_loc(loc.clone())?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_py_dict<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        let py_dict = data.downcast::<PyDict>().map_err(|_| ErrorType::DictType)?;
+        let mut items = SmallVec::with_capacity(py_dict.len());
+        let mut errors: Vec<LineError> = Vec::new();
+        for (key, value) in py_dict.iter() {
+            let value = input
+                .validate(&self.values_validator, state, &value)
+                .map_err(|e| e.with_py_input(&value));
+            let key_result = self.validate_key(state, &key, input);
+            Self::add_item(&key, key_result, value, &mut items, &mut errors)?;
+        }
+        if errors.is_empty() {
+            Ok(FieldValue::new_raw(RawData::Dict(Arc::new(items))))
+        } else {
+            Err(errors.into())
+        }
+    }
+}
+
+impl Validator for DictValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_dict(state, data, PyInput::Python)
+    }
+
+    /// JSON keys are always strings, so they're validated like `validate_strings`
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let peek = jiter.peek()?;
+        if peek != Peek::Object {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::DictType.into());
+        }
+        let mut items = SmallVec::new();
+        let mut errors: Vec<LineError> = Vec::new();
+        let next_key = state.truncate(jiter.known_object())?.flatten();
+        let mut next_key = next_key.map(|k| PyString::new_bound(py, k));
+        while let Some(key) = next_key {
+            if let Some(value) = self.values_validator.validate_json_item(state, jiter)? {
+                let key_result = self.validate_key(state, key.as_any(), PyInput::Strings);
+                Self::add_item(key.as_any(), key_result, value, &mut items, &mut errors)?;
+            }
+            if state.truncated() {
+                break;
+            }
+            let next = state.truncate(jiter.next_key())?.flatten();
+            next_key = next.map(|k| PyString::new_bound(py, k));
+        }
+        if errors.is_empty() {
+            Ok(FieldValue::new_raw(RawData::Dict(Arc::new(items))))
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_dict(state, data, PyInput::Strings)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UuidValidator {
+    strict: bool,
+}
+
+impl Validator for UuidValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let strict = state.strict_or(self.strict);
+        if let Some(uuid) = uuid_from_py(data)? {
+            Ok(FieldValue::Both(data.clone().unbind(), RawData::Uuid(uuid)))
+        } else if let (false, Ok(py_str)) = (strict, data.downcast::<PyString>()) {
+            Ok(FieldValue::new_raw(parse_uuid(py_str.to_str()?)?))
+        } else {
+            Err(ErrorType::UuidType.into())
+        }
+    }
+
+    fn validate_json(&self, _: &mut ValidationState, jiter: &mut Jiter) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::String {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::UuidType.into());
+        }
+        Ok(FieldValue::new_raw(parse_uuid(jiter.known_str()?)?))
+    }
 
-    fn validate_json(&self, _: Python, jiter: &mut Jiter) -> ValResult<FieldValue> {
-        match jiter.next_int()? {
-            NumberInt::Int(i) => Ok(FieldValue::new_raw(i)),
-            NumberInt::BigInt(_) => Err(ErrorType::IntTooBig.into()),
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        match data.downcast::<PyString>() {
+            Ok(py_str) => Ok(FieldValue::new_raw(parse_uuid(py_str.to_str()?)?)),
+            Err(_) => self.validate_python(state, data),
         }
     }
 }
+
+fn parse_uuid(s: &str) -> ValResult<Uuid> {
+    Uuid::parse_str(s).map_err(|e| {
+        ErrorType::UuidParsing {
+            error: e.to_string(),
+        }
+        .into()
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct DatetimeValidator {
+    strict: bool,
+}
+
+impl Validator for DatetimeValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let strict = state.strict_or(self.strict);
+        if let Ok(py_dt) = data.downcast::<PyDateTime>() {
+            let dt = datetime_from_py(py_dt)?;
+            Ok(FieldValue::Both(
+                data.clone().unbind(),
+                RawData::Datetime(dt),
+            ))
+        } else if let (false, Ok(py_str)) = (strict, data.downcast::<PyString>()) {
+            Ok(FieldValue::new_raw(parse_datetime(py_str.to_str()?)?))
+        } else {
+            Err(ErrorType::DatetimeType.into())
+        }
+    }
+
+    /// datetimes are ISO-8601 strings in JSON, with or without a timezone offset
+    fn validate_json(&self, _: &mut ValidationState, jiter: &mut Jiter) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::String {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::DatetimeType.into());
+        }
+        Ok(FieldValue::new_raw(parse_datetime(jiter.known_str()?)?))
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        match data.downcast::<PyString>() {
+            Ok(py_str) => Ok(FieldValue::new_raw(parse_datetime(py_str.to_str()?)?)),
+            Err(_) => self.validate_python(state, data),
+        }
+    }
+}
+
+// speedate accepts year 0, python's `datetime` starts at year 1
+fn parse_datetime(s: &str) -> ValResult<DateTime> {
+    let dt = DateTime::parse_str(s).map_err(|e| {
+        let error = e.get_documentation().unwrap_or_default().to_string();
+        ErrorType::DatetimeParsing { error }
+    })?;
+    if dt.date.year < 1 {
+        let error = "year 0 is out of range".to_string();
+        return Err(ErrorType::DatetimeParsing { error }.into());
+    }
+    Ok(dt)
+}
+
+/// Values keep their exact string form, so JSON output doesn't lose precision to `f64`
+#[derive(Debug, Clone)]
+pub struct DecimalValidator {
+    max_digits: Option<usize>,
+    decimal_places: Option<usize>,
+    // from the schema's "decimal_separator", e.g. `,` for `"1,5"`, only for strings, `None` for `.`
+    decimal_separator: Option<char>,
+    strict: bool,
+}
+
+impl DecimalValidator {
+    pub fn new(schema: &Bound<'_, PyDict>, strict: bool) -> PyResult<Self> {
+        let decimal_separator = match get_as_opt::<String>(schema, "decimal_separator")? {
+            None => None,
+            Some(separator) => {
+                let mut chars = separator.chars();
+                match (chars.next(), chars.next()) {
+                    (Some('.'), None) => None,
+                    (Some(c), None) => Some(c),
+                    _ => {
+                        return Err(PyValueError::new_err(
+                            "decimal_separator must be a single character",
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(Self {
+            max_digits: get_as_opt(schema, "max_digits")?,
+            decimal_places: get_as_opt(schema, "decimal_places")?,
+            decimal_separator,
+            strict,
+        })
+    }
+
+    /// digits are counted after `normalize()`, so trailing zeros like in `"1.50"` don't count
+    fn check_constraints(&self, decimal: &Bound<'_, PyAny>) -> ValResult<FieldValue> {
+        let py = decimal.py();
+        if !decimal
+            .call_method0(intern!(py, "is_finite"))?
+            .is_truthy()?
+        {
+            return Err(ErrorType::DecimalParsing.into());
+        }
+        if self.max_digits.is_some() || self.decimal_places.is_some() {
+            let normalized = decimal.call_method0(intern!(py, "normalize"))?;
+            let (_, digit_tuple, exponent): (Bound<PyAny>, Bound<PyTuple>, i64) = normalized
+                .call_method0(intern!(py, "as_tuple"))?
+                .extract()?;
+            let (digits, decimals) = match usize::try_from(exponent) {
+                Ok(exponent) => (digit_tuple.len() + exponent, 0),
+                Err(_) => {
+                    let decimals = exponent.unsigned_abs() as usize;
+                    (digit_tuple.len().max(decimals), decimals)
+                }
+            };
+            if let Some(max_digits) = self.max_digits {
+                if digits > max_digits {
+                    return Err(ErrorType::DecimalMaxDigits { max_digits }.into());
+                }
+            }
+            if let Some(decimal_places) = self.decimal_places {
+                if decimals > decimal_places {
+                    return Err(ErrorType::DecimalMaxPlaces { decimal_places }.into());
+                }
+            }
+        }
+        let decimal_str = decimal.str()?.to_str()?.to_owned();
+        Ok(FieldValue::Both(
+            decimal.clone().unbind(),
+            RawData::Decimal(decimal_str),
+        ))
+    }
+
+    fn check_str(&self, py: Python, s: &str) -> ValResult<FieldValue> {
+        self.check_constraints(&parse_decimal(py, s)?)
+    }
+
+    /// Strings from input use `decimal_separator`, numbers formatted by python or JSON numbers always use `.`
+    fn check_input_str(&self, py: Python, s: &str) -> ValResult<FieldValue> {
+        match self.decimal_separator {
+            Some(separator) if s.contains(separator) => {
+                self.check_str(py, &s.replace(separator, "."))
+            }
+            _ => self.check_str(py, s),
+        }
+    }
+}
+
+impl Validator for DecimalValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let strict = state.strict_or(self.strict);
+        if data.is_instance(get_decimal_type(py)?)? {
+            self.check_constraints(data)
+        } else if strict || data.is_instance_of::<PyBool>() {
+            Err(ErrorType::DecimalType.into())
+        } else if let Ok(py_str) = data.downcast::<PyString>() {
+            self.check_input_str(py, py_str.to_str()?)
+        } else if data.is_instance_of::<PyInt>() || data.is_instance_of::<PyFloat>() {
+            // via `str()` so floats keep their shortest representation, e.g. `0.1` not `0.1000000000000000055...`
+            self.check_str(py, data.str()?.to_str()?)
+        } else {
+            Err(ErrorType::DecimalType.into())
+        }
+    }
+
+    /// numbers are parsed from their exact text in the JSON, not via `f64`
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let peek = jiter.peek()?;
+        if peek == Peek::String {
+            self.check_input_str(py, jiter.known_str()?)
+        } else if peek.is_num() {
+            let number = std::str::from_utf8(jiter.next_number_bytes()?)
+                .map_err(|_| ErrorType::DecimalParsing)?;
+            self.check_str(py, number)
+        } else {
+            jiter.known_skip(peek)?;
+            Err(ErrorType::DecimalType.into())
+        }
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        match data.downcast::<PyString>() {
+            Ok(py_str) => self.check_input_str(py, py_str.to_str()?),
+            Err(_) => self.validate_python(state, data),
+        }
+    }
+}
+
+fn parse_decimal<'py>(py: Python<'py>, s: &str) -> ValResult<Bound<'py, PyAny>> {
+    get_decimal_type(py)?
+        .call1((s.trim(),))
+        .map_err(|_| ErrorType::DecimalParsing.into())
+}
+
+#[derive(Debug, Clone)]
+pub struct LiteralValidator {
+    expected: Vec<RawData>,
+    // formatted once for `ErrorType::LiteralError`
+    expected_repr: String,
+}
+
+impl LiteralValidator {
+    pub fn new(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let expected_list: Bound<PyList> = get_as_req(schema, "expected")?;
+        let expected = expected_list
+            .iter()
+            .map(|value| match RawData::from_py(&value) {
+                raw @ (RawData::None | RawData::Bool(_) | RawData::Int(_) | RawData::Str(_)) => {
+                    Ok(raw)
+                }
+                _ => Err(PyValueError::new_err(format!(
+                    "literal values must be None, bool, int or str, got {value}"
+                ))),
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let Some(expected_repr) = expected_repr(&expected) else {
+            return Err(PyValueError::new_err(
+                "literal expected values must not be empty",
+            ));
+        };
+        Ok(Self {
+            expected,
+            expected_repr,
+        })
+    }
+
+    /// bools and ints are distinct `RawData` variants, so `True` doesn't match `1`
+    fn check(&self, input: &RawData) -> ValResult<FieldValue> {
+        match self.expected.iter().find(|expected| *expected == input) {
+            Some(expected) => Ok(FieldValue::new_raw(expected.clone())),
+            None => Err(ErrorType::LiteralError {
+                expected: self.expected_repr.clone(),
+            }
+            .into()),
+        }
+    }
+}
+
+impl Validator for LiteralValidator {
+    fn validate_python<'py>(
+        &self,
+        _: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.check(&RawData::from_py(data))
+    }
+
+    fn validate_json(&self, _: &mut ValidationState, jiter: &mut Jiter) -> ValResult<FieldValue> {
+        self.check(&jiter.next_value()?.into())
+    }
+}
+
+/// The member itself is stored, python input can also be a member value unless in strict mode
+#[derive(Debug)]
+pub struct EnumValidator {
+    cls: Py<PyType>,
+    // the enum class name, used as the title
+    name: String,
+    values: Vec<RawData>,
+    // in the same order as `values`
+    members: Vec<PyObject>,
+    // formatted once for `ErrorType::EnumError`
+    expected_repr: String,
+    strict: bool,
+}
+
+impl EnumValidator {
+    pub fn new(schema: &Bound<'_, PyDict>, strict: bool) -> PyResult<Self> {
+        let cls: Bound<PyType> = get_as_req(schema, "cls")?;
+        let mut values = Vec::new();
+        let mut members = Vec::new();
+        for member in cls.iter()? {
+            let member = member?;
+            let value = member.getattr(intern!(cls.py(), "value"))?;
+            match RawData::from_py(&value) {
+                raw @ (RawData::None | RawData::Bool(_) | RawData::Int(_) | RawData::Str(_)) => {
+                    values.push(raw)
+                }
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "enum values must be None, bool, int or str, got {value}"
+                    )))
+                }
+            }
+            members.push(member.unbind());
+        }
+        let Some(expected_repr) = expected_repr(&values) else {
+            return Err(PyValueError::new_err("enum must have members"));
+        };
+        Ok(Self {
+            name: cls.name()?.to_string(),
+            cls: cls.unbind(),
+            values,
+            members,
+            expected_repr,
+            strict,
+        })
+    }
+
+    fn find_member(&self, py: Python, input: &RawData) -> ValResult<FieldValue> {
+        match self.values.iter().position(|value| value == input) {
+            Some(index) => Ok(FieldValue::Py(self.members[index].clone_ref(py))),
+            None => Err(self.error()),
+        }
+    }
+
+    fn error(&self) -> ValError {
+        ErrorType::EnumError {
+            expected: self.expected_repr.clone(),
+        }
+        .into()
+    }
+}
+
+impl Validator for EnumValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let strict = state.strict_or(self.strict);
+        if data.is_instance(self.cls.bind(py))? {
+            Ok(FieldValue::Py(data.clone().unbind()))
+        } else if strict {
+            Err(self.error())
+        } else {
+            self.find_member(py, &RawData::from_py(data))
+        }
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        self.find_member(py, &jiter.next_value()?.into())
+    }
+
+    /// strings are also compared as ints, for enums with int values
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let Ok(py_str) = data.downcast::<PyString>() else {
+            return self.validate_python(state, data);
+        };
+        let s = py_str.to_str()?;
+        self.find_member(py, &RawData::Str(s.to_owned()))
+            .or_else(|e| match str_as_int(s) {
+                Ok(i) => self.find_member(py, &RawData::Int(i)),
+                Err(_) => Err(e),
+            })
+    }
+}
+
+/// A `str` as UTF-8, lone surrogates can't be encoded so are a `string_unicode` error with python's reason
+pub fn py_str_to_str<'a>(py_str: &'a Bound<'_, PyString>) -> ValResult<&'a str> {
+    py_str.to_str().map_err(|e| {
+        let error = e.value_bound(py_str.py()).to_string();
+        ErrorType::StringUnicode { error }.into()
+    })
+}
+
+/// The UTF-8 bytes of JSON input passed as `str`, `bytes` or `bytearray`, `None` for other types
+pub fn json_bytes<'a>(json_data: &'a Bound<'_, PyAny>) -> ValResult<Option<Cow<'a, [u8]>>> {
+    if let Ok(py_bytes) = json_data.downcast::<PyBytes>() {
+        Ok(Some(Cow::Borrowed(py_bytes.as_bytes())))
+    } else if let Ok(py_str) = json_data.downcast::<PyString>() {
+        match py_str_to_str(py_str) {
+            Ok(s) => Ok(Some(Cow::Borrowed(s.as_bytes()))),
+            Err(e) => Err(e.with_py_input(json_data)),
+        }
+    } else if let Ok(py_byte_array) = json_data.downcast::<PyByteArray>() {
+        Ok(Some(Cow::Owned(py_byte_array.to_vec())))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Like pydantic's `Json[...]`, a string which is parsed and validated with the inner `schema`
+#[derive(Debug)]
+pub struct JsonFieldValidator {
+    validator: Box<CombinedValidator>,
+}
+
+impl JsonFieldValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let inner_schema = get_as_req(schema, "schema")?;
+        Ok(Self {
+            validator: Box::new(CombinedValidator::new(&inner_schema, strict, definitions)?),
+        })
+    }
+
+    /// The inner document is complete, so it ending early is invalid JSON even with `allow_partial`
+    fn validate_document(
+        &self,
+        state: &mut ValidationState,
+        json_data: &[u8],
+    ) -> ValResult<FieldValue> {
+        let allow_partial = std::mem::replace(&mut state.allow_partial, false);
+        let mut jiter = Jiter::new(json_data);
+        let start = jiter.clone();
+        let result = self
+            .validator
+            .validate_json(state, &mut jiter)
+            .map_err(|e| e.with_json_input(start))
+            .and_then(|field_value| {
+                jiter.finish()?;
+                Ok(field_value)
+            })
+            .map_err(|e| e.with_json_position(json_data));
+        state.allow_partial = allow_partial;
+        result
+    }
+}
+
+impl Validator for JsonFieldValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        match json_bytes(data)? {
+            Some(json_data) => self.validate_document(state, &json_data),
+            None => Err(ErrorType::JsonType.into()),
+        }
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::String {
+            jiter.known_skip(peek)?;
+            return Err(ErrorType::JsonType.into());
+        }
+        let json_data = jiter.known_str()?;
+        self.validate_document(state, json_data.as_bytes())
+    }
+}
+
+/// Calls `function` with the input then validates its result, JSON input is converted to python first
+#[derive(Debug)]
+pub struct FunctionBeforeValidator {
+    function: ValidatorFunction,
+    validator: Box<CombinedValidator>,
+}
+
+impl FunctionBeforeValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let inner_schema = get_as_req(schema, "schema")?;
+        Ok(Self {
+            function: ValidatorFunction::new(schema)?,
+            validator: Box::new(CombinedValidator::new(&inner_schema, strict, definitions)?),
+        })
+    }
+}
+
+impl Validator for FunctionBeforeValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let value = self.function.call(state, data)?;
+        self.validator.validate_python(state, &value)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let raw: RawData = jiter.next_value()?.into();
+        let data = raw.to_py(py)?.into_bound(py);
+        self.validate_python(state, &data)
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let value = self.function.call(state, data)?;
+        self.validator.validate_strings(state, &value)
+    }
+}
+
+/// Validates with the inner `schema` then calls `function` with the result
+#[derive(Debug)]
+pub struct FunctionAfterValidator {
+    function: ValidatorFunction,
+    validator: Box<CombinedValidator>,
+}
+
+impl FunctionAfterValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let inner_schema = get_as_req(schema, "schema")?;
+        Ok(Self {
+            function: ValidatorFunction::new(schema)?,
+            validator: Box::new(CombinedValidator::new(&inner_schema, strict, definitions)?),
+        })
+    }
+
+    fn call(&self, state: &mut ValidationState, field_value: FieldValue) -> ValResult<FieldValue> {
+        let py = state.py;
+        let value = field_value.into_py_object(py)?.into_bound(py);
+        let value = self.function.call(state, &value)?;
+        Ok(FieldValue::Py(value.unbind()))
+    }
+}
+
+/// With `with_context` the validation context is passed as a second argument
+#[derive(Debug)]
+struct ValidatorFunction {
+    function: PyObject,
+    with_context: bool,
+}
+
+impl ValidatorFunction {
+    fn new(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
+        Ok(Self {
+            function: get_as_req(schema, "function")?,
+            with_context: get_as_opt(schema, "with_context")?.unwrap_or(false),
+        })
+    }
+
+    fn call<'py>(
+        &self,
+        state: &ValidationState,
+        value: &Bound<'py, PyAny>,
+    ) -> ValResult<Bound<'py, PyAny>> {
+        let py = value.py();
+        let function = self.function.bind(py);
+        let result = match self.with_context {
+            true => function.call1((value, state.context)),
+            false => function.call1((value,)),
+        };
+        result.map_err(|e| ValError::from_function_error(py, e))
+    }
+}
+
+impl Validator for FunctionAfterValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let field_value = self.validator.validate_python(state, data)?;
+        self.call(state, field_value)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let field_value = self.validator.validate_json(state, jiter)?;
+        self.call(state, field_value)
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        let field_value = self.validator.validate_strings(state, data)?;
+        self.call(state, field_value)
+    }
+}
+
+/// The first choice to succeed wins, otherwise each choice's errors are located by its title
+#[derive(Debug)]
+pub struct UnionValidator {
+    choices: Vec<CombinedValidator>,
+}
+
+impl UnionValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let choices_list: Bound<PyList> = get_as_req(schema, "choices")?;
+        let choices = choices_list
+            .iter()
+            .map(|choice| CombinedValidator::new(choice.downcast()?, strict, definitions))
+            .collect::<PyResult<Vec<_>>>()?;
+        if choices.is_empty() {
+            return Err(PyValueError::new_err("union choices must not be empty"));
+        }
+        Ok(Self { choices })
+    }
+}
+
+impl UnionValidator {
+    fn validate_py_choices<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        let mut errors: Vec<LineError> = Vec::new();
+        for choice in &self.choices {
+            match input.validate(choice, state, data) {
+                Ok(field_value) => return Ok(field_value),
+                Err(e) => {
+                    errors.extend(e.with_py_input(data).line_errors_with_loc(choice.title())?)
+                }
+            }
+        }
+        Err(errors.into())
+    }
+}
+
+impl Validator for UnionValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_choices(state, data, PyInput::Python)
+    }
+
+    /// each attempt parses from a clone of `jiter`, so every choice starts at the beginning of the value
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let mut errors: Vec<LineError> = Vec::new();
+        for choice in &self.choices {
+            let mut attempt = jiter.clone();
+            match choice.validate_json_item(state, &mut attempt)? {
+                Some(Ok(field_value)) => {
+                    *jiter = attempt;
+                    return Ok(field_value);
+                }
+                Some(Err(e)) => errors.extend(e.line_errors_with_loc(choice.title())?),
+                // partial input ended within the value before the choice found an error
+                None => (),
+            }
+            // partial JSON input ending within a failed choice doesn't apply to the next one
+            state.truncated = false;
+        }
+        // if partial input ends within the value the state is left truncated, so the errors are taken to be
+        // for the parts which were complete, there may be none if every choice was cut off
+        state.truncate(jiter.next_skip())?;
+        Err(errors.into())
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_choices(state, data, PyInput::Strings)
+    }
+}
+
+/// Picks the choice for the value's `discriminator`, errors are located by the tag
+#[derive(Debug)]
+pub struct TaggedUnionValidator {
+    discriminator: String,
+    tags: KeyLookup,
+    choices: Vec<CombinedValidator>,
+    // the tags as listed in `UnionTagInvalid` errors, e.g. `'cat', 'dog'`
+    expected_tags: String,
+}
+
+impl TaggedUnionValidator {
+    pub fn new(
+        schema: &Bound<'_, PyDict>,
+        strict: bool,
+        definitions: &Definitions,
+    ) -> PyResult<Self> {
+        let choices_dict: Bound<PyDict> = get_as_req(schema, "choices")?;
+        let mut tags = Vec::with_capacity(choices_dict.len());
+        let mut choices = Vec::with_capacity(choices_dict.len());
+        for (tag, choice) in choices_dict.iter() {
+            tags.push(tag.extract::<String>()?);
+            choices.push(CombinedValidator::new(
+                choice.downcast()?,
+                strict,
+                definitions,
+            )?);
+        }
+        if choices.is_empty() {
+            return Err(PyValueError::new_err(
+                "tagged-union choices must not be empty",
+            ));
+        }
+        let expected_tags: Vec<String> = tags.iter().map(|tag| format!("'{tag}'")).collect();
+        Ok(Self {
+            discriminator: get_as_req(schema, "discriminator")?,
+            tags: KeyLookup::new(tags.iter().map(String::as_str)),
+            choices,
+            expected_tags: expected_tags.join(", "),
+        })
+    }
+
+    fn find_choice(&self, tag: &str) -> ValResult<&CombinedValidator> {
+        match self.tags.get(tag) {
+            Some(index) => Ok(&self.choices[index]),
+            None => Err(ErrorType::UnionTagInvalid {
+                discriminator: format!("'{}'", self.discriminator),
+                tag: tag.to_string(),
+                expected_tags: self.expected_tags.clone(),
+            }
+            .into()),
+        }
+    }
+
+    fn tag_not_found(&self) -> ValError {
+        ErrorType::UnionTagNotFound {
+            discriminator: format!("'{}'", self.discriminator),
+        }
+        .into()
+    }
+
+    fn validate_py_choice<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+        input: PyInput,
+    ) -> ValResult<FieldValue> {
+        let py = state.py;
+        let tag = match data.downcast::<PyDict>() {
+            Ok(dict) => dict.get_item(&self.discriminator)?,
+            Err(_) => match data.getattr(self.discriminator.as_str()) {
+                Ok(tag) => Some(tag),
+                Err(e) if e.is_instance_of::<PyAttributeError>(py) => None,
+                Err(e) => return Err(e.into()),
+            },
+        };
+        let tag = tag.ok_or_else(|| self.tag_not_found())?.str()?;
+        let tag = tag.to_str()?;
+        match input.validate(self.find_choice(tag)?, state, data) {
+            Ok(field_value) => Ok(field_value),
+            Err(e) => Err(e.with_py_input(data).line_errors_with_loc(tag)?.into()),
+        }
+    }
+
+    /// Read from a copy so the whole object is then validated by the choice, `None` if partial input ends first
+    fn json_tag(&self, state: &mut ValidationState, jiter: &Jiter) -> ValResult<Option<String>> {
+        let mut lookahead = jiter.clone();
+        let mut next_key = state.truncate(lookahead.known_object())?.flatten();
+        while let Some(key) = next_key {
+            if key == self.discriminator {
+                let Some(peek) = state.truncate(lookahead.peek())? else {
+                    return Ok(None);
+                };
+                return match peek {
+                    Peek::String => Ok(state.truncate(lookahead.known_str())?.map(str::to_string)),
+                    _ => match state.truncate(lookahead.known_value(peek))? {
+                        Some(value) => {
+                            let raw: RawData = value.into();
+                            Ok(Some(raw.to_py(state.py)?.bind(state.py).str()?.to_string()))
+                        }
+                        None => Ok(None),
+                    },
+                };
+            }
+            if state.truncate(lookahead.next_skip())?.is_none() {
+                return Ok(None);
+            }
+            next_key = state.truncate(lookahead.next_key())?.flatten();
+        }
+        if state.truncated() {
+            return Ok(None);
+        }
+        Err(self.tag_not_found())
+    }
+}
+
+impl Validator for TaggedUnionValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_choice(state, data, PyInput::Python)
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        let peek = jiter.peek()?;
+        if peek != Peek::Object {
+            jiter.known_skip(peek)?;
+            return Err(self.tag_not_found());
+        }
+        let choice = self.json_tag(state, jiter).and_then(|tag| match tag {
+            Some(tag) => Ok(Some((self.find_choice(&tag)?, tag))),
+            None => Ok(None),
+        });
+        let (choice, tag) = match choice {
+            Ok(Some(choice)) => choice,
+            // partial input ended before the tag, the state is left truncated with no errors as when every
+            // choice of a union is cut off
+            Ok(None) => return Err(Vec::new().into()),
+            Err(e) => {
+                // an invalid tag is reported even if partial input ends later in the object
+                state.truncate(jiter.next_skip())?;
+                return Err(e);
+            }
+        };
+        match choice.validate_json(state, jiter) {
+            Ok(field_value) => Ok(field_value),
+            Err(e) => Err(e.line_errors_with_loc(tag)?.into()),
+        }
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.validate_py_choice(state, data, PyInput::Strings)
+    }
+}
+
+/// Slots for every definition exist before any are built, so definitions can refer to each other
+#[derive(Debug, Default)]
+pub struct Definitions {
+    validators: HashMap<String, Arc<OnceLock<CombinedValidator>>>,
+}
+
+impl Definitions {
+    pub fn new(schema: &Bound<'_, PyDict>, strict: bool) -> PyResult<Self> {
+        let Some(schemas) = get_as_opt::<Bound<PyDict>>(schema, "definitions")? else {
+            return Ok(Self::default());
+        };
+        let validators = schemas
+            .keys()
+            .iter()
+            .map(|name| Ok((name.extract()?, Arc::new(OnceLock::new()))))
+            .collect::<PyResult<_>>()?;
+        let definitions = Self { validators };
+        for (name, schema) in schemas.iter() {
+            let name: String = name.extract()?;
+            let validator = CombinedValidator::new(schema.downcast()?, strict, &definitions)?;
+            // each name is only set once, `schemas` is a dict
+            let _ = definitions.validators[&name].set(validator);
+        }
+        Ok(definitions)
+    }
+
+    fn get(&self, name: &str) -> PyResult<Weak<OnceLock<CombinedValidator>>> {
+        match self.validators.get(name) {
+            Some(validator) => Ok(Arc::downgrade(validator)),
+            None => Err(PyValueError::new_err(format!("Unknown definition: {name}"))),
+        }
+    }
+}
+
+/// The reference is weak so recursive schemas don't form a reference cycle
+#[derive(Debug)]
+pub struct DefinitionRefValidator {
+    name: String,
+    validator: Weak<OnceLock<CombinedValidator>>,
+}
+
+impl DefinitionRefValidator {
+    pub fn new(schema: &Bound<'_, PyDict>, definitions: &Definitions) -> PyResult<Self> {
+        let name: String = get_as_req(schema, "schema_ref")?;
+        let validator = definitions.get(&name)?;
+        Ok(Self { name, validator })
+    }
+
+    fn with_validator<T>(
+        &self,
+        f: impl FnOnce(&CombinedValidator) -> ValResult<T>,
+    ) -> ValResult<T> {
+        let validator = self.validator.upgrade();
+        match validator.as_deref().and_then(OnceLock::get) {
+            Some(validator) => f(validator),
+            // e.g. validating assignment to a model whose `SchemaValidator` has been dropped
+            None => Err(ValError::InternalError(PyRuntimeError::new_err(format!(
+                "Definition \"{}\" is no longer available",
+                self.name
+            )))),
+        }
+    }
+}
+
+impl Validator for DefinitionRefValidator {
+    fn validate_python<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.with_validator(|v| v.validate_python(state, data))
+    }
+
+    fn validate_json(
+        &self,
+        state: &mut ValidationState,
+        jiter: &mut Jiter,
+    ) -> ValResult<FieldValue> {
+        self.with_validator(|v| v.validate_json(state, jiter))
+    }
+
+    fn validate_strings<'py>(
+        &self,
+        state: &mut ValidationState,
+        data: &Bound<'py, PyAny>,
+    ) -> ValResult<FieldValue> {
+        self.with_validator(|v| v.validate_strings(state, data))
+    }
+}
+
+/// Expected values for error messages, like `'a', 'b' or 'c'`, `None` if there are no values
+fn expected_repr(expected: &[RawData]) -> Option<String> {
+    match expected.split_last()? {
+        (last, []) => Some(literal_repr(last)),
+        (last, rest) => {
+            let rest: Vec<String> = rest.iter().map(literal_repr).collect();
+            Some(format!("{} or {}", rest.join(", "), literal_repr(last)))
+        }
+    }
+}
+
+/// python style representation of a literal value for error messages
+fn literal_repr(value: &RawData) -> String {
+    match value {
+        RawData::None => "None".to_string(),
+        RawData::Bool(true) => "True".to_string(),
+        RawData::Bool(false) => "False".to_string(),
+        RawData::Int(i) => i.to_string(),
+        RawData::Str(s) => format!("'{s}'"),
+        _ => "<unknown>".to_string(),
+    }
+}
+
+/// only floats with no fractional part are valid ints
+fn float_as_int(f: f64) -> ValResult<i64> {
+    if !f.is_finite() || f.fract() != 0.0 {
+        Err(ErrorType::IntFromFloat.into())
+    } else if f < i64::MIN as f64 || f >= i64::MAX as f64 {
+        Err(ErrorType::IntTooBig.into())
+    } else {
+        Ok(f as i64)
+    }
+}
+
+/// Like python's `int()` a sign and leading zeros are accepted, out of range integers are too big
+fn str_as_int(s: &str) -> ValResult<i64> {
+    s.trim().parse().map_err(|e: ParseIntError| match e.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => ErrorType::IntTooBig.into(),
+        _ => ErrorType::IntType.into(),
+    })
+}
+
+/// Like python's `float()`, e.g. `"inf"` and `"nan"` are accepted
+fn str_as_float(s: &str) -> ValResult<f64> {
+    s.trim().parse().map_err(|_| ErrorType::FloatType.into())
+}
+
+fn int_as_bool(i: i64) -> Option<bool> {
+    match i {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
+
+fn str_as_bool(s: &str) -> Option<bool> {
+    match s.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::run_python;
+
+    #[test]
+    fn bool_spellings() {
+        run_python(
+            r#"
+            v = model_validator(field('b', {'type': 'bool'}))
+            for value, expected in [
+                (True, True), (False, False), (1, True), (0, False),
+                ('true', True), ('false', False), ('yes', True), ('no', False),
+                ('on', True), ('off', False), ('TRUE', True), ('Off', False),
+            ]:
+                assert v.validate_python({'b': value}).b is expected, value
+            for json, expected in [
+                (b'true', True), (b'false', False), (b'1', True), (b'0', False),
+                (b'"yes"', True), (b'"NO"', False),
+            ]:
+                assert v.validate_json(b'{"b": %s}' % json).b is expected, json
+            for value in ['maybe', 2, 1.0, None, '']:
+                assert errors(v.validate_python, {'b': value}) == [('bool_type', ('b',))], value
+            for json in [b'"maybe"', b'2', b'1.0', b'null', b'[]']:
+                assert errors(v.validate_json, b'{"b": %s}' % json) == [('bool_type', ('b',))], json
+            "#,
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(field('b', {'type': 'bytes'}))
+            m = v.validate_python({'b': b'\x00\xffab'})
+            assert m.b == b'\x00\xffab'
+            dumped = m.__pydantic_model_data__.model_dump_json()
+            assert json.loads(dumped) == {'b': 'AP9hYg=='}
+            assert v.validate_json(dumped).b == b'\x00\xffab'
+            assert v.validate_python({'b': 'é'}).b == 'é'.encode()
+            assert errors(v.validate_python, {'b': 1}) == [('bytes_type', ('b',))]
+            assert errors(v.validate_json, b'{"b": "not base64!"}') == [('bytes_type', ('b',))]
+
+            strict = model_validator(field('b', {'type': 'bytes'}), strict=True)
+            assert strict.validate_python({'b': b'ab'}).b == b'ab'
+            assert errors(strict.validate_python, {'b': 'ab'}) == [('bytes_type', ('b',))]
+            assert errors(v.validate_python, {'b': 'ab'}, strict=True) == [('bytes_type', ('b',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn string_length() {
+        run_python(
+            r#"
+            v = model_validator(field('s', {'type': 'string', 'min_length': 2, 'max_length': 3}))
+            # lengths are counted in characters, not bytes
+            for value in ['ab', 'abc', 'éé', 'ééé', '日本', '😀😀😀']:
+                assert v.validate_python({'s': value}).s == value, value
+                assert v.validate_json(('{"s": "%s"}' % value).encode()).s == value, value
+            for value in ['a', '😀']:
+                assert errors(v.validate_python, {'s': value}) == [('string_too_short', ('s',))], value
+            for value in ['abcd', 'éééé', '😀😀😀😀']:
+                assert errors(v.validate_python, {'s': value}) == [('string_too_long', ('s',))], value
+                assert errors(v.validate_json, ('{"s": "%s"}' % value).encode()) == [('string_too_long', ('s',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn int_constraints() {
+        run_python(
+            r#"
+            v = model_validator(field('i', {'type': 'int', 'gt': 0, 'ge': 1, 'lt': 10, 'le': 8}))
+            assert v.validate_python({'i': 1}).i == 1
+            assert v.validate_json(b'{"i": 8}').i == 8
+            for value, error_type in [(0, 'greater_than'), (-1, 'greater_than'), (9, 'less_than_equal'), (10, 'less_than')]:
+                assert errors(v.validate_python, {'i': value}) == [(error_type, ('i',))], value
+            "#,
+        );
+    }
+
+    #[test]
+    fn int_multiple_of() {
+        run_python(
+            r#"
+            v = model_validator(field('i', {'type': 'int', 'multiple_of': 3}))
+            assert v.validate_python({'i': -9}).i == -9
+            try:
+                v.validate_python({'i': 7})
+            except ValidationError as e:
+                assert e.errors()[0]['msg'] == 'Input should be a multiple of 3', e.errors()
+            else:
+                raise AssertionError('expected a ValidationError')
+            v = model_validator(field('i', {'type': 'int', 'multiple_of': -1}))
+            assert v.validate_python({'i': -2**63}).i == -2**63
+            try:
+                model_validator(field('i', {'type': 'int', 'multiple_of': 0}))
+            except ValueError as e:
+                assert 'multiple_of must not be zero' in str(e), e
+            else:
+                raise AssertionError('expected a ValueError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn list() {
+        run_python(
+            r#"
+            v = model_validator(field('l', {'type': 'list', 'items_schema': {'type': 'int'}}))
+            assert v.validate_python({'l': [1, '2']}).l == [1, 2]
+            assert v.validate_json(b'{"l": [1, "2"]}').l == [1, 2]
+            assert errors(v.validate_python, {'l': [1, 'x', 3, 'y']}) == [('int_type', ('l', 1)), ('int_type', ('l', 3))]
+            assert errors(v.validate_json, b'{"l": [1, "x"]}') == [('int_type', ('l', 1))]
+            for value in [(1, 2), {}, 'ab']:
+                assert errors(v.validate_python, {'l': value}) == [('list_type', ('l',))], value
+
+            nested = model_validator(field('l', {'type': 'list', 'items_schema': {'type': 'list', 'items_schema': {'type': 'int'}}}))
+            assert errors(nested.validate_json, b'{"l": [[1], [2, "x"]]}') == [('int_type', ('l', 1, 1))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn dict() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(field('d', {'type': 'dict', 'values_schema': {'type': 'int', 'ge': 0}}))
+            m = v.validate_python({'d': {'a': 1, 'b': 2}})
+            assert m.d == {'a': 1, 'b': 2}
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'d': {'a': 1, 'b': 2}}
+            assert v.validate_json(b'{"d": {}}').d == {}
+            assert errors(v.validate_python, {'d': {'a': 1, 'b': -2}}) == [('greater_than_equal', ('d', 'b'))]
+            assert errors(v.validate_python, {'d': {'a': 1, 3: -2}}) == [('greater_than_equal', ('d', 3))]
+            assert errors(v.validate_json, b'{"d": {"a": 1, "b": -2}}') == [('greater_than_equal', ('d', 'b'))]
+            assert errors(v.validate_python, {'d': [1]}) == [('dict_type', ('d',))]
+            assert errors(v.validate_json, b'{"d": []}') == [('dict_type', ('d',))]
+
+            v = model_validator(field('d', {
+                'type': 'dict',
+                'keys_schema': {'type': 'string', 'max_length': 1},
+                'values_schema': {'type': 'list', 'items_schema': {'type': 'bool'}},
+            }))
+            assert errors(v.validate_json, b'{"d": {"a": [true], "bb": [false]}}') == [('string_too_long', ('d', 'bb'))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn bool_strict() {
+        run_python(
+            r#"
+            v = model_validator(field('b', {'type': 'bool'}), strict=True)
+            assert v.validate_python({'b': True}).b is True
+            assert v.validate_json(b'{"b": false}').b is False
+            for value in [1, 'yes']:
+                assert errors(v.validate_python, {'b': value}) == [('bool_type', ('b',))], value
+            for json in [b'1', b'"yes"']:
+                assert errors(v.validate_json, b'{"b": %s}' % json) == [('bool_type', ('b',))], json
+            # strings are still coerced for string input such as form data
+            assert v.validate_strings({'b': 'yes'}).b is True
+            "#,
+        );
+    }
+
+    #[test]
+    fn strict_vs_lax() {
+        run_python(
+            r#"
+            fields = [
+                field('i', {'type': 'int'}, required=False, default=0),
+                field('s', {'type': 'string'}, required=False, default=''),
+                field('b', {'type': 'bool'}, required=False, default=False),
+                field('y', {'type': 'bytes'}, required=False, default=b''),
+            ]
+            lax = model_validator(*fields)
+            strict = model_validator(*fields, strict=True)
+
+            assert lax.validate_python({'i': '5'}).i == 5
+            assert lax.validate_python({'i': 5.0}).i == 5
+            assert lax.validate_json(b'{"i": "5"}').i == 5
+            assert lax.validate_json(b'{"i": 5.0}').i == 5
+            for value in ['5', 5.0, True]:
+                assert errors(strict.validate_python, {'i': value}) == [('int_type', ('i',))], value
+            for json in [b'"5"', b'5.0', b'true']:
+                assert errors(strict.validate_json, b'{"i": %s}' % json) == [('int_type', ('i',))], json
+            assert strict.validate_python({'i': 5}).i == 5
+
+            assert errors(strict.validate_python, {'s': b'x'}) == [('string_type', ('s',))]
+            assert errors(strict.validate_python, {'b': 1}) == [('bool_type', ('b',))]
+            assert errors(strict.validate_python, {'y': 'x'}) == [('bytes_type', ('y',))]
+            assert lax.validate_python({'s': b'x', 'b': 1, 'y': 'x'}).y == b'x'
+
+            # the per-call flag overrides the schema's in either direction
+            assert strict.validate_python({'i': '5'}, strict=False).i == 5
+            assert errors(lax.validate_python, {'i': '5'}, strict=True) == [('int_type', ('i',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn uuid() {
+        run_python(
+            r#"
+            import json, uuid
+            v = model_validator(field('u', {'type': 'uuid'}))
+            u = uuid.uuid4()
+            assert v.validate_python({'u': u}).u is u
+            assert v.validate_python({'u': str(u)}).u == u
+            m = v.validate_json(('{"u": "%s"}' % u.hex).encode())
+            assert m.u == u
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'u': str(u)}
+            assert errors(v.validate_python, {'u': 'not-a-uuid'}) == [('uuid_parsing', ('u',))]
+            assert errors(v.validate_python, {'u': 1}) == [('uuid_type', ('u',))]
+            assert errors(v.validate_python, {'u': str(u)}, strict=True) == [('uuid_type', ('u',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn parse_datetime_year_0() {
+        assert!(parse_datetime("0001-01-01T00:00:00").is_ok());
+        assert!(parse_datetime("0000-01-01T00:00:00").is_err());
+    }
+
+    #[test]
+    fn datetime() {
+        run_python(
+            r#"
+            from datetime import datetime, timezone, timedelta
+            v = model_validator(field('d', {'type': 'datetime'}))
+            aware = datetime(2020, 1, 2, 3, 4, 5, tzinfo=timezone(timedelta(hours=2)))
+            assert v.validate_json(b'{"d": "2020-01-02T03:04:05+02:00"}').d == aware
+            assert v.validate_python({'d': '2020-01-02T03:04:05+02:00'}).d == aware
+            naive = v.validate_json(b'{"d": "2020-01-02T03:04:05"}').d
+            assert naive == datetime(2020, 1, 2, 3, 4, 5) and naive.tzinfo is None
+            assert v.validate_python({'d': aware}).d is aware
+            for value in ['0000-01-01T00:00:00', '2020-13-01T00:00:00', 'nope']:
+                assert errors(v.validate_python, {'d': value}) == [('datetime_parsing', ('d',))]
+                json = ('{"d": "%s"}' % value).encode()
+                assert errors(v.validate_json, json) == [('datetime_parsing', ('d',))]
+            assert errors(v.validate_json, b'{"d": 1}') == [('datetime_type', ('d',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn literal() {
+        run_python(
+            r#"
+            v = model_validator(field('x', {'type': 'literal', 'expected': ['a', 1, True, None]}))
+            for value in ['a', 1, True, None]:
+                x = v.validate_python({'x': value}).x
+                assert x == value and type(x) is type(value), value
+            for json, expected in [(b'"a"', 'a'), (b'1', 1), (b'true', True), (b'null', None)]:
+                assert v.validate_json(b'{"x": %s}' % json).x == expected, json
+            # no coercion and no bool/int confusion
+            for value in ['b', 2, False, 1.0, [1]]:
+                assert errors(v.validate_python, {'x': value}) == [('literal_error', ('x',))], value
+            try:
+                v.validate_python({'x': 'b'})
+            except ValidationError as e:
+                assert e.errors()[0]['msg'] == "Input should be 'a', 1, True or None", e.errors()
+
+            for expected, msg in [([], 'literal expected values must not be empty'), ([1.5], 'literal values must be None, bool, int or str, got 1.5')]:
+                try:
+                    model_validator(field('x', {'type': 'literal', 'expected': expected}))
+                except ValueError as e:
+                    assert msg in str(e), e
+                else:
+                    raise AssertionError(f'expected a ValueError for {expected}')
+            "#,
+        );
+    }
+
+    #[test]
+    fn union() {
+        run_python(
+            r#"
+            u = {'type': 'union', 'choices': [{'type': 'int'}, {'type': 'string'}]}
+            v = model_validator(field('x', u))
+            assert v.validate_python({'x': 5}).x == 5
+            assert v.validate_python({'x': 'x'}).x == 'x'
+            # choices are tried in order, so lax int wins for numeric strings
+            assert v.validate_python({'x': '5'}).x == 5
+            assert v.validate_python({'x': '5'}, strict=True).x == '5'
+            assert errors(v.validate_python, {'x': [1]}) == [('int_type', ('x', 'int')), ('string_type', ('x', 'str'))]
+
+            v = model_validator(field('x', {'type': 'union', 'choices': [
+                {'type': 'model', 'cls': Model, 'fields': [field('a', {'type': 'int'})]},
+                {'type': 'list', 'items_schema': {'type': 'int'}},
+            ]}))
+            assert v.validate_json(b'{"x": {"a": 1}}').x.a == 1
+            assert v.validate_json(b'{"x": [1]}').x == [1]
+            assert ('int_type', ('x', 'Model', 'a')) in errors(v.validate_json, b'{"x": {"a": "q"}}')
+
+            try:
+                model_validator(field('x', {'type': 'union', 'choices': []}))
+            except ValueError as e:
+                assert 'union choices must not be empty' in str(e), e
+            else:
+                raise AssertionError('expected a ValueError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn decimal() {
+        run_python(
+            r#"
+            import json
+            from decimal import Decimal
+            v = model_validator(
+                field('d', {'type': 'decimal', 'decimal_places': 2}),
+                field('m', {'type': 'decimal', 'max_digits': 4}, required=False, default=None),
+                field('l', {'type': 'list', 'items_schema': {'type': 'decimal'}}, required=False, default=[]),
+            )
+            for value, expected in [('1.23', '1.23'), (Decimal('1.2300'), '1.2300'), (1.5, '1.5'), (3, '3')]:
+                d = v.validate_python({'d': value}).d
+                assert isinstance(d, Decimal) and str(d) == expected, value
+            assert v.validate_python({'d': 1, 'm': '1.2E+3'}).m == Decimal('1.2E+3')
+            for data, error_type in [
+                ({'d': '1.234'}, 'decimal_max_places'),
+                ({'d': True}, 'decimal_type'),
+                ({'d': [1]}, 'decimal_type'),
+                ({'d': 'abc'}, 'decimal_parsing'),
+                ({'d': 'NaN'}, 'decimal_parsing'),
+            ]:
+                assert errors(v.validate_python, data) == [(error_type, ('d',))], data
+            for m in ['12345', '123.45']:
+                assert errors(v.validate_python, {'d': 1, 'm': m}) == [('decimal_max_digits', ('m',))], m
+
+            # JSON numbers keep the precision of their source text
+            pi = '3.14159265358979323846264338327950288'
+            m = v.validate_json(b'{"d": 0.10, "l": [1e-2, "%s"]}' % pi.encode())
+            assert str(m.d) == '0.10'
+            assert m.l == [Decimal('0.01'), Decimal(pi)]
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'d': '0.10', 'm': None, 'l': ['0.01', pi]}
+            assert errors(v.validate_json, b'{"d": 1.234}') == [('decimal_max_places', ('d',))]
+            assert errors(v.validate_json, b'{"d": null}') == [('decimal_type', ('d',))]
+
+            strict = model_validator(field('d', {'type': 'decimal'}), strict=True)
+            assert errors(strict.validate_python, {'d': '1.1'}) == [('decimal_type', ('d',))]
+            assert strict.validate_strings({'d': '1.1'}).d == Decimal('1.1')
+            assert strict.validate_json(b'{"d": 1.1}').d == Decimal('1.1')
+            "#,
+        );
+    }
+
+    #[test]
+    fn int_lax() {
+        run_python(
+            r#"
+            v = model_validator(field('i', {'type': 'int'}))
+            for value in [5.0, '5', ' 5 ']:
+                assert v.validate_python({'i': value}).i == 5, value
+            for json in [b'5.0', b'"5"']:
+                assert v.validate_json(b'{"i": %s}' % json).i == 5, json
+            for value, error_type in [(5.5, 'int_from_float'), ('abc', 'int_type'), (1e30, 'int_parsing_size')]:
+                assert errors(v.validate_python, {'i': value}) == [(error_type, ('i',))], value
+            assert errors(v.validate_json, b'{"i": 5.5}') == [('int_from_float', ('i',))]
+            assert errors(v.validate_json, b'{"i": "abc"}') == [('int_type', ('i',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn set() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(
+                field('s', {'type': 'set', 'items_schema': {'type': 'int'}}),
+                field('t', {'type': 'set', 'items_schema': {'type': 'string'}}, required=False, default=None),
+            )
+            for value, expected in [({1, 2}, {1, 2}), (frozenset([3]), {3}), ([1, '1', 2, 1.0, 3], {1, 2, 3}), ([], set())]:
+                s = v.validate_python({'s': value}).s
+                assert type(s) is set and s == expected, value
+            for value in [5, (1, 2)]:
+                assert errors(v.validate_python, {'s': value}) == [('set_type', ('s',))], value
+            assert errors(v.validate_python, {'s': [1, 'x', 2]}) == [('int_type', ('s', 1))]
+
+            # JSON dumps keep the order each item was first seen
+            m = v.validate_json(b'{"s": [3, 1, 3, 2, 1]}')
+            assert m.s == {1, 2, 3}
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'s': [3, 1, 2], 't': None}
+            m = v.validate_json(b'{"s": [], "t": ["b", "a", "b"]}')
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'s': [], 't': ['b', 'a']}
+            assert errors(v.validate_json, b'{"s": {"a": 1}}') == [('set_type', ('s',))]
+            assert errors(v.validate_json, b'{"s": [1, "x", [1]]}') == [('int_type', ('s', 1)), ('int_type', ('s', 2))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn tuple() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(field('t', {'type': 'tuple', 'items_schemas': [{'type': 'int'}, {'type': 'string'}]}))
+            for value in [(1, 'a'), [1, 'a']]:
+                m = v.validate_python({'t': value})
+                assert m.t == (1, 'a'), value
+                assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'t': [1, 'a']}
+            assert v.validate_json(b'{"t": [1, "a"]}').t == (1, 'a')
+            assert v.validate_strings({'t': ['5', 'x']}).t == (5, 'x')
+            for value in [(1,), (1, 'a', 3)]:
+                assert errors(v.validate_python, {'t': value}) == [('tuple_length', ('t',))], value
+            assert errors(v.validate_python, {'t': ('x', 2)}) == [('int_type', ('t', 0))]
+            assert errors(v.validate_python, {'t': 'ab'}) == [('tuple_type', ('t',))]
+            for json_ in [b'[1]', b'[]', b'[1, "a", {"x": [1]}]']:
+                assert errors(v.validate_json, b'{"t": %s}' % json_) == [('tuple_length', ('t',))], json_
+            assert errors(v.validate_json, b'{"t": ["x", 2]}') == [('int_type', ('t', 0)), ('string_type', ('t', 1))]
+            assert errors(v.validate_json, b'{"t": {}}') == [('tuple_type', ('t',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn enum_() {
+        run_python(
+            r#"
+            import enum, json
+
+            class Color(enum.Enum):
+                RED = 'r'
+                GREEN = 'g'
+
+            class Num(enum.IntEnum):
+                ONE = 1
+                TWO = 2
+
+            fields = [
+                field('c', {'type': 'enum', 'cls': Color}),
+                field('n', {'type': 'enum', 'cls': Num}, required=False, default=Num.ONE),
+            ]
+            v = model_validator(*fields)
+            m = v.validate_python({'c': 'r', 'n': 2})
+            assert m.c is Color.RED and m.n is Num.TWO
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'c': 'r', 'n': 2}
+            assert v.validate_python({'c': Color.GREEN}).c is Color.GREEN
+            m = v.validate_json(b'{"c": "g", "n": 2}')
+            assert m.c is Color.GREEN and m.n is Num.TWO
+            assert v.validate_strings({'c': 'r', 'n': '2'}).n is Num.TWO
+            assert errors(v.validate_python, {'c': 'x', 'n': 3}) == [('enum', ('c',)), ('enum', ('n',))]
+            assert errors(v.validate_json, b'{"c": "R"}') == [('enum', ('c',))]
+            try:
+                v.validate_python({'c': 'x'})
+            except ValidationError as e:
+                assert e.errors()[0]['msg'] == "Input should be 'r' or 'g'", e.errors()
+
+            strict = model_validator(*fields, strict=True)
+            assert strict.validate_python({'c': Color.RED}).c is Color.RED
+            assert errors(strict.validate_python, {'c': 'r'}) == [('enum', ('c',))]
+
+            u = model_validator(field('x', {'type': 'union', 'choices': [{'type': 'enum', 'cls': Num}, {'type': 'int'}]}))
+            assert errors(u.validate_python, {'x': 'a'}) == [('enum', ('x', 'Num')), ('int_type', ('x', 'int'))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn function_before() {
+        run_python(
+            r#"
+            def strip(v):
+                return v.strip() if isinstance(v, str) else v
+
+            def check(v):
+                if v == 'x':
+                    raise ValueError('no x')
+                assert v != 'y', 'no y'
+                if v == 'z':
+                    raise KeyError('z')
+                return v
+
+            v = model_validator(
+                field('s', {'type': 'function-before', 'function': strip, 'schema': {'type': 'string', 'min_length': 3}}),
+                field('c', {'type': 'function-before', 'function': check, 'schema': {'type': 'string'}}, required=False, default=''),
+            )
+            assert v.validate_python({'s': '  abc  '}).s == 'abc'
+            assert v.validate_json(b'{"s": "  abcd "}').s == 'abcd'
+            assert errors(v.validate_python, {'s': '  ab  '}) == [('string_too_short', ('s',))]
+            assert errors(v.validate_json, b'{"s": " a "}') == [('string_too_short', ('s',))]
+            assert errors(v.validate_python, {'s': 'abc', 'c': 'x'}) == [('value_error', ('c',))]
+            assert errors(v.validate_json, b'{"s": "abc", "c": "y"}') == [('assertion_error', ('c',))]
+            try:
+                v.validate_python({'s': 'abc', 'c': 'x'})
+            except ValidationError as e:
+                assert e.errors()[0]['msg'] == 'Value error, no x', e.errors()
+            # other exceptions aren't validation errors and propagate unchanged
+            try:
+                v.validate_python({'s': 'abc', 'c': 'z'})
+            except KeyError as e:
+                assert e.args == ('z',)
+            else:
+                raise AssertionError('expected a KeyError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn function_after() {
+        run_python(
+            r#"
+            import json
+
+            def even(v):
+                if v % 2:
+                    raise ValueError(f'{v} is odd')
+                return v
+
+            v = model_validator(
+                field('n', {'type': 'function-after', 'function': even, 'schema': {'type': 'int'}}),
+                field('l', {
+                    'type': 'list',
+                    'items_schema': {'type': 'function-after', 'function': lambda v: v * 2, 'schema': {'type': 'int'}},
+                }, required=False, default=[]),
+            )
+            m = v.validate_python({'n': '4', 'l': [1, '2']})
+            assert (m.n, m.l) == (4, [2, 4])
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'n': 4, 'l': [2, 4]}
+            m = v.validate_json(b'{"n": 2, "l": [3]}')
+            assert (m.n, m.l) == (2, [6])
+            assert errors(v.validate_python, {'n': 3}) == [('value_error', ('n',))]
+            assert errors(v.validate_json, b'{"n": 5, "l": [1]}') == [('value_error', ('n',))]
+            # the function only runs once the inner schema passes
+            assert errors(v.validate_json, b'{"n": "x"}') == [('int_type', ('n',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn function_with_context() {
+        run_python(
+            r#"
+            def check_min(v, context):
+                if context is not None and v < context['min']:
+                    raise ValueError(f"must be at least {context['min']}")
+                return v
+
+            s = {'type': 'function-after', 'function': check_min, 'with_context': True, 'schema': {'type': 'int'}}
+            v = model_validator(field('n', s), field('l', {'type': 'list', 'items_schema': s}, required=False, default=[]))
+            ctx = {'min': 3}
+            assert v.validate_python({'n': 5}, context=ctx).n == 5
+            assert v.validate_python({'n': 2}).n == 2
+            assert errors(v.validate_python, {'n': 2}, context=ctx) == [('value_error', ('n',))]
+            assert errors(v.validate_json, b'{"n": 4, "l": [1, 9]}', context=ctx) == [('value_error', ('l', 0))]
+            assert errors(v.validate_strings, {'n': '1'}, context=ctx) == [('value_error', ('n',))]
+            assert errors(v.validate_json_many, b'[{"n": 1}]', context=ctx) == [('value_error', (0, 'n'))]
+            assert errors(v.validate_partial, {'n': 1}, context=ctx) == [('value_error', ('n',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn definitions() {
+        run_python(
+            r#"
+            node = {'type': 'model', 'cls': Model, 'fields': [
+                field('value', {'type': 'int'}),
+                field('children', {
+                    'type': 'list', 'items_schema': {'type': 'definition-ref', 'schema_ref': 'Node'},
+                }, required=False, default_factory=list),
+            ]}
+            v = SchemaValidator({'type': 'definition-ref', 'schema_ref': 'Node', 'definitions': {'Node': node}})
+            t = v.validate_python({'value': 1, 'children': [{'value': 2, 'children': [{'value': 3}]}, {'value': 4}]})
+            assert t.children[0].children[0].value == 3
+            assert t.__pydantic_model_data__.model_dump() == {'value': 1, 'children': [
+                {'value': 2, 'children': [{'value': 3, 'children': []}]},
+                {'value': 4, 'children': []},
+            ]}
+            assert errors(
+                v.validate_json, b'{"value": 1, "children": [{"value": 2, "children": [{"value": "x"}]}]}'
+            ) == [('int_type', ('children', 0, 'children', 0, 'value'))]
+            try:
+                SchemaValidator({'type': 'definition-ref', 'schema_ref': 'Nope'})
+            except ValueError as e:
+                assert 'Unknown definition: Nope' in str(e), e
+            else:
+                raise AssertionError('expected a ValueError')
+
+            lists = SchemaValidator({'type': 'definition-ref', 'schema_ref': 'L', 'definitions': {
+                'L': {'type': 'list', 'items_schema': {'type': 'definition-ref', 'schema_ref': 'L'}},
+            }})
+            assert lists.validate_python([[], [[]]]) == [[], [[]]]
+            cyclic = []
+            cyclic.append(cyclic)
+            assert errors(lists.validate_python, cyclic)[0][0] == 'recursion_limit'
+            "#,
+        );
+    }
+
+    #[test]
+    fn int_from_json_float() {
+        run_python(
+            r#"
+            lax = model_validator(field('a', {'type': 'int'}))
+            strict = model_validator(field('a', {'type': 'int'}), strict=True)
+            for json, expected in [(b'5', 5), (b'5.0', 5), (b'5e0', 5), (b'-0.0', 0)]:
+                assert lax.validate_json(b'{"a": %s}' % json).a == expected, json
+            assert errors(lax.validate_json, b'{"a": 5.5}') == [('int_from_float', ('a',))]
+            assert errors(lax.validate_json, b'{"a": 1e300}') == [('int_parsing_size', ('a',))]
+            # strict mode only accepts JSON integers, even for whole floats
+            assert strict.validate_json(b'{"a": 5}').a == 5
+            for json in [b'5.0', b'5.5', b'5e0', b'-0.0', b'1e300']:
+                assert errors(strict.validate_json, b'{"a": %s}' % json) == [('int_type', ('a',))], json
+            "#,
+        );
+    }
+
+    #[test]
+    fn dict_json_keys() {
+        run_python(
+            r#"
+            import json
+            schema = {'type': 'dict', 'keys_schema': {'type': 'int'}, 'values_schema': {'type': 'string'}}
+            for strict in [False, True]:
+                v = model_validator(field('d', schema), strict=strict)
+                # JSON keys are always strings, so they're parsed even in strict mode
+                m = v.validate_json(b'{"d": {"1": "a", "2": "b", " 3": "c"}}')
+                assert m.d == {1: 'a', 2: 'b', 3: 'c'}, strict
+                assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'d': {'1': 'a', '2': 'b', '3': 'c'}}
+                assert sorted(errors(v.validate_json, b'{"d": {"1": "a", "x": "b", "1.5": "c"}}')) == [
+                    ('int_type', ('d', '1.5')), ('int_type', ('d', 'x')),
+                ]
+                assert v.validate_python({'d': {1: 'a'}}).d == {1: 'a'}
+
+            assert model_validator(field('d', schema)).validate_python({'d': {'1': 'a'}}).d == {1: 'a'}
+            strict = model_validator(field('d', schema), strict=True)
+            assert errors(strict.validate_python, {'d': {'1': 'a'}}) == [('int_type', ('d', '1'))]
+            bools = model_validator(
+                field('d', {'type': 'dict', 'keys_schema': {'type': 'bool'}, 'values_schema': {'type': 'int'}}), strict=True
+            )
+            assert bools.validate_json(b'{"d": {"true": 1, "false": 0}}').d == {True: 1, False: 0}
+            "#,
+        );
+    }
+
+    #[test]
+    fn with_metadata() {
+        run_python(
+            r#"
+            s = {'type': 'with-metadata', 'schema': {'type': 'string', 'min_length': 1}, 'metadata': {'max_length': 3, 'title': 'x', 'gt': 5}}
+            v = model_validator(field('a', s))
+            assert v.validate_python({'a': 'abc'}).a == 'abc'
+            assert errors(v.validate_python, {'a': 'abcd'}) == [('string_too_long', ('a',))]
+            assert errors(v.validate_python, {'a': ''}) == [('string_too_short', ('a',))]
+
+            # nested metadata is merged from the inside out
+            v = SchemaValidator({
+                'type': 'with-metadata',
+                'schema': {'type': 'with-metadata', 'schema': {'type': 'int', 'gt': 0}, 'metadata': {'le': 10}},
+                'metadata': {'gt': 5},
+            })
+            assert v.validate_json(b'6') == 6
+            assert errors(v.validate_json, b'5') == [('greater_than', ())]
+            assert errors(v.validate_python, 11) == [('less_than_equal', ())]
+            try:
+                SchemaValidator({'type': 'with-metadata', 'schema': {'type': 'int'}})
+            except KeyError:
+                pass
+            else:
+                raise AssertionError('expected a KeyError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn decimal_separator() {
+        run_python(
+            r#"
+            from decimal import Decimal
+            comma = model_validator(field('d', {'type': 'decimal', 'decimal_separator': ','}))
+            default = model_validator(field('d', {'type': 'decimal'}))
+            assert comma.validate_python({'d': '1,5'}).d == Decimal('1.5')
+            assert comma.validate_json(b'{"d": "1,5"}').d == Decimal('1.5')
+            assert comma.validate_strings({'d': ' -2,25 '}).d == Decimal('-2.25')
+            # numbers, and strings with a `.`, are still accepted
+            assert comma.validate_json(b'{"d": 1.5}').d == Decimal('1.5')
+            assert comma.validate_python({'d': 1.5}).d == Decimal('1.5')
+            assert comma.validate_python({'d': '1.5'}).d == Decimal('1.5')
+            assert errors(comma.validate_python, {'d': '1,5'}, strict=True) == [('decimal_type', ('d',))]
+
+            assert errors(default.validate_python, {'d': '1,5'}) == [('decimal_parsing', ('d',))]
+            assert errors(default.validate_json, b'{"d": "1,5"}') == [('decimal_parsing', ('d',))]
+            assert errors(default.validate_strings, {'d': '1,5'}) == [('decimal_parsing', ('d',))]
+            try:
+                SchemaValidator({'type': 'decimal', 'decimal_separator': ',,'})
+            except ValueError as e:
+                assert 'decimal_separator must be a single character' in str(e), e
+            else:
+                raise AssertionError('expected a ValueError')
+            "#,
+        );
+    }
+
+    #[test]
+    fn string_from_numbers() {
+        run_python(
+            r#"
+            v = model_validator(field('s', {'type': 'string', 'max_length': 4}))
+            for value, expected in [(5, '5'), (True, 'True'), (1.5, '1.5')]:
+                assert v.validate_python({'s': value}).s == expected, value
+            # constraints apply to the coerced string
+            assert errors(v.validate_python, {'s': 12345}) == [('string_too_long', ('s',))]
+            assert errors(v.validate_python, {'s': 5}, strict=True) == [('string_type', ('s',))]
+            assert errors(v.validate_python, {'s': [1]}) == [('string_type', ('s',))]
+            assert errors(v.validate_json, b'{"s": 5}') == [('string_type', ('s',))]
+
+            # JSON numbers keep their source text
+            v = model_validator(field('s', {'type': 'string', 'coerce_numbers_to_str': True}))
+            assert v.validate_json(b'{"s": 1.50e3}').s == '1.50e3'
+            assert v.validate_json(b'{"s": -7}').s == '-7'
+            assert errors(v.validate_json, b'{"s": 5}', strict=True) == [('string_type', ('s',))]
+            assert errors(v.validate_json, b'{"s": true}') == [('string_type', ('s',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn json_field() {
+        run_python(
+            r#"
+            v = model_validator(field('j', {'type': 'json', 'schema': {'type': 'int'}}))
+            for value in ['42', b' 42 ', bytearray(b'42')]:
+                j = v.validate_python({'j': value}).j
+                assert j == 42 and type(j) is int, value
+            assert v.validate_json(b'{"j": "42"}').j == 42
+            assert errors(v.validate_python, {'j': '4x'}) == [('json_invalid', ('j',))]
+            assert errors(v.validate_python, {'j': '"a"'}) == [('int_type', ('j',))]
+            assert errors(v.validate_python, {'j': 42}) == [('json_type', ('j',))]
+            assert errors(v.validate_json, b'{"j": 42}') == [('json_type', ('j',))]
+            # the inner document must be complete, even for partial input
+            assert errors(v.validate_json, b'{"j": "[1"}') == [('json_invalid', ('j',))]
+            assert errors(v.validate_json, b'{"j": "[1"}', allow_partial=True) == [('json_invalid', ('j',))]
+
+            v = model_validator(
+                field('j', {'type': 'json', 'schema': {'type': 'list', 'items_schema': {'type': 'int'}}}),
+                field('k', {'type': 'int'}),
+            )
+            assert v.validate_json(b'{"j": "[1, 2]", "k": 1}').j == [1, 2]
+            assert errors(v.validate_json, b'{"j": "[1, \\"x\\", 3]", "k": "z"}') == [
+                ('int_type', ('j', 1)), ('int_type', ('k',)),
+            ]
+            assert SchemaValidator({'type': 'json', 'schema': {'type': 'int'}}).validate_json('"5"') == 5
+            "#,
+        );
+    }
+
+    #[test]
+    fn dict_key_locations() {
+        run_python(
+            r#"
+            v = SchemaValidator({'type': 'dict', 'keys_schema': {'type': 'int'}, 'values_schema': {'type': 'int'}})
+            # int keys stay ints in the location, other keys use their `str()`
+            assert errors(v.validate_python, {1: 1, 2: 'x'}) == [('int_type', (2,))]
+            assert errors(v.validate_json, b'{"2": "x"}') == [('int_type', ('2',))]
+            assert sorted(errors(v.validate_python, {True: 'x', 10**30: 'y', 'k': 1})) == [
+                ('int_parsing_size', ('1000000000000000000000000000000',)),
+                ('int_type', ('1000000000000000000000000000000',)),
+                ('int_type', ('True',)),
+                ('int_type', ('k',)),
+            ]
+            try:
+                v.validate_python({2: 'x'})
+            except ValidationError as e:
+                assert '"loc":[2]' in e.json(), e.json()
+            "#,
+        );
+    }
+
+    #[test]
+    fn list_length() {
+        run_python(
+            r#"
+            calls = []
+
+            def count(v):
+                calls.append(v)
+                return v
+
+            item = {'type': 'function-after', 'function': count, 'schema': {'type': 'int'}}
+            v = model_validator(
+                field('l', {'type': 'list', 'items_schema': item, 'min_length': 2, 'max_length': 3}),
+                field('n', {'type': 'int'}),
+            )
+            for items in [[1, 2], [1, 2, 3]]:
+                assert v.validate_python({'l': items, 'n': 1}).l == items
+                assert v.validate_json(b'{"l": %s, "n": 1}' % str(items).encode()).l == items
+            assert errors(v.validate_python, {'l': [1], 'n': 1}) == [('too_short', ('l',))]
+            assert errors(v.validate_json, b'{"l": [1, 2, 3, 4], "n": 1}') == [('too_long', ('l',))]
+            assert errors(v.validate_python, {'l': ['x'], 'n': 1}) == [('int_type', ('l', 0))]
+
+            # items past `max_length` aren't validated, and the rest of the input still is
+            calls.clear()
+            assert errors(v.validate_json, b'{"l": [1, 2, 3, 4, 5, 6], "n": "x"}') == [('too_long', ('l',)), ('int_type', ('n',))]
+            assert calls == [1, 2, 3]
+            calls.clear()
+            assert errors(v.validate_python, {'l': [1, 2, 3, 4, 5, 6], 'n': 1}) == [('too_long', ('l',))]
+            assert calls == []
+            # errors of the items before `max_length` are kept
+            calls.clear()
+            assert errors(v.validate_json, b'{"l": [1, "x", 3, 4, 5], "n": 1}') == [('too_long', ('l',)), ('int_type', ('l', 1))]
+            assert calls == [1, 3]
+            assert v.validate_json(b'{"n": 1, "l": [1', allow_partial=True).l == [1]
+
+            try:
+                SchemaValidator({'type': 'list', 'items_schema': {'type': 'int'}, 'min_length': 1}).validate_python([])
+            except ValidationError as e:
+                assert e.errors()[0]['ctx'] == {'field_type': 'List', 'min_length': 1}, e.errors()
+            "#,
+        );
+    }
+
+    #[test]
+    fn tagged_union() {
+        run_python(
+            r#"
+            class Cat(Model):
+                __slots__ = ()
+
+            class Dog(Model):
+                __slots__ = ()
+
+            pet = {'type': 'tagged-union', 'discriminator': 'type', 'choices': {
+                'cat': {'type': 'model', 'cls': Cat, 'fields': [
+                    field('type', {'type': 'literal', 'expected': ['cat']}), field('meows', {'type': 'int'}),
+                ]},
+                'dog': {'type': 'model', 'cls': Dog, 'fields': [
+                    field('type', {'type': 'literal', 'expected': ['dog']}), field('barks', {'type': 'int'}),
+                ]},
+            }}
+            v = model_validator(field('pet', pet), field('n', {'type': 'int'}))
+            m = v.validate_python({'pet': {'type': 'cat', 'meows': 3}, 'n': 1})
+            assert type(m.pet) is Cat and m.pet.meows == 3
+            # the tag needn't come first in JSON
+            m = v.validate_json(b'{"pet": {"meows": 4, "type": "cat"}, "n": 1}')
+            assert type(m.pet) is Cat and m.pet.meows == 4
+            assert type(v.validate_json(b'{"pet": {"type": "dog", "barks": 1}, "n": 1}').pet) is Dog
+
+            assert errors(v.validate_python, {'pet': {'type': 'fish'}, 'n': 1}) == [('union_tag_invalid', ('pet',))]
+            assert errors(v.validate_json, b'{"pet": {"type": 1}, "n": 1}') == [('union_tag_invalid', ('pet',))]
+            assert errors(v.validate_json, b'{"pet": {"type": "fish", "x": [1, 2]}, "n": "x"}') == [
+                ('int_type', ('n',)), ('union_tag_invalid', ('pet',)),
+            ]
+            for json in [b'{"meows": 1}', b'[1]']:
+                assert errors(v.validate_json, b'{"pet": %s, "n": 1}' % json) == [('union_tag_not_found', ('pet',))], json
+            assert errors(v.validate_python, {'pet': 'x', 'n': 1}) == [('union_tag_not_found', ('pet',))]
+            # only the matching choice's errors are reported
+            assert errors(v.validate_python, {'pet': {'type': 'cat', 'meows': 'x'}, 'n': 1}) == [('int_type', ('pet', 'cat', 'meows'))]
+            assert errors(v.validate_json, b'{"pet": {"type": "cat", "meows": "x"}, "n": 1}') == [('int_type', ('pet', 'cat', 'meows'))]
+            assert v.validate_json(b'{"n": 1, "pet": {"type": "cat", "meo', allow_partial=True).n == 1
+
+            try:
+                SchemaValidator(pet).validate_python({'type': 'fish'})
+            except ValidationError as e:
+                assert e.errors()[0]['msg'] == "Input tag 'fish' found using 'type' does not match any of the expected tags: 'cat', 'dog'"
+            "#,
+        );
+    }
+
+    #[test]
+    fn coerce_single_to_list() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(
+                field('ids', {'type': 'list', 'items_schema': {'type': 'int'}, 'coerce_single_to_list': True}, required=False, default=None),
+                field('short', {
+                    'type': 'list', 'items_schema': {'type': 'int'}, 'coerce_single_to_list': True, 'max_length': 0,
+                }, required=False, default=None),
+            )
+            for validate in [v.validate_python, lambda data, **kw: v.validate_json(json.dumps(data), **kw)]:
+                assert validate({'ids': 1}).ids == [1]
+                assert validate({'ids': '2'}).ids == [2]
+                assert validate({'ids': [1, '2']}).ids == [1, 2]
+                assert errors(validate, {'ids': None}) == [('list_type', ('ids',))]
+                # the single value is validated as the first item
+                assert errors(validate, {'ids': 'x'}) == [('int_type', ('ids', 0))]
+                assert errors(validate, {'ids': {'a': 1}}) == [('int_type', ('ids', 0))]
+                assert errors(validate, {'short': 3}) == [('too_long', ('short',))]
+                for data in [{'ids': 1}, {'ids': 'x'}, {'short': 3}]:
+                    assert errors(validate, data, strict=True) == [('list_type', (next(iter(data)),))], data
+            assert v.validate_json('{"ids": 12', allow_partial=True).ids == [12]
+            "#,
+        );
+    }
+
+    #[test]
+    fn str_as_int_bounds() {
+        assert_eq!(str_as_int("9223372036854775807").ok(), Some(i64::MAX));
+        assert_eq!(str_as_int("-9223372036854775808").ok(), Some(i64::MIN));
+        assert_eq!(str_as_int("+5").ok(), Some(5));
+        assert_eq!(str_as_int("007").ok(), Some(7));
+        assert_eq!(str_as_int(" -3 ").ok(), Some(-3));
+        assert!(str_as_int("9223372036854775808").is_err());
+        assert!(str_as_int("--1").is_err());
+    }
+
+    #[test]
+    fn int_bounds() {
+        run_python(
+            r#"
+            v = model_validator(field('a', {'type': 'int'}))
+            MAX, MIN = 2**63 - 1, -2**63
+            for value in [MAX, MIN]:
+                assert v.validate_json(b'{"a": %d}' % value).a == value
+                assert v.validate_json(b'{"a": "%d"}' % value).a == value
+                assert v.validate_python({'a': str(value)}).a == value
+                assert v.validate_python({'a': value}).a == value
+            for value in [MAX + 1, MIN - 1, 10**30]:
+                assert errors(v.validate_json, b'{"a": %d}' % value) == [('int_parsing_size', ('a',))]
+                assert errors(v.validate_json, b'{"a": "%d"}' % value) == [('int_parsing_size', ('a',))]
+                assert errors(v.validate_python, {'a': str(value)}) == [('int_parsing_size', ('a',))]
+                assert errors(v.validate_python, {'a': value}) == [('int_parsing_size', ('a',))]
+
+            # strings follow python's `int()`, JSON numbers follow the JSON grammar
+            for value, expected in [('+5', 5), ('007', 7), (' -3 ', -3), ('-0', 0)]:
+                assert v.validate_python({'a': value}).a == expected, value
+                assert v.validate_json(b'{"a": "%s"}' % value.encode()).a == expected, value
+            for value in ['--1', '1_000', '+', '']:
+                assert errors(v.validate_python, {'a': value}) == [('int_type', ('a',))], value
+            assert v.validate_json(b'{"a": -0}').a == 0
+            for json in [b'+1', b'01', b'-']:
+                assert errors(v.validate_json, b'{"a": %s}' % json) == [('json_invalid', ())], json
+            "#,
+        );
+    }
+
+    #[derive(Debug)]
+    struct Always42;
+
+    impl Validator for Always42 {
+        fn validate_python<'py>(
+            &self,
+            _: &mut ValidationState,
+            _: &Bound<'py, PyAny>,
+        ) -> ValResult<FieldValue> {
+            Ok(FieldValue::new_raw(42i64))
+        }
+
+        fn validate_json(
+            &self,
+            _: &mut ValidationState,
+            jiter: &mut Jiter,
+        ) -> ValResult<FieldValue> {
+            jiter.next_skip()?;
+            Ok(FieldValue::new_raw(42i64))
+        }
+    }
+
+    #[test]
+    fn custom_validator() {
+        register_validator("always-42", |_, _| Ok(Box::new(Always42)));
+        run_python(
+            r#"
+            v = model_validator(
+                field('a', {'type': 'always-42'}),
+                field('b', {'type': 'int'}),
+            )
+            m = v.validate_python({'a': 'anything', 'b': 1})
+            assert (m.a, m.b) == (42, 1)
+            m = v.validate_json(b'{"a": {"nested": [1, 2]}, "b": 2}')
+            assert (m.a, m.b) == (42, 2)
+            assert m.__pydantic_model_data__.model_dump_json() == '{"a":42,"b":2}'
+            assert errors(v.validate_json, b'{"b": 3}') == [('missing', ('a',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn unknown_schema_type() {
+        run_python(
+            r#"
+            try:
+                model_validator(field('a', {'type': 'never-registered'}))
+            except ValueError as e:
+                assert str(e) == 'Unknown validator: never-registered', e
+            else:
+                raise AssertionError('expected an unknown schema type to raise')
+            "#,
+        );
+    }
+
+    #[test]
+    fn float() {
+        run_python(
+            r#"
+            import json
+            v = model_validator(field('f', {'type': 'float'}))
+            for value in [1.5, 2, '2.5', ' -1e3 ']:
+                assert v.validate_python({'f': value}).f == float(value), value
+            assert type(v.validate_python({'f': 2}).f) is float
+            assert v.validate_json(b'{"f": 3}').f == 3.0
+            assert v.validate_json(b'{"f": "1.25"}').f == 1.25
+            assert v.validate_strings({'f': '0.5'}).f == 0.5
+            for value in ['abc', None, [1.0]]:
+                assert errors(v.validate_python, {'f': value}) == [('float_type', ('f',))], value
+            assert errors(v.validate_json, b'{"f": "abc"}') == [('float_type', ('f',))]
+            m = v.validate_json(b'{"f": 1e3}')
+            assert json.loads(m.__pydantic_model_data__.model_dump_json()) == {'f': 1000.0}
+            v = model_validator(field('f', {'type': 'float'}), strict=True)
+            assert v.validate_python({'f': 2}).f == 2.0
+            for value in ['1.5', True]:
+                assert errors(v.validate_python, {'f': value}) == [('float_type', ('f',))], value
+            assert errors(v.validate_json, b'{"f": "1.5"}') == [('float_type', ('f',))]
+            "#,
+        );
+    }
+
+    #[test]
+    fn float_bounds() {
+        run_python(
+            r#"
+            v = model_validator(field('f', {'type': 'float', 'gt': 0, 'le': 1.5}))
+            assert v.validate_python({'f': 0.5}).f == 0.5
+            assert v.validate_json(b'{"f": 1}').f == 1.0
+            # the exclusive bound fails at the limit, the inclusive one passes
+            assert errors(v.validate_python, {'f': 0.0}) == [('greater_than', ('f',))]
+            assert v.validate_python({'f': 1.5}).f == 1.5
+            assert errors(v.validate_json, b'{"f": 1.75}') == [('less_than_equal', ('f',))]
+            # NaN fails every bound
+            for value in [float('nan'), 'nan']:
+                assert errors(v.validate_python, {'f': value}) == [('greater_than', ('f',))], value
+            try:
+                v.validate_python({'f': -1})
+            except ValidationError as e:
+                error = e.errors()[0]
+                assert error['msg'] == 'Input should be greater than 0', error
+                assert error['ctx'] == {'gt': 0.0}, error
+            else:
+                raise AssertionError('expected a ValidationError')
+            for key in ['gt', 'ge', 'lt', 'le']:
+                try:
+                    model_validator(field('f', {'type': 'float', key: float('nan')}))
+                except ValueError as e:
+                    assert str(e) == f'{key} must not be NaN', e
+                else:
+                    raise AssertionError('expected a NaN bound to raise')
+            "#,
+        );
+    }
+}